@@ -2,7 +2,8 @@ extern crate piston_window;
 use chrono::prelude::*;
 use piston_window::*;
 use rtlib::render::{
-    ConsoleRenderer, NffParser, PixelBuffer, RenderConfig, Renderer, SceneGenerator,
+    ConsoleRenderer, NffParser, ObjParser, PixelBuffer, RenderConfig, Renderer, SceneFileParser,
+    SceneGenerator,
 };
 use scenes::*;
 use std::sync::{Arc, Mutex};
@@ -27,6 +28,16 @@ enum SceneType {
         #[structopt(help = "Name of file in nff/ subdirectory")]
         filename: String,
     },
+    #[structopt(about = "Parse Obj File")]
+    Obj {
+        #[structopt(help = "Name of file in obj/ subdirectory")]
+        filename: String,
+    },
+    #[structopt(about = "Parse Scene File")]
+    Scene {
+        #[structopt(help = "Name of .json or .toml file in scenefiles/ subdirectory")]
+        filename: String,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -54,6 +65,12 @@ struct MainOptions {
 
     #[structopt(short = "n", long = "no-save")]
     no_save: bool,
+
+    #[structopt(
+        long = "write-scene",
+        help = "Write the scene to this .json or .toml file instead of rendering it"
+    )]
+    write_scene: Option<String>,
     // #[structopt(short = "p", long = "profile")]
     // profile: bool,
 }
@@ -124,14 +141,42 @@ fn main() {
     );
 
     let scene_generator = match opts.scene_type {
-        SceneType::CornellBox => CornellBoxScene::new(&render_config),
-        SceneType::Spheres => ManySpheresScene::new(&render_config),
+        SceneType::CornellBox => Ok(CornellBoxScene::new(&render_config)),
+        SceneType::Spheres => Ok(ManySpheresScene::new(&render_config)),
         SceneType::Nff { filename } => {
             std::fs::create_dir_all("./nff").unwrap();
             let nff_file_path = format!("./nff/{}.nff", filename);
             NffParser::new(&nff_file_path, &render_config)
         }
+        SceneType::Obj { filename } => {
+            std::fs::create_dir_all("./obj").unwrap();
+            let obj_file_path = format!("./obj/{}.obj", filename);
+            ObjParser::new(&obj_file_path, &render_config)
+        }
+        SceneType::Scene { filename } => {
+            std::fs::create_dir_all("./scenefiles").unwrap();
+            let scene_file_path = format!("./scenefiles/{}", filename);
+            SceneFileParser::new(&scene_file_path, &render_config)
+        }
     };
+    let scene_generator = match scene_generator {
+        Ok(scene_generator) => scene_generator,
+        Err(err) => {
+            eprintln!("error loading scene: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(write_scene_path) = opts.write_scene {
+        match scene_generator.write_scene_file(&write_scene_path) {
+            Ok(()) => println!("Wrote scene to {}", write_scene_path),
+            Err(err) => {
+                eprintln!("error writing scene: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     let render_config = Arc::new(scene_generator.get_render_config());
 
@@ -1,8 +1,7 @@
 use crate::cameras::Camera;
-use crate::get_random_in_unit_sphere;
 use crate::render::Ray;
 use crate::stats::RenderStats;
-use crate::{InnerSpace, Vector3};
+use crate::{get_random_in_unit_sphere, next_rand_f32, InnerSpace, Vector3};
 
 pub struct NormalCamera {
     origin: Vector3<f32>,
@@ -11,7 +10,11 @@ pub struct NormalCamera {
     vertical: Vector3<f32>,
     u: Vector3<f32>,
     v: Vector3<f32>,
+    w: Vector3<f32>,
     lens_radius: f32,
+    near_clip: f32,
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
 impl NormalCamera {
@@ -46,9 +49,28 @@ impl NormalCamera {
             vertical,
             u,
             v,
+            w,
             lens_radius,
+            near_clip: 0.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
+
+    /// Starts camera rays at this distance in front of the camera (the NFF "hither"
+    /// plane), so geometry closer than that is not seen.
+    pub fn with_near_clip(mut self, near_clip: f32) -> NormalCamera {
+        self.near_clip = near_clip;
+        self
+    }
+
+    /// Gives each ray a time picked uniformly between shutter open and close, so
+    /// anything moving during that interval is motion blurred.
+    pub fn with_shutter(mut self, shutter_open: f32, shutter_close: f32) -> NormalCamera {
+        self.shutter_open = shutter_open;
+        self.shutter_close = shutter_close;
+        self
+    }
 }
 
 impl Camera for NormalCamera {
@@ -56,12 +78,21 @@ impl Camera for NormalCamera {
         let rd = self.lens_radius * get_random_in_unit_sphere();
         let offset = (self.u * rd.x) + (self.v * rd.y);
         stat.camera_ray_create();
+        let time = self.shutter_open + (next_rand_f32() * (self.shutter_close - self.shutter_open));
+
+        let origin = self.origin + offset;
+        let direction =
+            self.lower_left_corner + (s * self.horizontal) + (t * self.vertical) - origin;
+        if self.near_clip <= 0.0 {
+            return Ray::new_at_time(origin, direction, time, stat);
+        }
 
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + (s * self.horizontal) + (t * self.vertical)
-                - self.origin
-                - offset,
+        // move the origin forward to where the ray crosses the near plane
+        let distance_along_view = -direction.dot(self.w);
+        Ray::new_at_time(
+            origin + (direction * (self.near_clip / distance_along_view)),
+            direction,
+            time,
             stat,
         )
     }
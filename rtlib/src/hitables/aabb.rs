@@ -15,37 +15,54 @@ impl fmt::Display for AABB {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::next_rand_f32;
-//     use test::Bencher;
-
-//     #[bench]
-//     fn bench_render(b: &mut Bencher) {
-//         let ab = AABB::new(
-//             vec3(next_rand_f32(), next_rand_f32(), next_rand_f32()),
-//             vec3(next_rand_f32(), next_rand_f32(), next_rand_f32()),
-//         );
-//         b.iter(|| {
-//             ab.hit(
-//                 &Ray::new(
-//                     vec3(next_rand_f32(), next_rand_f32(), next_rand_f32()),
-//                     vec3(next_rand_f32(), next_rand_f32(), next_rand_f32()),
-//                 ),
-//                 0.0,
-//                 1.0,
-//             );
-//         });
-//     }
-// }
+// Narrows [t0, t1] to where the ray is between the two planes of one axis.  Every
+// operation is a min/max, and f32::min/max ignore a NaN operand.  A NaN shows up when
+// the ray lies in one of the planes (0 * inf), and ignoring it leaves the interval
+// untouched, so rays grazing a face count as hits.
+#[inline]
+fn clip_slab(min: f32, max: f32, origin: f32, inv_dir: f32, t0: f32, t1: f32) -> (f32, f32) {
+    let near = (min - origin) * inv_dir;
+    let far = (max - origin) * inv_dir;
+    let (near, far) = if inv_dir.is_sign_negative() {
+        (far, near)
+    } else {
+        (near, far)
+    };
+    (t0.max(near), t1.min(far))
+}
+
+/// Slab test of the box between min and max against the part of the ray between t_min
+/// and t_max.  Uses the ray's precomputed inverse direction, so axis-parallel rays
+/// (infinite inverse components) need no special casing.
+#[inline]
+pub(crate) fn hit_slabs(
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> bool {
+    clip_slabs(min, max, ray, t_min, t_max).is_some()
+}
 
+/// The part of [t_min, t_max] where the ray is inside the box, if any.
 #[inline]
-fn swap_if_first_greater(a: f32, b: f32) -> (f32, f32) {
-    if a > b {
-        (b, a)
+pub(crate) fn clip_slabs(
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32)> {
+    let origin = ray.get_origin();
+    let inv_dir = ray.get_inverse_direction();
+    let (t0, t1) = clip_slab(min.x, max.x, origin.x, inv_dir.x, t_min, t_max);
+    let (t0, t1) = clip_slab(min.y, max.y, origin.y, inv_dir.y, t0, t1);
+    let (t0, t1) = clip_slab(min.z, max.z, origin.z, inv_dir.z, t0, t1);
+    if t0 <= t1 {
+        Some((t0, t1))
     } else {
-        (a, b)
+        None
     }
 }
 
@@ -58,42 +75,10 @@ impl AABB {
         AABB::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0))
     }
 
-    pub fn hit(&self, ray: &Ray, _t_min: f32, _t_max: f32, stat: &mut RenderStats) -> bool {
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
         info!("aabb::hit()");
         stat.aabb_hit();
-
-        let (mut txmin, mut txmax) = swap_if_first_greater(
-            (self.min.x - ray.get_origin().x) / ray.get_direction().x,
-            (self.max.x - ray.get_origin().x) / ray.get_direction().x,
-        );
-
-        let (tymin, tymax) = swap_if_first_greater(
-            (self.min.y - ray.get_origin().y) / ray.get_direction().y,
-            (self.max.y - ray.get_origin().y) / ray.get_direction().y,
-        );
-
-        if txmin > tymax || tymin > txmax {
-            return false;
-        }
-
-        if tymin > txmin {
-            txmin = tymin;
-        }
-
-        if tymax < txmax {
-            txmax = tymax;
-        }
-
-        let (tzmin, tzmax) = swap_if_first_greater(
-            (self.min.z - ray.get_origin().z) / ray.get_direction().z,
-            (self.max.z - ray.get_origin().z) / ray.get_direction().z,
-        );
-
-        if txmin > tzmax || tzmin > txmax {
-            return false;
-        }
-
-        true
+        hit_slabs(self.min, self.max, ray, t_min, t_max)
     }
 
     pub fn get_surrounding_box(&self, other: Arc<Box<AABB>>) -> Arc<Box<AABB>> {
@@ -112,3 +97,159 @@ impl AABB {
         AABB::new(small, big)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Arc<Box<AABB>> {
+        AABB::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0))
+    }
+
+    fn hits(origin: Vector3<f32>, direction: Vector3<f32>, t_min: f32, t_max: f32) -> bool {
+        let mut stat = RenderStats::default();
+        let ray = Ray::new(origin, direction, &mut stat);
+        unit_box().hit(&ray, t_min, t_max, &mut stat)
+    }
+
+    #[test]
+    fn hits_box_in_front_of_ray() {
+        assert!(hits(
+            vec3(0.0, 0.0, -5.0),
+            vec3(0.0, 0.0, 1.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(hits(
+            vec3(-5.0, -5.0, -5.0),
+            vec3(1.0, 1.0, 1.0),
+            0.0,
+            f32::MAX
+        ));
+    }
+
+    #[test]
+    fn misses_box_beside_ray() {
+        assert!(!hits(
+            vec3(0.0, 3.0, -5.0),
+            vec3(0.0, 0.0, 1.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(!hits(
+            vec3(-5.0, 0.0, -5.0),
+            vec3(1.0, 0.0, 0.2),
+            0.0,
+            f32::MAX
+        ));
+    }
+
+    #[test]
+    fn culls_box_behind_ray_origin() {
+        assert!(!hits(
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 1.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(hits(
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, -1.0),
+            0.0,
+            f32::MAX
+        ));
+    }
+
+    #[test]
+    fn culls_box_outside_interval() {
+        // the box spans t = 4..6 along this ray
+        let origin = vec3(0.0, 0.0, -5.0);
+        let direction = vec3(0.0, 0.0, 1.0);
+        assert!(!hits(origin, direction, 0.0, 3.9));
+        assert!(!hits(origin, direction, 6.1, f32::MAX));
+        assert!(hits(origin, direction, 0.0, 4.1));
+        assert!(hits(origin, direction, 5.9, 100.0));
+    }
+
+    #[test]
+    fn hits_from_inside_box() {
+        assert!(hits(
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.3, -0.2, 1.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(hits(vec3(0.5, 0.5, 0.5), vec3(-1.0, 0.0, 0.0), 0.001, 0.01));
+    }
+
+    #[test]
+    fn handles_axis_parallel_rays() {
+        for direction in [
+            vec3(1.0, 0.0, 0.0),
+            vec3(-1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, -1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, -1.0),
+        ] {
+            let origin = direction * -5.0;
+            assert!(hits(origin, direction, 0.0, f32::MAX));
+            assert!(hits(origin + vec3(0.5, 0.5, 0.5), direction, 0.0, f32::MAX));
+            assert!(!hits(
+                origin + vec3(2.0, 2.0, 2.0),
+                direction,
+                0.0,
+                f32::MAX
+            ));
+        }
+    }
+
+    #[test]
+    fn handles_negative_zero_direction() {
+        assert!(hits(
+            vec3(-5.0, 0.0, 0.0),
+            vec3(1.0, -0.0, -0.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(!hits(
+            vec3(-5.0, 2.0, 0.0),
+            vec3(1.0, -0.0, -0.0),
+            0.0,
+            f32::MAX
+        ));
+    }
+
+    #[test]
+    fn counts_ray_in_face_plane_as_hit() {
+        // origin.y == max.y with no y motion gives 0 * inf = NaN for that slab
+        assert!(hits(
+            vec3(-5.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            0.0,
+            f32::MAX
+        ));
+        assert!(hits(
+            vec3(-1.0, -1.0, -5.0),
+            vec3(0.0, 0.0, 1.0),
+            0.0,
+            f32::MAX
+        ));
+    }
+
+    #[test]
+    fn handles_zero_direction() {
+        let zero = vec3(0.0, 0.0, 0.0);
+        assert!(hits(vec3(0.5, 0.5, 0.5), zero, 0.0, f32::MAX));
+        assert!(!hits(vec3(2.0, 0.0, 0.0), zero, 0.0, f32::MAX));
+    }
+
+    #[test]
+    fn ignores_nan_direction_component() {
+        // the x slab clips to NaN and is ignored, so only y and z decide
+        let nan = vec3(f32::NAN, 0.0, 1.0);
+        assert!(hits(vec3(0.0, 0.0, -5.0), nan, 0.0, f32::MAX));
+        assert!(hits(vec3(7.0, 0.0, -5.0), nan, 0.0, f32::MAX));
+        assert!(!hits(vec3(0.0, 2.0, -5.0), nan, 0.0, f32::MAX));
+    }
+}
@@ -0,0 +1,452 @@
+use crate::hitables::{FlatBvh, FlatBvhNode, HitableList, ThreadHitable, AABB};
+use crate::{vec3, Vector3};
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::fmt;
+
+// Relative cost of visiting an interior node compared to intersecting a primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+const INTERSECTION_COST: f32 = 1.0;
+
+// Below this many primitives the two halves are built on the current thread.
+const PARALLEL_BUILD_THRESHOLD: usize = 256;
+
+// Past this depth nodes are split at the median, which bounds the total depth so the
+// fixed size traversal stack in FlatBvh can't overflow.
+const MAX_SAH_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhStrategy {
+    /// No acceleration structure, every hitable is tested in turn.
+    List,
+    /// Splits at the median centroid of the longest axis.
+    Median,
+    /// Binned surface area heuristic.
+    Sah,
+}
+
+#[derive(Clone, Debug, Serialize, Default)]
+pub struct BvhBuildStats {
+    pub primitive_count: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub sah_cost: f32,
+}
+
+impl fmt::Display for BvhBuildStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "primitives: {}, nodes: {}, leaves: {}, depth: {}, sah cost: {:.2}",
+            self.primitive_count.to_formatted_string(&Locale::en),
+            self.node_count.to_formatted_string(&Locale::en),
+            self.leaf_count.to_formatted_string(&Locale::en),
+            self.max_depth,
+            self.sah_cost
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BvhBuilder {
+    strategy: BvhStrategy,
+    max_leaf_size: usize,
+    bin_count: usize,
+}
+
+impl Default for BvhBuilder {
+    fn default() -> Self {
+        BvhBuilder::new(BvhStrategy::Sah)
+    }
+}
+
+struct PrimitiveInfo {
+    hitable: ThreadHitable,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    centroid: Vector3<f32>,
+}
+
+#[derive(Clone, Copy)]
+struct Bounds {
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+}
+
+impl Bounds {
+    fn empty() -> Bounds {
+        Bounds {
+            min: vec3(f32::MAX, f32::MAX, f32::MAX),
+            max: vec3(-f32::MAX, -f32::MAX, -f32::MAX),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min.x > self.max.x
+    }
+
+    fn grow(&mut self, min: Vector3<f32>, max: Vector3<f32>) {
+        self.min = vec3(
+            self.min.x.min(min.x),
+            self.min.y.min(min.y),
+            self.min.z.min(min.z),
+        );
+        self.max = vec3(
+            self.max.x.max(max.x),
+            self.max.y.max(max.y),
+            self.max.z.max(max.z),
+        );
+    }
+
+    fn union(&self, other: &Bounds) -> Bounds {
+        let mut bounds = *self;
+        bounds.grow(other.min, other.max);
+        bounds
+    }
+
+    fn get_surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.max - self.min;
+        2.0 * ((d.x * d.y) + (d.y * d.z) + (d.z * d.x))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Bounds,
+    count: usize,
+}
+
+struct BuiltNode {
+    bounds: Bounds,
+    stats: BvhBuildStats,
+    kind: BuiltNodeKind,
+}
+
+enum BuiltNodeKind {
+    Leaf {
+        first: usize,
+        count: usize,
+    },
+    Interior {
+        axis: usize,
+        left: Box<BuiltNode>,
+        right: Box<BuiltNode>,
+    },
+}
+
+// Appends the subtree depth first, so the left child always directly follows its parent.
+fn flatten(node: &BuiltNode, nodes: &mut Vec<FlatBvhNode>) {
+    let node_index = nodes.len();
+    nodes.push(FlatBvhNode {
+        min: node.bounds.min,
+        max: node.bounds.max,
+        offset: 0,
+        count: 0,
+        axis: 0,
+    });
+
+    match &node.kind {
+        BuiltNodeKind::Leaf { first, count } => {
+            nodes[node_index].offset = *first as u32;
+            nodes[node_index].count = *count as u32;
+        }
+        BuiltNodeKind::Interior { axis, left, right } => {
+            flatten(left, nodes);
+            nodes[node_index].offset = nodes.len() as u32;
+            nodes[node_index].axis = *axis as u8;
+            flatten(right, nodes);
+        }
+    }
+}
+
+fn get_component(v: Vector3<f32>, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+fn get_bounds(infos: &[PrimitiveInfo]) -> (Bounds, Bounds) {
+    let mut bounds = Bounds::empty();
+    let mut centroid_bounds = Bounds::empty();
+    for info in infos {
+        bounds.grow(info.min, info.max);
+        centroid_bounds.grow(info.centroid, info.centroid);
+    }
+    (bounds, centroid_bounds)
+}
+
+// Moves everything matching the predicate to the front.  The sort is stable so the
+// resulting tree doesn't depend on anything but the input order.
+fn partition(infos: &mut [PrimitiveInfo], goes_left: impl Fn(&PrimitiveInfo) -> bool) -> usize {
+    infos.sort_by_key(|info| !goes_left(info));
+    infos.iter().take_while(|info| goes_left(info)).count()
+}
+
+impl BvhBuilder {
+    pub fn new(strategy: BvhStrategy) -> BvhBuilder {
+        BvhBuilder {
+            strategy,
+            max_leaf_size: 4,
+            bin_count: 16,
+        }
+    }
+
+    /// The most primitives a leaf may hold.  Leaves can end up smaller when splitting is
+    /// cheaper.
+    pub fn with_max_leaf_size(mut self, max_leaf_size: usize) -> BvhBuilder {
+        self.max_leaf_size = max_leaf_size.max(1);
+        self
+    }
+
+    /// Number of buckets along each axis the SAH strategy evaluates splits between.
+    pub fn with_bin_count(mut self, bin_count: usize) -> BvhBuilder {
+        self.bin_count = bin_count.max(2);
+        self
+    }
+
+    pub fn get_strategy(&self) -> BvhStrategy {
+        self.strategy
+    }
+
+    /// Builds the acceleration structure over the hitables.  The stats are None when the
+    /// strategy doesn't build a tree.
+    pub fn build(
+        &self,
+        hitables: &[ThreadHitable],
+        time_0: f32,
+        time_1: f32,
+    ) -> (ThreadHitable, Option<BvhBuildStats>) {
+        if self.strategy == BvhStrategy::List || hitables.is_empty() {
+            return (HitableList::from_vec(hitables.to_vec()), None);
+        }
+
+        let mut infos: Vec<PrimitiveInfo> = hitables
+            .iter()
+            .map(|hitable| {
+                let bounding_box = hitable.get_bounding_box(time_0, time_1);
+                PrimitiveInfo {
+                    hitable: hitable.clone(),
+                    min: bounding_box.min,
+                    max: bounding_box.max,
+                    centroid: (bounding_box.min + bounding_box.max) * 0.5,
+                }
+            })
+            .collect();
+
+        let built = self.build_node(&mut infos, 0, 1);
+        let mut stats = built.stats.clone();
+        let root_area = built.bounds.get_surface_area();
+        stats.sah_cost = if root_area > 0.0 {
+            stats.sah_cost / root_area
+        } else {
+            0.0
+        };
+
+        let mut nodes = Vec::with_capacity(stats.node_count);
+        flatten(&built, &mut nodes);
+        let primitives = infos.into_iter().map(|info| info.hitable).collect();
+        let bounding_box = AABB::new(built.bounds.min, built.bounds.max);
+
+        (FlatBvh::new(nodes, primitives, bounding_box), Some(stats))
+    }
+
+    // `first` is where infos starts in the full primitive array, so leaves can refer to
+    // their primitives by range once everything has been reordered.
+    fn build_node(&self, infos: &mut [PrimitiveInfo], first: usize, depth: usize) -> BuiltNode {
+        let (bounds, centroid_bounds) = get_bounds(infos);
+
+        if infos.len() == 1 {
+            return self.build_leaf(infos, first, bounds, depth);
+        }
+
+        let (axis, mid) = if self.strategy == BvhStrategy::Sah && depth < MAX_SAH_DEPTH {
+            match self.find_sah_split(infos, &bounds, &centroid_bounds) {
+                Some(split) => split,
+                None if infos.len() <= self.max_leaf_size => {
+                    return self.build_leaf(infos, first, bounds, depth);
+                }
+                None => self.split_at_median(infos, &centroid_bounds),
+            }
+        } else {
+            if infos.len() <= self.max_leaf_size {
+                return self.build_leaf(infos, first, bounds, depth);
+            }
+            self.split_at_median(infos, &centroid_bounds)
+        };
+
+        let count = infos.len();
+        let (left_infos, right_infos) = infos.split_at_mut(mid);
+        let (left, right) = if count >= PARALLEL_BUILD_THRESHOLD {
+            rayon::join(
+                || self.build_node(left_infos, first, depth + 1),
+                || self.build_node(right_infos, first + mid, depth + 1),
+            )
+        } else {
+            (
+                self.build_node(left_infos, first, depth + 1),
+                self.build_node(right_infos, first + mid, depth + 1),
+            )
+        };
+
+        let node_bounds = left.bounds.union(&right.bounds);
+        let stats = BvhBuildStats {
+            primitive_count: left.stats.primitive_count + right.stats.primitive_count,
+            node_count: 1 + left.stats.node_count + right.stats.node_count,
+            leaf_count: left.stats.leaf_count + right.stats.leaf_count,
+            max_depth: left.stats.max_depth.max(right.stats.max_depth),
+            sah_cost: (TRAVERSAL_COST * node_bounds.get_surface_area())
+                + left.stats.sah_cost
+                + right.stats.sah_cost,
+        };
+
+        BuiltNode {
+            bounds: node_bounds,
+            stats,
+            kind: BuiltNodeKind::Interior {
+                axis,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        }
+    }
+
+    fn build_leaf(
+        &self,
+        infos: &[PrimitiveInfo],
+        first: usize,
+        bounds: Bounds,
+        depth: usize,
+    ) -> BuiltNode {
+        BuiltNode {
+            bounds,
+            stats: BvhBuildStats {
+                primitive_count: infos.len(),
+                node_count: 1,
+                leaf_count: 1,
+                max_depth: depth,
+                sah_cost: INTERSECTION_COST * infos.len() as f32 * bounds.get_surface_area(),
+            },
+            kind: BuiltNodeKind::Leaf {
+                first,
+                count: infos.len(),
+            },
+        }
+    }
+
+    // Returns the split axis and where to split the (reordered) primitives, or None when
+    // a leaf is cheaper than any split.
+    fn find_sah_split(
+        &self,
+        infos: &mut [PrimitiveInfo],
+        bounds: &Bounds,
+        centroid_bounds: &Bounds,
+    ) -> Option<(usize, usize)> {
+        let bin_count = self.bin_count;
+        let mut best: Option<(f32, usize, usize)> = None;
+
+        for axis in 0..3 {
+            let axis_min = get_component(centroid_bounds.min, axis);
+            let extent = get_component(centroid_bounds.max, axis) - axis_min;
+            if extent <= 0.0 {
+                continue;
+            }
+
+            let get_bin = |info: &PrimitiveInfo| -> usize {
+                let offset = (get_component(info.centroid, axis) - axis_min) / extent;
+                ((offset * bin_count as f32) as usize).min(bin_count - 1)
+            };
+
+            let mut bins = vec![
+                Bin {
+                    bounds: Bounds::empty(),
+                    count: 0,
+                };
+                bin_count
+            ];
+            for info in infos.iter() {
+                let bin = &mut bins[get_bin(info)];
+                bin.bounds.grow(info.min, info.max);
+                bin.count += 1;
+            }
+
+            // Sweep from the right so each split's right side cost is a lookup.
+            let mut right_areas = vec![0.0; bin_count];
+            let mut right_counts = vec![0; bin_count];
+            let mut right_bounds = Bounds::empty();
+            let mut right_count = 0;
+            for i in (1..bin_count).rev() {
+                right_bounds = right_bounds.union(&bins[i].bounds);
+                right_count += bins[i].count;
+                right_areas[i] = right_bounds.get_surface_area();
+                right_counts[i] = right_count;
+            }
+
+            let mut left_bounds = Bounds::empty();
+            let mut left_count = 0;
+            for split in 1..bin_count {
+                left_bounds = left_bounds.union(&bins[split - 1].bounds);
+                left_count += bins[split - 1].count;
+                if left_count == 0 || right_counts[split] == 0 {
+                    continue;
+                }
+                let cost = (left_count as f32 * left_bounds.get_surface_area())
+                    + (right_counts[split] as f32 * right_areas[split]);
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        let (best_cost, axis, split) = best?;
+
+        let area = bounds.get_surface_area();
+        let split_cost = if area > 0.0 {
+            TRAVERSAL_COST + (INTERSECTION_COST * best_cost / area)
+        } else {
+            TRAVERSAL_COST
+        };
+        let leaf_cost = INTERSECTION_COST * infos.len() as f32;
+        if infos.len() <= self.max_leaf_size && leaf_cost <= split_cost {
+            return None;
+        }
+
+        let axis_min = get_component(centroid_bounds.min, axis);
+        let extent = get_component(centroid_bounds.max, axis) - axis_min;
+        let mid = partition(infos, |info| {
+            let offset = (get_component(info.centroid, axis) - axis_min) / extent;
+            ((offset * bin_count as f32) as usize).min(bin_count - 1) < split
+        });
+
+        if mid == 0 || mid == infos.len() {
+            return None;
+        }
+        Some((axis, mid))
+    }
+
+    fn split_at_median(
+        &self,
+        infos: &mut [PrimitiveInfo],
+        centroid_bounds: &Bounds,
+    ) -> (usize, usize) {
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+
+        // A stable sort keeps primitives with equal centroids in their input order.
+        infos.sort_by(|a, b| {
+            get_component(a.centroid, axis).total_cmp(&get_component(b.centroid, axis))
+        });
+        (axis, infos.len() / 2)
+    }
+}
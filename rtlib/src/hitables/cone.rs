@@ -0,0 +1,235 @@
+use crate::hitables::cylinder::{get_disks_bounding_box, get_side_uv, hit_cap, random_in_disk};
+use crate::hitables::{get_area_pdf_value, get_phi, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::next_rand_f32;
+use crate::pdfs::OrthoNormalBase;
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{vec3, InnerSpace, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+// Truncated cone between two end caps with an arbitrary axis.  Equal radii give a
+// cylinder and a zero radius at one end gives a pointed cone.  When `capped` is
+// false the ends are left open (the way NFF describes cones).  `phi_max` (degrees)
+// cuts it down to a wedge, measured around the axis from the frame's u direction.
+pub struct Cone {
+    base: Vector3<f32>,
+    base_radius: f32,
+    apex: Vector3<f32>,
+    apex_radius: f32,
+    capped: bool,
+    phi_max: f32,
+    height: f32,
+    // change in radius per unit of height
+    slope: f32,
+    uvw: OrthoNormalBase,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Cone {
+    pub fn new(
+        base: Vector3<f32>,
+        base_radius: f32,
+        apex: Vector3<f32>,
+        apex_radius: f32,
+        capped: bool,
+        phi_max: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let axis = apex - base;
+        let height = axis.magnitude();
+        let uvw = OrthoNormalBase::from_w(axis);
+
+        let bounding_box = get_disks_bounding_box(base, base_radius, apex, apex_radius, uvw.w());
+
+        Arc::new(Box::new(Cone {
+            base,
+            base_radius,
+            apex,
+            apex_radius,
+            capped,
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians(),
+            height,
+            slope: (apex_radius - base_radius) / height,
+            uvw,
+            material_id,
+            bounding_box,
+        }))
+    }
+
+    fn get_side_area(&self) -> f32 {
+        let radius_change = self.apex_radius - self.base_radius;
+        let slant = ((self.height * self.height) + (radius_change * radius_change)).sqrt();
+        0.5 * self.phi_max * (self.base_radius + self.apex_radius) * slant
+    }
+
+    pub fn get_area(&self) -> f32 {
+        let side_area = self.get_side_area();
+        if self.capped {
+            side_area
+                + (0.5
+                    * self.phi_max
+                    * ((self.base_radius * self.base_radius)
+                        + (self.apex_radius * self.apex_radius)))
+        } else {
+            side_area
+        }
+    }
+
+    fn hit_side(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // work in the cone's frame, with the base at the origin and the axis along z
+        let oc = ray.get_origin() - self.base;
+        let o = vec3(
+            oc.dot(self.uvw.u()),
+            oc.dot(self.uvw.v()),
+            oc.dot(self.uvw.w()),
+        );
+        let d = ray.get_direction();
+        let d = vec3(
+            d.dot(self.uvw.u()),
+            d.dot(self.uvw.v()),
+            d.dot(self.uvw.w()),
+        );
+
+        // x^2 + y^2 = (r0 + slope * z)^2
+        let r0 = self.base_radius + (self.slope * o.z);
+        let r1 = self.slope * d.z;
+        let a = (d.x * d.x) + (d.y * d.y) - (r1 * r1);
+        let b = 2.0 * ((o.x * d.x) + (o.y * d.y) - (r0 * r1));
+        let c = (o.x * o.x) + (o.y * o.y) - (r0 * r0);
+
+        let roots = if a.abs() < 1e-8 {
+            if b.abs() < 1e-8 {
+                return None;
+            }
+            [-c / b, f32::MAX]
+        } else {
+            let discriminant = (b * b) - (4.0 * a * c);
+            if discriminant < 0.0 {
+                return None;
+            }
+            let disc_sqrt = discriminant.sqrt();
+            let t0 = (-b - disc_sqrt) / (2.0 * a);
+            let t1 = (-b + disc_sqrt) / (2.0 * a);
+            [t0.min(t1), t0.max(t1)]
+        };
+
+        for t in roots.iter() {
+            if *t <= t_min || *t >= t_max {
+                continue;
+            }
+            let local = o + (d * *t);
+            if local.z < 0.0 || local.z > self.height {
+                continue;
+            }
+            if get_phi(local.x, local.y) > self.phi_max {
+                continue;
+            }
+
+            let radial = vec3(local.x, local.y, 0.0).normalize();
+            let local_normal = vec3(radial.x, radial.y, -self.slope).normalize();
+            return Some(HitRecord::new(
+                *t,
+                ray.get_point_at_parameter(*t),
+                self.uvw.local(local_normal),
+                0.0, // todo: distance_squared
+                self.material_id,
+                get_side_uv(local, self.height, self.phi_max),
+            ));
+        }
+
+        None
+    }
+
+    fn random_point(&self) -> Vector3<f32> {
+        let side_area = self.get_side_area();
+        let base_area = 0.5 * self.phi_max * self.base_radius * self.base_radius;
+        let pick = next_rand_f32() * self.get_area();
+
+        if pick < side_area || !self.capped {
+            // the circumference grows with the radius, so pick the radius with density
+            // proportional to it and find the height that has it
+            let (r0, r1) = (self.base_radius, self.apex_radius);
+            let z = if (r1 - r0).abs() < 1e-6 {
+                self.height * next_rand_f32()
+            } else {
+                let r = ((r0 * r0) + (next_rand_f32() * ((r1 * r1) - (r0 * r0)))).sqrt();
+                (r - r0) / self.slope
+            };
+            let r = r0 + (self.slope * z);
+            let phi = self.phi_max * next_rand_f32();
+            self.base + self.uvw.local(vec3(r * phi.cos(), r * phi.sin(), z))
+        } else if pick < side_area + base_area {
+            self.base
+                + self
+                    .uvw
+                    .local(random_in_disk(self.base_radius, self.phi_max))
+        } else {
+            self.apex
+                + self
+                    .uvw
+                    .local(random_in_disk(self.apex_radius, self.phi_max))
+        }
+    }
+}
+
+impl fmt::Display for Cone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Cone(base: {:?}, base_radius: {}, apex: {:?}, apex_radius: {}, capped: {}, phi_max: {})]",
+            self.base,
+            self.base_radius,
+            self.apex,
+            self.apex_radius,
+            self.capped,
+            self.phi_max.to_degrees()
+        )
+    }
+}
+
+impl Hitable for Cone {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("cone::hit()");
+        stat.cone_hit();
+
+        let mut closest = self.hit_side(ray, t_min, t_max);
+        if self.capped {
+            let caps = [
+                (self.base, self.base_radius, -self.uvw.w()),
+                (self.apex, self.apex_radius, self.uvw.w()),
+            ];
+            for (center, radius, normal) in caps.iter() {
+                let closest_t = closest.map_or(t_max, |hr| hr.get_t());
+                if let Some(hr) = hit_cap(
+                    ray,
+                    t_min,
+                    closest_t,
+                    *center,
+                    *radius,
+                    *normal,
+                    &self.uvw,
+                    self.phi_max,
+                    self.material_id,
+                ) {
+                    closest = Some(hr);
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        get_area_pdf_value(self, self.get_area(), origin, v, stat)
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        self.random_point() - origin
+    }
+}
@@ -0,0 +1,161 @@
+use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{vec3, InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// The left solid with the right one cut out of it.
+    Difference,
+}
+
+impl CsgOperation {
+    fn is_inside(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// A boolean combination of two closed solids whose normals point outwards.  The ray's
+/// hits with both are merged in order of t, tracking whether the ray is inside each
+/// one, and the hits where it enters or leaves the combination are its surface.
+/// Normals are turned to point out of the combination, so the surfaces of the right
+/// solid that bound a difference face into the hole.
+pub struct Csg {
+    operation: CsgOperation,
+    left: ThreadHitable,
+    right: ThreadHitable,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: ThreadHitable,
+        right: ThreadHitable,
+    ) -> ThreadHitable {
+        let left_box = left.get_bounding_box(0.0, 1.0);
+        let right_box = right.get_bounding_box(0.0, 1.0);
+        let bounding_box = match operation {
+            CsgOperation::Union => left_box.get_surrounding_box(right_box),
+            CsgOperation::Intersection => AABB::new(
+                vec3(
+                    left_box.min.x.max(right_box.min.x),
+                    left_box.min.y.max(right_box.min.y),
+                    left_box.min.z.max(right_box.min.z),
+                ),
+                vec3(
+                    left_box.max.x.min(right_box.max.x),
+                    left_box.max.y.min(right_box.max.y),
+                    left_box.max.z.min(right_box.max.z),
+                ),
+            ),
+            CsgOperation::Difference => left_box,
+        };
+
+        Arc::new(Box::new(Csg {
+            operation,
+            left,
+            right,
+            bounding_box,
+        }))
+    }
+}
+
+// Whether the ray starts inside a closed solid: the first surface it crosses is then
+// one it leaves through.
+fn starts_inside(hits: &[HitRecord], direction: Vector3<f32>) -> bool {
+    hits.first()
+        .is_some_and(|hr| hr.get_normal().dot(direction) > 0.0)
+}
+
+impl fmt::Display for Csg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Csg({:?}, {}, {})]",
+            self.operation, self.left, self.right
+        )
+    }
+}
+
+impl Hitable for Csg {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("csg::hit()");
+        self.hit_all(ray, t_min, t_max, stat).into_iter().next()
+    }
+
+    fn hit_all(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Vec<HitRecord> {
+        info!("csg::hit_all()");
+        stat.csg_hit();
+
+        // Hits past t_max still tell whether the ray is inside a solid before them.
+        let direction = ray.get_direction();
+        let left_hits = self.left.hit_all(ray, t_min, f32::MAX, stat);
+        if left_hits.is_empty() && self.operation != CsgOperation::Union {
+            return vec![];
+        }
+        let right_hits = self.right.hit_all(ray, t_min, f32::MAX, stat);
+
+        let mut in_left = starts_inside(&left_hits, direction);
+        let mut in_right = starts_inside(&right_hits, direction);
+        let mut inside = self.operation.is_inside(in_left, in_right);
+
+        let mut hits = vec![];
+        let (mut l, mut r) = (0, 0);
+        while l < left_hits.len() || r < right_hits.len() {
+            let take_left =
+                r >= right_hits.len() || (l < left_hits.len() && left_hits[l].t <= right_hits[r].t);
+            let mut hr = if take_left {
+                l += 1;
+                in_left = !in_left;
+                left_hits[l - 1]
+            } else {
+                r += 1;
+                in_right = !in_right;
+                right_hits[r - 1]
+            };
+            if hr.t >= t_max {
+                break;
+            }
+
+            let now_inside = self.operation.is_inside(in_left, in_right);
+            if now_inside != inside {
+                // entering means the normal faces against the ray
+                if (hr.normal.dot(direction) < 0.0) != now_inside {
+                    hr.normal = -hr.normal;
+                }
+                hits.push(hr);
+                inside = now_inside;
+            }
+        }
+
+        hits
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
@@ -19,9 +19,9 @@ impl Cube {
             XyRect::new(p0.x, p1.x, p0.y, p1.y, p1.z, material_id),
             FlipNormals::new(XyRect::new(p0.x, p1.x, p0.y, p1.y, p0.z, material_id)),
             XzRect::new(p0.x, p1.x, p0.z, p1.z, p1.y, material_id),
-            FlipNormals::new(XzRect::new(p0.x, p1.x, p0.z, p1.z, p1.y, material_id)),
+            FlipNormals::new(XzRect::new(p0.x, p1.x, p0.z, p1.z, p0.y, material_id)),
             YzRect::new(p0.y, p1.y, p0.z, p1.z, p1.x, material_id),
-            FlipNormals::new(YzRect::new(p0.y, p1.y, p0.z, p1.z, p1.x, material_id)),
+            FlipNormals::new(YzRect::new(p0.y, p1.y, p0.z, p1.z, p0.x, material_id)),
         ]);
         Arc::new(Box::new(Cube {
             pos_min: p0,
@@ -0,0 +1,117 @@
+use crate::hitables::{get_area_pdf_value, get_phi, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::next_rand_f32;
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{vec3, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// A disk in the plane z = `height` facing +z, centered on the z axis.  A non-zero
+/// `inner_radius` makes it an annulus and `phi_max` (degrees) cuts it down to a sector.
+/// Place it with a `Transform`.
+pub struct Disk {
+    height: f32,
+    radius: f32,
+    inner_radius: f32,
+    phi_max: f32,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Disk {
+    pub fn new(
+        height: f32,
+        radius: f32,
+        inner_radius: f32,
+        phi_max: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let bounding_box = AABB::new(
+            vec3(-radius, -radius, height - 0.0001),
+            vec3(radius, radius, height + 0.0001),
+        );
+
+        Arc::new(Box::new(Disk {
+            height,
+            radius,
+            inner_radius,
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians(),
+            material_id,
+            bounding_box,
+        }))
+    }
+
+    pub fn get_area(&self) -> f32 {
+        0.5 * self.phi_max * ((self.radius * self.radius) - (self.inner_radius * self.inner_radius))
+    }
+}
+
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Disk(height: {}, radius: {}, inner_radius: {}, phi_max: {})]",
+            self.height,
+            self.radius,
+            self.inner_radius,
+            self.phi_max.to_degrees()
+        )
+    }
+}
+
+impl Hitable for Disk {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("disk::hit()");
+        stat.disk_hit();
+
+        let d = ray.get_direction();
+        if d.z == 0.0 {
+            return None;
+        }
+        let t = (self.height - ray.get_origin().z) / d.z;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        let p = ray.get_point_at_parameter(t);
+        let distance_squared = (p.x * p.x) + (p.y * p.y);
+        if distance_squared > self.radius * self.radius
+            || distance_squared < self.inner_radius * self.inner_radius
+        {
+            return None;
+        }
+        let phi = get_phi(p.x, p.y);
+        if phi > self.phi_max {
+            return None;
+        }
+
+        // v runs from the outer edge (0) to the inner one (1)
+        let distance = distance_squared.sqrt();
+        Some(HitRecord::new(
+            t,
+            p,
+            vec3(0.0, 0.0, 1.0),
+            0.0, // todo: distance_squared
+            self.material_id,
+            Point2::new(
+                phi / self.phi_max,
+                (self.radius - distance) / (self.radius - self.inner_radius),
+            ),
+        ))
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        get_area_pdf_value(self, self.get_area(), origin, v, stat)
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        let inner_sq = self.inner_radius * self.inner_radius;
+        let r = (inner_sq + (next_rand_f32() * ((self.radius * self.radius) - inner_sq))).sqrt();
+        let phi = self.phi_max * next_rand_f32();
+        vec3(r * phi.cos(), r * phi.sin(), self.height) - origin
+    }
+}
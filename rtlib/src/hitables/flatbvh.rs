@@ -0,0 +1,182 @@
+use crate::hitables::{hit_slabs, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{vec3, Vector3};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+// Both builders of flat BVHs, BvhBuilder for the scene and TriangleMesh for its faces,
+// fall back to median splits deep in the tree, which keeps the depth, and so the number
+// of pending far children, below this.
+const MAX_TRAVERSAL_DEPTH: usize = 128;
+
+// Nodes are stored depth first.  The left child of an interior node is always the next
+// node in the array and `offset` points at the right child.  For leaves `offset` is the
+// first primitive and `count` the number of primitives.
+#[derive(Clone)]
+pub(crate) struct FlatBvhNode {
+    pub(crate) min: Vector3<f32>,
+    pub(crate) max: Vector3<f32>,
+    pub(crate) offset: u32,
+    pub(crate) count: u32,
+    pub(crate) axis: u8,
+}
+
+impl FlatBvhNode {
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+// Walks every node of a flat BVH the ray passes through, nearer child first.  `on_leaf`
+// gets each leaf's primitive range and returns the new upper bound for t, or None to
+// stop the traversal.
+pub(crate) fn traverse_flat_bvh(
+    nodes: &[FlatBvhNode],
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+    stat: &mut RenderStats,
+    mut on_leaf: impl FnMut(Range<usize>, f32, &mut RenderStats) -> Option<f32>,
+) {
+    let dir = ray.get_direction();
+    let dir_is_negative = [dir.x < 0.0, dir.y < 0.0, dir.z < 0.0];
+
+    let mut closest_t = t_max;
+    let mut stack = [0_u32; MAX_TRAVERSAL_DEPTH];
+    let mut stack_size = 1;
+
+    while stack_size > 0 {
+        stack_size -= 1;
+        let node_index = stack[stack_size];
+        let node = &nodes[node_index as usize];
+        stat.bvh_node_hit();
+        if !hit_slabs(node.min, node.max, ray, t_min, closest_t) {
+            continue;
+        }
+
+        if node.is_leaf() {
+            let first = node.offset as usize;
+            match on_leaf(first..(first + node.count as usize), closest_t, stat) {
+                Some(t) => closest_t = t,
+                None => return,
+            }
+        } else {
+            // push the far child first so the near one is visited first
+            let left = node_index + 1;
+            let right = node.offset;
+            let (near, far) = if dir_is_negative[node.axis as usize] {
+                (right, left)
+            } else {
+                (left, right)
+            };
+            stack[stack_size] = far;
+            stack[stack_size + 1] = near;
+            stack_size += 2;
+        }
+    }
+}
+
+/// A BVH over the scene's hitables stored as one contiguous node array.  Traversal uses
+/// an explicit stack, visits the child nearer the ray origin first and skips any node
+/// that starts beyond the closest hit found so far.
+pub struct FlatBvh {
+    nodes: Vec<FlatBvhNode>,
+    primitives: Vec<ThreadHitable>,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl FlatBvh {
+    pub(crate) fn new(
+        nodes: Vec<FlatBvhNode>,
+        primitives: Vec<ThreadHitable>,
+        bounding_box: Arc<Box<AABB>>,
+    ) -> ThreadHitable {
+        Arc::new(Box::new(FlatBvh {
+            nodes,
+            primitives,
+            bounding_box,
+        }))
+    }
+}
+
+impl fmt::Display for FlatBvh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[FlatBvh(aabb: <{}>, nodes: {}, primitives: {})]",
+            self.bounding_box,
+            self.nodes.len(),
+            self.primitives.len()
+        )
+    }
+}
+
+impl Hitable for FlatBvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("flatbvh::hit()");
+
+        let mut closest: Option<HitRecord> = None;
+        traverse_flat_bvh(
+            &self.nodes,
+            ray,
+            t_min,
+            t_max,
+            stat,
+            |range, mut closest_t, stat| {
+                for primitive in &self.primitives[range] {
+                    if let Some(hit_record) = primitive.hit(ray, t_min, closest_t, stat) {
+                        closest_t = hit_record.get_t();
+                        closest = Some(hit_record);
+                    }
+                }
+                Some(closest_t)
+            },
+        );
+
+        closest
+    }
+
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("flatbvh::any_hit()");
+
+        let mut found = false;
+        traverse_flat_bvh(
+            &self.nodes,
+            ray,
+            t_min,
+            t_max,
+            stat,
+            |range, closest_t, stat| {
+                found = self.primitives[range]
+                    .iter()
+                    .any(|primitive| primitive.any_hit(ray, t_min, closest_t, stat));
+                if found {
+                    None
+                } else {
+                    Some(closest_t)
+                }
+            },
+        );
+
+        found
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(0.0, 0.0, 0.0)
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+}
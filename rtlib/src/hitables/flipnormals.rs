@@ -25,14 +25,17 @@ impl Hitable for FlipNormals {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
         info!("flipnormals::hit()");
         if let Some(hr) = self.hitable.hit(ray, t_min, t_max, stat) {
-            return Some(HitRecord::new(
-                hr.get_t(),
-                hr.get_p(),
-                -hr.get_normal(),
-                0.0, // todo: distance_squared
-                hr.get_material_id(),
-                hr.get_uv_coords(),
-            ));
+            return Some(
+                HitRecord::new(
+                    hr.get_t(),
+                    hr.get_p(),
+                    -hr.get_normal(),
+                    0.0, // todo: distance_squared
+                    hr.get_material_id(),
+                    hr.get_uv_coords(),
+                )
+                .with_vertex_color(hr.get_vertex_color()),
+            );
         }
         None
     }
@@ -0,0 +1,273 @@
+use crate::hitables::{clip_slabs, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::materials::{CompiledMaterials, MediumMaterial};
+use crate::next_rand_f32;
+use crate::phasefunctions::ThreadPhaseFunction;
+use crate::render::{Color, Ray};
+use crate::stats::RenderStats;
+use crate::textures::{vector_perlin_turbulence, ColorTexture};
+use crate::{vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// Densities on a regular 3D grid of `resolution` samples, x varying fastest then y.
+/// The corner samples sit on the corners of the box the grid is stretched over, and
+/// densities in between are interpolated.
+pub struct DensityGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> DensityGrid {
+        if resolution.iter().any(|n| *n < 2) {
+            panic!("density grid must have at least 2 samples along each axis");
+        }
+        if values.len() != resolution[0] * resolution[1] * resolution[2] {
+            panic!("density grid must have one value per sample");
+        }
+
+        DensityGrid { resolution, values }
+    }
+
+    /// A cloud from vector perlin turbulence, `frequency` noise cells across the grid.
+    /// It fades out toward the sides of the grid so it doesn't look like a box.
+    pub fn from_noise(resolution: [usize; 3], frequency: f32) -> DensityGrid {
+        let mut values = Vec::with_capacity(resolution[0] * resolution[1] * resolution[2]);
+        for k in 0..resolution[2] {
+            for j in 0..resolution[1] {
+                for i in 0..resolution[0] {
+                    // -1 to 1 over the grid
+                    let q = vec3(
+                        (2.0 * (i as f32) / ((resolution[0] - 1).max(1) as f32)) - 1.0,
+                        (2.0 * (j as f32) / ((resolution[1] - 1).max(1) as f32)) - 1.0,
+                        (2.0 * (k as f32) / ((resolution[2] - 1).max(1) as f32)) - 1.0,
+                    );
+                    let falloff = (1.0 - q.magnitude2()).max(0.0);
+                    let noise = vector_perlin_turbulence(q * (0.5 * frequency));
+                    values.push(falloff * noise);
+                }
+            }
+        }
+
+        DensityGrid::new(resolution, values)
+    }
+
+    pub fn get_max_value(&self) -> f32 {
+        self.values.iter().cloned().fold(0.0, f32::max)
+    }
+
+    fn get_value(&self, i: usize, j: usize, k: usize) -> f32 {
+        self.values[(((k * self.resolution[1]) + j) * self.resolution[0]) + i]
+    }
+
+    /// The density at `p`, given from 0 to 1 along each side of the grid.
+    pub fn get_density(&self, p: Vector3<f32>) -> f32 {
+        let mut cell = [0_usize; 3];
+        let mut fraction = [0.0_f32; 3];
+        for axis in 0..3 {
+            let last = (self.resolution[axis] - 1) as f32;
+            let x = (p[axis] * last).clamp(0.0, last);
+            let c = (x.floor() as usize).min(self.resolution[axis] - 2);
+            cell[axis] = c;
+            fraction[axis] = x - (c as f32);
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                weight *= if offset[axis] == 1 {
+                    fraction[axis]
+                } else {
+                    1.0 - fraction[axis]
+                };
+            }
+            if weight > 0.0 {
+                density += weight
+                    * self.get_value(
+                        cell[0] + offset[0],
+                        cell[1] + offset[1],
+                        cell[2] + offset[2],
+                    );
+            }
+        }
+        density
+    }
+}
+
+/// A medium whose density varies over the box from `min` to `max`, like a cloud or
+/// smoke.  Light is absorbed at `absorption` and scattered at `scattering` per unit of
+/// density and distance, and scattering is tinted by `albedo` and spread by the phase
+/// function.
+///
+/// Collisions are found with delta tracking: free flights are drawn against a majorant,
+/// the extinction at the densest sample, and each tentative collision is real with the
+/// probability of the actual extinction over the majorant.  Absorption is folded into the
+/// color of the scattered light rather than ending paths.  Occlusion queries estimate
+/// the transmittance with ratio tracking instead.
+pub struct GridMedium {
+    grid: DensityGrid,
+    absorption: f32,
+    scattering: f32,
+    majorant: f32,
+    phase_function: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl GridMedium {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        grid: DensityGrid,
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+        absorption: f32,
+        scattering: f32,
+        albedo: Color,
+        phase_function: ThreadPhaseFunction,
+        materials: &mut CompiledMaterials,
+    ) -> ThreadHitable {
+        let extinction = absorption + scattering;
+        let scattered = if extinction > 0.0 {
+            albedo.multiply_by_scalar(scattering / extinction)
+        } else {
+            albedo
+        };
+        let phase_function = materials.add(MediumMaterial::new(
+            ColorTexture::new(scattered.r(), scattered.g(), scattered.b()),
+            phase_function,
+        ));
+
+        Arc::new(Box::new(GridMedium {
+            majorant: extinction * grid.get_max_value(),
+            grid,
+            absorption,
+            scattering,
+            phase_function,
+            bounding_box: AABB::new(min, max),
+        }))
+    }
+
+    fn get_extinction(&self, p: Vector3<f32>) -> f32 {
+        let min = self.bounding_box.min;
+        let size = self.bounding_box.max - min;
+        let local = vec3(
+            (p.x - min.x) / size.x,
+            (p.y - min.y) / size.y,
+            (p.z - min.z) / size.z,
+        );
+        (self.absorption + self.scattering) * self.grid.get_density(local)
+    }
+
+    // The next tentative collision along the ray after t, drawn against the majorant.
+    fn get_next_collision(&self, t: f32, speed: f32) -> f32 {
+        t - ((1.0 - next_rand_f32()).ln() / (self.majorant * speed))
+    }
+
+    /// The fraction of light that makes it through the medium between t_min and t_max,
+    /// estimated with ratio tracking.
+    pub fn get_transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let (t_start, t_end) = match clip_slabs(
+            self.bounding_box.min,
+            self.bounding_box.max,
+            ray,
+            t_min,
+            t_max,
+        ) {
+            Some(span) => span,
+            None => return 1.0,
+        };
+        let speed = ray.get_direction().magnitude();
+        if self.majorant <= 0.0 || speed == 0.0 {
+            return 1.0;
+        }
+
+        let mut transmittance = 1.0;
+        let mut t = t_start;
+        loop {
+            t = self.get_next_collision(t, speed);
+            if t >= t_end {
+                return transmittance;
+            }
+            transmittance *=
+                1.0 - (self.get_extinction(ray.get_point_at_parameter(t)) / self.majorant);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+    }
+}
+
+impl fmt::Display for GridMedium {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[GridMedium(resolution: {:?}, absorption: {}, scattering: {}, aabb: <{}>)]",
+            self.grid.resolution, self.absorption, self.scattering, self.bounding_box
+        )
+    }
+}
+
+impl Hitable for GridMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("gridmedium::hit()");
+        stat.grid_medium_hit();
+
+        let (t_start, t_end) = clip_slabs(
+            self.bounding_box.min,
+            self.bounding_box.max,
+            ray,
+            t_min,
+            t_max,
+        )?;
+        let speed = ray.get_direction().magnitude();
+        if self.majorant <= 0.0 || speed == 0.0 {
+            return None;
+        }
+
+        let mut t = t_start;
+        loop {
+            t = self.get_next_collision(t, speed);
+            if t >= t_end {
+                return None;
+            }
+            let p = ray.get_point_at_parameter(t);
+            if next_rand_f32() * self.majorant < self.get_extinction(p) {
+                return Some(HitRecord::new(
+                    t,
+                    p,
+                    Vector3::unit_x(), // arbitrary
+                    0.0,               // todo: distance_squared
+                    self.phase_function,
+                    Point2::new(0.0, 0.0), // don't need u/v since PhaseFunction is a calculation
+                ));
+            }
+        }
+    }
+
+    // Blocks the ray with the chance that light doesn't make it through, so on average
+    // it lets through the ratio tracked transmittance.
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("gridmedium::any_hit()");
+        stat.grid_medium_hit();
+
+        next_rand_f32() >= self.get_transmittance(ray, t_min, t_max)
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
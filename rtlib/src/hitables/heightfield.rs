@@ -0,0 +1,397 @@
+use crate::hitables::{clip_slabs, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::textures::perlin_noise;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// A grid of heights between 0 and 1, `width` samples along x by `depth` along z.
+pub struct HeightMap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>,
+}
+
+impl HeightMap {
+    /// `heights` holds `depth` rows of `width` samples, the first row at the low z end.
+    pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> HeightMap {
+        if width < 2 || depth < 2 {
+            panic!("height map must be at least 2 by 2 samples");
+        }
+        if heights.len() != width * depth {
+            panic!("height map must have width * depth samples");
+        }
+
+        HeightMap {
+            width,
+            depth,
+            heights,
+        }
+    }
+
+    /// Reads the brightness of a grayscale (or color) image.  The top row of the image
+    /// ends up at the far z end, so an image texture of the same file lines up with it.
+    pub fn from_image_file(file_path: &str) -> image::ImageResult<HeightMap> {
+        let image = image::open(file_path)?.to_luma16();
+        let (width, depth) = (image.width() as usize, image.height() as usize);
+        if width < 2 || depth < 2 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let mut heights = Vec::with_capacity(width * depth);
+        for j in (0..depth).rev() {
+            for i in 0..width {
+                heights.push(f32::from(image.get_pixel(i as u32, j as u32)[0]) / 65535.0);
+            }
+        }
+
+        Ok(HeightMap::new(width, depth, heights))
+    }
+
+    /// Sums `octaves` layers of perlin noise, each at twice the frequency and half the
+    /// weight of the last, and stretches the result to fill 0 to 1.  `frequency` is the
+    /// number of noise cells across the first layer.  The noise tables are random, so
+    /// every run makes different terrain.
+    pub fn from_perlin(width: usize, depth: usize, frequency: f32, octaves: u32) -> HeightMap {
+        let mut heights = Vec::with_capacity(width * depth);
+        for j in 0..depth {
+            for i in 0..width {
+                let p = vec3(
+                    (i as f32) / ((width - 1).max(1) as f32),
+                    0.5,
+                    (j as f32) / ((depth - 1).max(1) as f32),
+                ) * frequency;
+                let mut height = 0.0;
+                let mut weight = 1.0;
+                let mut scale = 1.0;
+                for _ in 0..octaves.max(1) {
+                    height += weight * perlin_noise(p * scale, true);
+                    weight *= 0.5;
+                    scale *= 2.0;
+                }
+                heights.push(height);
+            }
+        }
+
+        let low = heights.iter().cloned().fold(f32::MAX, f32::min);
+        let high = heights.iter().cloned().fold(f32::MIN, f32::max);
+        if high > low {
+            for height in heights.iter_mut() {
+                *height = (*height - low) / (high - low);
+            }
+        }
+
+        HeightMap::new(width, depth, heights)
+    }
+
+    fn get_height(&self, i: usize, j: usize) -> f32 {
+        self.heights[(j * self.width) + i]
+    }
+}
+
+// Lowest and highest height under each node of one level of the quadtree.
+struct QuadTreeLevel {
+    width: usize,
+    depth: usize,
+    ranges: Vec<(f32, f32)>,
+}
+
+/// Terrain from a height map, stretched over the box from `min` to `max` with height 0 at
+/// `min.y` and 1 at `max.y`.  Each grid cell is two triangles, built when a ray reaches
+/// it.  Rays find their cells by walking a quadtree of height ranges from the front, so
+/// most of the grid is never looked at.  Normals are interpolated from the slope at the
+/// samples, and UVs run from 0 to 1 over x and z.
+pub struct Heightfield {
+    map: HeightMap,
+    normals: Vec<Vector3<f32>>,
+    // levels[0] holds single cells, the last level is one node covering everything
+    levels: Vec<QuadTreeLevel>,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Heightfield {
+    pub fn new(
+        map: HeightMap,
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let cell_size = vec3(
+            (max.x - min.x) / ((map.width - 1) as f32),
+            max.y - min.y,
+            (max.z - min.z) / ((map.depth - 1) as f32),
+        );
+
+        let mut normals = Vec::with_capacity(map.heights.len());
+        for j in 0..map.depth {
+            for i in 0..map.width {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(map.width - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(map.depth - 1));
+                let slope_x = (map.get_height(i1, j) - map.get_height(i0, j)) * cell_size.y
+                    / (((i1 - i0) as f32) * cell_size.x);
+                let slope_z = (map.get_height(i, j1) - map.get_height(i, j0)) * cell_size.y
+                    / (((j1 - j0) as f32) * cell_size.z);
+                normals.push(to_unit_vector(vec3(-slope_x, 1.0, -slope_z)));
+            }
+        }
+
+        let mut cells = Vec::with_capacity((map.width - 1) * (map.depth - 1));
+        for j in 0..map.depth - 1 {
+            for i in 0..map.width - 1 {
+                let corners = [
+                    map.get_height(i, j),
+                    map.get_height(i + 1, j),
+                    map.get_height(i, j + 1),
+                    map.get_height(i + 1, j + 1),
+                ];
+                cells.push((
+                    corners.iter().cloned().fold(f32::MAX, f32::min),
+                    corners.iter().cloned().fold(f32::MIN, f32::max),
+                ));
+            }
+        }
+        let mut levels = vec![QuadTreeLevel {
+            width: map.width - 1,
+            depth: map.depth - 1,
+            ranges: cells,
+        }];
+        while levels[levels.len() - 1].width > 1 || levels[levels.len() - 1].depth > 1 {
+            let below = &levels[levels.len() - 1];
+            let width = below.width.div_ceil(2);
+            let depth = below.depth.div_ceil(2);
+            let mut ranges = Vec::with_capacity(width * depth);
+            for j in 0..depth {
+                for i in 0..width {
+                    let mut range = (f32::MAX, f32::MIN);
+                    for (ci, cj) in Heightfield::children(below, i, j) {
+                        let child = below.ranges[(cj * below.width) + ci];
+                        range = (range.0.min(child.0), range.1.max(child.1));
+                    }
+                    ranges.push(range);
+                }
+            }
+            levels.push(QuadTreeLevel {
+                width,
+                depth,
+                ranges,
+            });
+        }
+
+        Arc::new(Box::new(Heightfield {
+            map,
+            normals,
+            levels,
+            material_id,
+            bounding_box: AABB::new(min, max),
+        }))
+    }
+
+    // The nodes of `below` under node (i, j) of the level above it.
+    fn children(below: &QuadTreeLevel, i: usize, j: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, depth) = (below.width, below.depth);
+        (0..4)
+            .map(move |k| ((2 * i) + (k % 2), (2 * j) + (k / 2)))
+            .filter(move |&(ci, cj)| ci < width && cj < depth)
+    }
+
+    fn get_vertex(&self, i: usize, j: usize) -> Vector3<f32> {
+        let min = self.bounding_box.min;
+        let max = self.bounding_box.max;
+        vec3(
+            min.x + ((max.x - min.x) * (i as f32) / ((self.map.width - 1) as f32)),
+            min.y + ((max.y - min.y) * self.map.get_height(i, j)),
+            min.z + ((max.z - min.z) * (j as f32) / ((self.map.depth - 1) as f32)),
+        )
+    }
+
+    fn get_normal(&self, i: usize, j: usize) -> Vector3<f32> {
+        self.normals[(j * self.map.width) + i]
+    }
+
+    // The box around node (i, j) of a level, padded a little so rays along flat ground
+    // or the seams between cells still reach the triangles.
+    fn get_node_bounds(&self, level: usize, i: usize, j: usize) -> (Vector3<f32>, Vector3<f32>) {
+        let span = 1 << level;
+        let (low, high) = self.levels[level].ranges[(j * self.levels[level].width) + i];
+        let min = self.bounding_box.min;
+        let max = self.bounding_box.max;
+        let cell_x = (max.x - min.x) / ((self.map.width - 1) as f32);
+        let cell_z = (max.z - min.z) / ((self.map.depth - 1) as f32);
+        let i_end = ((i + 1) * span).min(self.map.width - 1);
+        let j_end = ((j + 1) * span).min(self.map.depth - 1);
+        let pad = (max - min).magnitude() * 1e-5;
+        (
+            vec3(
+                min.x + (cell_x * ((i * span) as f32)) - pad,
+                min.y + ((max.y - min.y) * low) - pad,
+                min.z + (cell_z * ((j * span) as f32)) - pad,
+            ),
+            vec3(
+                min.x + (cell_x * (i_end as f32)) + pad,
+                min.y + ((max.y - min.y) * high) + pad,
+                min.z + (cell_z * (j_end as f32)) + pad,
+            ),
+        )
+    }
+
+    // Closest hit with one of the two triangles of cell (i, j), as the distance and the
+    // interpolated normal.
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        i: usize,
+        j: usize,
+    ) -> Option<(f32, Vector3<f32>)> {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let mut closest: Option<(f32, Vector3<f32>)> = None;
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [a, b, c] = triangle.map(|k| corners[k]);
+            let limit = closest.map_or(t_max, |(t, _)| t);
+            if let Some((t, u, v)) = hit_triangle(
+                ray,
+                t_min,
+                limit,
+                self.get_vertex(a.0, a.1),
+                self.get_vertex(b.0, b.1),
+                self.get_vertex(c.0, c.1),
+            ) {
+                let normal = (self.get_normal(a.0, a.1) * (1.0 - u - v))
+                    + (self.get_normal(b.0, b.1) * u)
+                    + (self.get_normal(c.0, c.1) * v);
+                closest = Some((t, to_unit_vector(normal)));
+            }
+        }
+        closest
+    }
+}
+
+// Möller–Trumbore, returning the distance and the barycentric coordinates of b and c.
+// The edges are widened a hair so rays can't slip between neighbouring triangles.
+fn hit_triangle(
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+) -> Option<(f32, f32, f32)> {
+    const EDGE_TOLERANCE: f32 = 1e-5;
+
+    let e1 = b - a;
+    let e2 = c - a;
+    let dir = ray.get_direction();
+    let pvec = dir.cross(e2);
+    let det = e1.dot(pvec);
+    if det == 0.0 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = ray.get_origin() - a;
+    let u = tvec.dot(pvec) * inv_det;
+    if !(-EDGE_TOLERANCE..=1.0 + EDGE_TOLERANCE).contains(&u) {
+        return None;
+    }
+
+    let qvec = tvec.cross(e1);
+    let v = dir.dot(qvec) * inv_det;
+    if v < -EDGE_TOLERANCE || (u + v) > 1.0 + EDGE_TOLERANCE {
+        return None;
+    }
+
+    let t = e2.dot(qvec) * inv_det;
+    if t > t_min && t < t_max {
+        Some((t, u.max(0.0), v.max(0.0)))
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for Heightfield {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Heightfield(width: {}, depth: {}, aabb: <{}>)]",
+            self.map.width, self.map.depth, self.bounding_box
+        )
+    }
+}
+
+impl Hitable for Heightfield {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("heightfield::hit()");
+        stat.heightfield_hit();
+
+        // nodes waiting to be visited, with the distance the ray enters them at.  Children
+        // go on nearest last, so the front of the terrain is searched first and the
+        // search stops once every waiting node starts behind the closest hit.
+        let top = self.levels.len() - 1;
+        let (min, max) = self.get_node_bounds(top, 0, 0);
+        let (t_enter, _) = clip_slabs(min, max, ray, t_min, t_max)?;
+        let mut stack = vec![(top, 0, 0, t_enter)];
+        let mut closest: Option<(f32, Vector3<f32>)> = None;
+        while let Some((level, i, j, t_enter)) = stack.pop() {
+            let limit = closest.map_or(t_max, |(t, _)| t);
+            if t_enter > limit {
+                continue;
+            }
+
+            if level == 0 {
+                if let Some(hit) = self.hit_cell(ray, t_min, limit, i, j) {
+                    closest = Some(hit);
+                }
+                continue;
+            }
+
+            let mut children: Vec<(usize, usize, usize, f32)> =
+                Heightfield::children(&self.levels[level - 1], i, j)
+                    .filter_map(|(ci, cj)| {
+                        let (min, max) = self.get_node_bounds(level - 1, ci, cj);
+                        clip_slabs(min, max, ray, t_min, limit)
+                            .map(|(t_enter, _)| (level - 1, ci, cj, t_enter))
+                    })
+                    .collect();
+            children.sort_by(|a, b| b.3.partial_cmp(&a.3).unwrap_or(std::cmp::Ordering::Equal));
+            stack.extend(children);
+        }
+
+        let (t, normal) = closest?;
+        let p = ray.get_point_at_parameter(t);
+        let min = self.bounding_box.min;
+        let max = self.bounding_box.max;
+        Some(HitRecord::new(
+            t,
+            p,
+            normal,
+            0.0, // todo: distance_squared
+            self.material_id,
+            Point2::new(
+                ((p.x - min.x) / (max.x - min.x)).clamp(0.0, 1.0),
+                ((p.z - min.z) / (max.z - min.z)).clamp(0.0, 1.0),
+            ),
+        ))
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
@@ -1,8 +1,8 @@
 use crate::hitables::HitRecord;
 use crate::hitables::{Hitable, ThreadHitable, AABB};
-use crate::next_rand_f32;
 use crate::render::Ray;
 use crate::stats::RenderStats;
+use crate::{get_random_in_unit_sphere, next_rand_f32};
 use crate::{vec3, Point2, Vector3};
 use std::fmt;
 use std::sync::Arc;
@@ -64,6 +64,7 @@ impl Hitable for HitableList {
                         final_hitrecord.t = hit_record.t;
                         final_hitrecord.normal = hit_record.normal;
                         final_hitrecord.material_id = hit_record.material_id;
+                        final_hitrecord.vertex_color = hit_record.vertex_color;
                     }
                 }
                 None => {}
@@ -91,6 +92,11 @@ impl Hitable for HitableList {
     }
 
     fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        if self.hitables.is_empty() {
+            // matches the uniform sphere directions handed out by random()
+            return 1.0 / (4.0 * std::f32::consts::PI);
+        }
+
         let weight = 1.0 / (self.hitables.len() as f32);
         let mut sum = 0.0;
         for i in 0..self.hitables.len() {
@@ -101,8 +107,23 @@ impl Hitable for HitableList {
     }
 
     fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
-        let count = self.hitables.len() - 1;
-        let index = (next_rand_f32() * (count as f32)).round() as usize;
+        if self.hitables.is_empty() {
+            // a scene without lights still needs a valid direction to sample
+            return get_random_in_unit_sphere();
+        }
+
+        // every hitable equally likely, to match the weights in get_pdf_value()
+        let count = self.hitables.len();
+        let index = ((next_rand_f32() * (count as f32)) as usize).min(count - 1);
         return self.hitables[index].random(origin);
     }
+
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("hitablelist::any_hit()");
+        stat.hitable_list_hit();
+
+        self.hitables
+            .iter()
+            .any(|hitable| hitable.any_hit(ray, t_min, t_max, stat))
+    }
 }
@@ -1,3 +1,4 @@
+use crate::render::Color;
 use crate::{Point2, Vector3};
 
 #[derive(Copy, Clone)]
@@ -10,6 +11,9 @@ pub struct HitRecord {
 
     // Texture Coordinates
     pub uv_coords: Point2<f32>,
+
+    // Interpolated from the vertices, for meshes that have colors
+    pub vertex_color: Option<Color>,
 }
 
 impl HitRecord {
@@ -28,9 +32,15 @@ impl HitRecord {
             distance_squared,
             material_id,
             uv_coords,
+            vertex_color: None,
         }
     }
 
+    pub fn with_vertex_color(mut self, vertex_color: Option<Color>) -> HitRecord {
+        self.vertex_color = vertex_color;
+        self
+    }
+
     pub fn get_uv_coords(&self) -> Point2<f32> {
         self.uv_coords
     }
@@ -50,4 +60,8 @@ impl HitRecord {
     pub fn get_material_id(&self) -> u64 {
         self.material_id
     }
+
+    pub fn get_vertex_color(&self) -> Option<Color> {
+        self.vertex_color
+    }
 }
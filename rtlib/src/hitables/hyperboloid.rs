@@ -0,0 +1,142 @@
+use crate::hitables::polynomial::solve_quadratic;
+use crate::hitables::{get_phi, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// The surface swept by the segment from `p1` to `p2` turning `phi_max` degrees around
+/// the z axis.  A segment parallel to the axis gives a cylinder, one that meets it a
+/// cone and a skew one a hyperboloid of one sheet.  The segment can't be perpendicular
+/// to the axis (that would be a `Disk`).  Open at both ends, place it with a `Transform`.
+pub struct Hyperboloid {
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    z_min: f32,
+    z_max: f32,
+    phi_max: f32,
+    // x^2 + y^2 = a * z^2 + b * z + c
+    a: f64,
+    b: f64,
+    c: f64,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Hyperboloid {
+    pub fn new(
+        p1: Vector3<f32>,
+        p2: Vector3<f32>,
+        phi_max: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        assert!(
+            p1.z != p2.z,
+            "hyperboloid segment must not be perpendicular to the z axis"
+        );
+
+        // the point of the segment at height z is p1 + s * (p2 - p1), s = (z - z1) / dz,
+        // and the square of its distance to the axis is quadratic in z
+        let p1_64 = p1.cast::<f64>().unwrap();
+        let p2_64 = p2.cast::<f64>().unwrap();
+        let (dx, dy, dz) = (p2_64.x - p1_64.x, p2_64.y - p1_64.y, p2_64.z - p1_64.z);
+        let e = ((dx * dx) + (dy * dy)) / (dz * dz);
+        let f = 2.0 * ((p1_64.x * dx) + (p1_64.y * dy)) / dz;
+        let g = (p1_64.x * p1_64.x) + (p1_64.y * p1_64.y);
+        let z1 = p1_64.z;
+
+        // the distance to the axis is largest at one of the ends
+        let radius = ((p1.x * p1.x) + (p1.y * p1.y))
+            .max((p2.x * p2.x) + (p2.y * p2.y))
+            .sqrt();
+        let z_min = p1.z.min(p2.z);
+        let z_max = p1.z.max(p2.z);
+        let bounding_box = AABB::new(vec3(-radius, -radius, z_min), vec3(radius, radius, z_max));
+
+        Arc::new(Box::new(Hyperboloid {
+            p1,
+            p2,
+            z_min,
+            z_max,
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians(),
+            a: e,
+            b: f - (2.0 * e * z1),
+            c: (e * z1 * z1) - (f * z1) + g,
+            material_id,
+            bounding_box,
+        }))
+    }
+}
+
+impl fmt::Display for Hyperboloid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Hyperboloid(p1: {:?}, p2: {:?}, phi_max: {})]",
+            self.p1,
+            self.p2,
+            self.phi_max.to_degrees()
+        )
+    }
+}
+
+impl Hitable for Hyperboloid {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("hyperboloid::hit()");
+        stat.hyperboloid_hit();
+
+        let o = ray.get_origin().cast::<f64>().unwrap();
+        let d = ray.get_direction().cast::<f64>().unwrap();
+        let a = (d.x * d.x) + (d.y * d.y) - (self.a * d.z * d.z);
+        let b = 2.0 * ((o.x * d.x) + (o.y * d.y) - (self.a * o.z * d.z)) - (self.b * d.z);
+        let c = (o.x * o.x) + (o.y * o.y) - (self.a * o.z * o.z) - (self.b * o.z) - self.c;
+
+        for t in solve_quadratic(a, b, c) {
+            let t = t as f32;
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = ray.get_point_at_parameter(t);
+            if p.z < self.z_min || p.z > self.z_max {
+                continue;
+            }
+            let phi = get_phi(p.x, p.y);
+            if phi > self.phi_max {
+                continue;
+            }
+
+            let slope = ((2.0 * self.a * f64::from(p.z)) + self.b) as f32;
+            return Some(HitRecord::new(
+                t,
+                p,
+                to_unit_vector(vec3(2.0 * p.x, 2.0 * p.y, -slope)),
+                0.0, // todo: distance_squared
+                self.material_id,
+                Point2::new(
+                    phi / self.phi_max,
+                    (p.z - self.p1.z) / (self.p2.z - self.p1.z),
+                ),
+            ));
+        }
+
+        None
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
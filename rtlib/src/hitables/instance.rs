@@ -0,0 +1,72 @@
+use crate::hitables::{HitRecord, Hitable, ThreadHitable, Transform, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{Matrix4, Vector3};
+use std::fmt;
+use std::sync::Arc;
+
+/// One placement of shared geometry.  The geometry (usually a `TriangleMesh` or a
+/// `FlatBvh` from `BvhBuilder`) is built once and every instance holds an `Arc` to
+/// it, so a thousand instances cost the memory of one copy plus a matrix each.  Put
+/// the instances themselves through `BvhBuilder` (which `create_scene` does) to get
+/// the top-level BVH over them.
+pub struct Instance {
+    transform: Transform,
+    material_id: Option<u64>,
+}
+
+impl Instance {
+    /// The transform must be affine and invertible.  When a material id is given it
+    /// replaces the materials of the geometry.
+    pub fn new(
+        geometry: ThreadHitable,
+        object_to_world: Matrix4<f32>,
+        material_id: Option<u64>,
+    ) -> ThreadHitable {
+        Arc::new(Box::new(Instance {
+            transform: Transform::build(geometry, object_to_world),
+            material_id,
+        }))
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Instance({}, material: {:?})]",
+            self.transform, self.material_id
+        )
+    }
+}
+
+impl Hitable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("instance::hit()");
+        stat.instance_hit();
+
+        let mut hit_record = self.transform.hit_transformed(ray, t_min, t_max, stat)?;
+        if let Some(material_id) = self.material_id {
+            hit_record.material_id = material_id;
+        }
+        Some(hit_record)
+    }
+
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("instance::any_hit()");
+        stat.instance_hit();
+        self.transform.any_hit_transformed(ray, t_min, t_max, stat)
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        self.transform.get_pdf_value(origin, v, stat)
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        self.transform.random(origin)
+    }
+
+    fn get_bounding_box(&self, t0: f32, t1: f32) -> Arc<Box<AABB>> {
+        self.transform.get_bounding_box(t0, t1)
+    }
+}
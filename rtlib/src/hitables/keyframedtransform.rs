@@ -0,0 +1,216 @@
+use crate::hitables::transform::{transform_bounding_box, transform_point, transform_vector};
+use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Matrix4, Vector3};
+use cgmath::{Matrix, Quaternion};
+use std::fmt;
+use std::sync::Arc;
+
+// Steps per keyframe segment used to sweep the bounding box.
+const BOUNDS_STEPS: usize = 32;
+
+/// Where a hitable is at one point in time.  The matrix is `translation * rotation *
+/// scale`, so the object is scaled first and translated last.
+#[derive(Clone, Copy, Debug)]
+pub struct TransformKeyframe {
+    pub time: f32,
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl TransformKeyframe {
+    pub fn new(
+        time: f32,
+        translation: Vector3<f32>,
+        rotation: Quaternion<f32>,
+        scale: Vector3<f32>,
+    ) -> TransformKeyframe {
+        TransformKeyframe {
+            time,
+            translation,
+            rotation: rotation.normalize(),
+            scale,
+        }
+    }
+
+    fn interpolate(&self, other: &TransformKeyframe, amount: f32) -> TransformKeyframe {
+        TransformKeyframe {
+            time: self.time + (amount * (other.time - self.time)),
+            translation: self.translation + (amount * (other.translation - self.translation)),
+            rotation: self.rotation.slerp(other.rotation, amount),
+            scale: self.scale + (amount * (other.scale - self.scale)),
+        }
+    }
+
+    fn object_to_world(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    // Built from the parts rather than by inverting the matrix, it runs once per ray.
+    fn world_to_object(&self) -> Matrix4<f32> {
+        Matrix4::from_nonuniform_scale(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z)
+            * Matrix4::from(self.rotation.conjugate())
+            * Matrix4::from_translation(-self.translation)
+    }
+}
+
+/// Animates a hitable through a list of keyframes.  Each ray is tested against the
+/// placement at its own time, with translation and scale interpolated linearly and
+/// rotation spherically between the surrounding keyframes.  Before the first and after
+/// the last keyframe the hitable holds still.  The bounding box covers the whole motion.
+pub struct KeyframedTransform {
+    hitable: ThreadHitable,
+    keyframes: Vec<TransformKeyframe>,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl KeyframedTransform {
+    /// Keyframes are sorted by time, there must be at least one and every scale
+    /// component must be non-zero.
+    pub fn new(hitable: ThreadHitable, mut keyframes: Vec<TransformKeyframe>) -> ThreadHitable {
+        assert!(
+            !keyframes.is_empty(),
+            "keyframed transform needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let bounding_box = sweep_bounding_box(&hitable.get_bounding_box(0.0, 1.0), &keyframes);
+
+        Arc::new(Box::new(KeyframedTransform {
+            hitable,
+            keyframes,
+            bounding_box,
+        }))
+    }
+
+    pub fn get_keyframe_at(&self, time: f32) -> TransformKeyframe {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return *first;
+        }
+        if time >= last.time {
+            return *last;
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (k0, k1) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        k0.interpolate(k1, (time - k0.time) / (k1.time - k0.time))
+    }
+
+    // Where light sampling sees the hitable, see `Hitable::get_pdf_value`.
+    fn mid_keyframe(&self) -> TransformKeyframe {
+        let first = self.keyframes[0].time;
+        let last = self.keyframes[self.keyframes.len() - 1].time;
+        self.get_keyframe_at((first + last) * 0.5)
+    }
+}
+
+fn sweep_bounding_box(object_box: &AABB, keyframes: &[TransformKeyframe]) -> Arc<Box<AABB>> {
+    let mut bounding_box = transform_bounding_box(&keyframes[0].object_to_world(), object_box);
+    let corner = vec3(
+        object_box.min.x.abs().max(object_box.max.x.abs()),
+        object_box.min.y.abs().max(object_box.max.y.abs()),
+        object_box.min.z.abs().max(object_box.max.z.abs()),
+    );
+
+    for pair in keyframes.windows(2) {
+        let (k0, k1) = (&pair[0], &pair[1]);
+        let mut segment_box = bounding_box.clone();
+        for step in 1..=BOUNDS_STEPS {
+            let k = k0.interpolate(k1, step as f32 / BOUNDS_STEPS as f32);
+            segment_box = segment_box
+                .get_surrounding_box(transform_bounding_box(&k.object_to_world(), object_box));
+        }
+
+        // Between two samples a rotating point moves along an arc that can bulge out of
+        // the boxes by at most r * (1 - cos(step_angle / 2)).
+        let angle = 2.0 * k0.rotation.dot(k1.rotation).abs().min(1.0).acos();
+        let max_scale = |s: Vector3<f32>| s.x.abs().max(s.y.abs()).max(s.z.abs());
+        let radius = corner.magnitude() * max_scale(k0.scale).max(max_scale(k1.scale));
+        let pad = radius * (1.0 - (angle / (2.0 * BOUNDS_STEPS as f32)).cos());
+        let pad = vec3(pad, pad, pad);
+        bounding_box = bounding_box
+            .get_surrounding_box(AABB::new(segment_box.min - pad, segment_box.max + pad));
+    }
+
+    bounding_box
+}
+
+impl fmt::Display for KeyframedTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[KeyframedTransform({}, keyframes: {})]",
+            self.hitable,
+            self.keyframes.len()
+        )
+    }
+}
+
+impl Hitable for KeyframedTransform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("keyframedtransform::hit()");
+        stat.keyframed_transform_hit();
+
+        let world_to_object = self.get_keyframe_at(ray.get_time()).world_to_object();
+        let object_ray = Ray::new_at_time(
+            transform_point(&world_to_object, ray.get_origin()),
+            transform_vector(&world_to_object, ray.get_direction()),
+            ray.get_time(),
+            stat,
+        );
+
+        let normal_to_world = world_to_object.transpose();
+        self.hitable.hit(&object_ray, t_min, t_max, stat).map(|hr| {
+            HitRecord::new(
+                hr.get_t(),
+                ray.get_point_at_parameter(hr.get_t()),
+                to_unit_vector(transform_vector(&normal_to_world, hr.get_normal())),
+                0.0, // todo: distance_squared
+                hr.get_material_id(),
+                hr.get_uv_coords(),
+            )
+            .with_vertex_color(hr.get_vertex_color())
+        })
+    }
+
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("keyframedtransform::any_hit()");
+        stat.keyframed_transform_hit();
+
+        let world_to_object = self.get_keyframe_at(ray.get_time()).world_to_object();
+        let object_ray = Ray::new_at_time(
+            transform_point(&world_to_object, ray.get_origin()),
+            transform_vector(&world_to_object, ray.get_direction()),
+            ray.get_time(),
+            stat,
+        );
+        self.hitable.any_hit(&object_ray, t_min, t_max, stat)
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        let world_to_object = self.mid_keyframe().world_to_object();
+        self.hitable.get_pdf_value(
+            transform_point(&world_to_object, origin),
+            transform_vector(&world_to_object, v),
+            stat,
+        )
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        let keyframe = self.mid_keyframe();
+        transform_vector(
+            &keyframe.object_to_world(),
+            self.hitable
+                .random(transform_point(&keyframe.world_to_object(), origin)),
+        )
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+}
@@ -1,6 +1,7 @@
 use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
-use crate::materials::{CompiledMaterials, IsotropicMaterial};
+use crate::materials::{CompiledMaterials, MediumMaterial};
 use crate::next_rand_f32;
+use crate::phasefunctions::ThreadPhaseFunction;
 use crate::render::Ray;
 use crate::stats::RenderStats;
 use crate::textures::ThreadTexture;
@@ -19,9 +20,10 @@ impl ConstantMedium {
         boundary: ThreadHitable,
         density: f32,
         texture: ThreadTexture,
+        phase_function: ThreadPhaseFunction,
         materials: &mut CompiledMaterials,
     ) -> ThreadHitable {
-        let phase_function = materials.add(IsotropicMaterial::new(texture));
+        let phase_function = materials.add(MediumMaterial::new(texture, phase_function));
         Arc::new(Box::new(ConstantMedium {
             boundary,
             density,
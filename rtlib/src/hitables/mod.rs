@@ -1,42 +1,79 @@
 mod aabb;
-mod bvhnode;
+mod bvhbuilder;
+mod cone;
+mod csg;
 mod cube;
 mod cylinder;
+mod disk;
+mod flatbvh;
 mod flipnormals;
+mod gridmedium;
+mod heightfield;
 mod hitablelist;
 mod hitrecord;
+mod hyperboloid;
+mod instance;
+mod keyframedtransform;
 mod medium;
+mod movingsphere;
+mod paraboloid;
+mod parallelogram;
+mod polynomial;
 mod rotatex;
 mod rotatey;
 mod rotatez;
+mod sdf;
 mod sphere;
+mod torus;
+mod transform;
 mod translate;
 mod triangle;
+mod trianglemesh;
 mod xyrect;
 mod xzrect;
 mod yzrect;
 
 pub use self::aabb::AABB;
-pub use self::bvhnode::BvhNode;
+pub use self::bvhbuilder::{BvhBuildStats, BvhBuilder, BvhStrategy};
+pub use self::cone::Cone;
+pub use self::csg::{Csg, CsgOperation};
 pub use self::cube::Cube;
 pub use self::cylinder::Cylinder;
+pub use self::disk::Disk;
+pub use self::flatbvh::FlatBvh;
 pub use self::flipnormals::FlipNormals;
+pub use self::gridmedium::{DensityGrid, GridMedium};
+pub use self::heightfield::{HeightMap, Heightfield};
 pub use self::hitablelist::HitableList;
 pub use self::hitrecord::HitRecord;
+pub use self::hyperboloid::Hyperboloid;
+pub use self::instance::Instance;
+pub use self::keyframedtransform::{KeyframedTransform, TransformKeyframe};
 pub use self::medium::ConstantMedium;
+pub use self::movingsphere::MovingSphere;
+pub use self::paraboloid::Paraboloid;
+pub use self::parallelogram::Parallelogram;
 pub use self::rotatex::RotateX;
 pub use self::rotatey::RotateY;
 pub use self::rotatez::RotateZ;
+pub use self::sdf::{Sdf, SdfNode};
 pub use self::sphere::Sphere;
+pub use self::torus::Torus;
+pub use self::transform::Transform;
 pub use self::translate::Translate;
 pub use self::triangle::Triangle;
+pub use self::trianglemesh::{MeshFace, TriangleMesh};
 pub use self::xyrect::XyRect;
 pub use self::xzrect::XzRect;
 pub use self::yzrect::YzRect;
 
+pub(crate) use self::aabb::{clip_slabs, hit_slabs};
+pub(crate) use self::flatbvh::{traverse_flat_bvh, FlatBvhNode};
+pub(crate) use self::triangle::random_on_triangle;
+
 use crate::render::Ray;
 use crate::stats::RenderStats;
-use crate::Vector3;
+use crate::{InnerSpace, Vector3};
 use std::fmt;
 use std::sync::Arc;
 
@@ -45,9 +82,70 @@ pub type ThreadHitable = Arc<Box<dyn Hitable + Send>>;
 pub trait Hitable: Sync + fmt::Display {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord>;
     fn get_bounding_box(&self, t0: f32, t1: f32) -> Arc<Box<AABB>>;
+    /// `get_pdf_value` and `random` sample the hitable as a light.  They get no ray time,
+    /// so anything that moves is sampled where it is halfway through its motion.
     fn get_pdf_value(&self, _origin: Vector3<f32>, _v: Vector3<f32>, stat: &mut RenderStats)
         -> f32;
     fn random(&self, _origin: Vector3<f32>) -> Vector3<f32>;
+
+    /// Whether anything is hit between t_min and t_max.  Occlusion queries don't need the
+    /// closest hit, so acceleration structures can stop at the first one they find.
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        self.hit(ray, t_min, t_max, stat).is_some()
+    }
+
+    /// Every hit between t_min and t_max in order of t.  `Csg` needs all of them to know
+    /// where the ray is inside each solid.  By default the ray is walked forward one
+    /// `hit` at a time.
+    fn hit_all(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Vec<HitRecord> {
+        let mut hits = vec![];
+        let mut t_start = t_min;
+        while hits.len() < MAX_HITS_PER_RAY {
+            match self.hit(ray, t_start, t_max, stat) {
+                Some(hr) => {
+                    t_start = hr.t + (0.0001 * hr.t.abs().max(1.0));
+                    hits.push(hr);
+                }
+                None => break,
+            }
+        }
+        hits
+    }
+}
+
+// Stops the default `hit_all` on surfaces that keep reporting hits at the same t.
+const MAX_HITS_PER_RAY: usize = 64;
+
+/// The pdf, over directions from `origin`, of `random` picking a point uniformly on a
+/// surface of this area.  Every point the direction passes through could have been the
+/// one picked, so the densities of all of them are added up.
+pub(crate) fn get_area_pdf_value(
+    hitable: &dyn Hitable,
+    area: f32,
+    origin: Vector3<f32>,
+    v: Vector3<f32>,
+    stat: &mut RenderStats,
+) -> f32 {
+    let ray = Ray::new(origin, v, stat);
+    let mut pdf = 0.0;
+    for hr in hitable.hit_all(&ray, 0.001, f32::MAX, stat) {
+        let distance_squared = hr.t * hr.t * v.magnitude2();
+        let cosine = (v.dot(hr.get_normal()) / v.magnitude()).abs();
+        if cosine > 1e-6 {
+            pdf += distance_squared / (cosine * area);
+        }
+    }
+    pdf
+}
+
+/// The angle of (x, y) around the z axis, from 0 to 2 pi.
+pub(crate) fn get_phi(x: f32, y: f32) -> f32 {
+    let phi = y.atan2(x);
+    if phi < 0.0 {
+        phi + (2.0 * std::f32::consts::PI)
+    } else {
+        phi
+    }
 }
 
 fn to_single_array(v: Vector3<f32>) -> Vec<f32> {
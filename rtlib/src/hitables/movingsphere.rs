@@ -0,0 +1,135 @@
+use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
+use crate::pdfs::OrthoNormalBase;
+use crate::random_to_sphere;
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// A sphere whose center moves in a straight line from `center0` at `time0` to `center1`
+/// at `time1`.  Outside that interval it rests at the nearer end, so the bounding box
+/// covering both ends is valid for any shutter.
+pub struct MovingSphere {
+    center0: Vector3<f32>,
+    center1: Vector3<f32>,
+    time0: f32,
+    time1: f32,
+    radius: f32,
+    radius_sq: f32,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vector3<f32>,
+        center1: Vector3<f32>,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let extent = vec3(radius, radius, radius);
+        let box0 = AABB::new(center0 - extent, center0 + extent);
+        let box1 = AABB::new(center1 - extent, center1 + extent);
+
+        Arc::new(Box::new(MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            radius_sq: radius * radius,
+            material_id,
+            bounding_box: box0.get_surrounding_box(box1),
+        }))
+    }
+
+    pub fn center(&self, time: f32) -> Vector3<f32> {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + (s * (self.center1 - self.center0))
+    }
+
+    fn get_sphere_uv(&self, p: Vector3<f32>) -> Point2<f32> {
+        let pi = std::f32::consts::PI;
+        let punit = to_unit_vector(p);
+        let phi = punit.z.atan2(punit.x);
+        let theta = punit.y.asin();
+        let u = 1.0 - ((phi + pi) / (2.0 * pi));
+        let v = (theta + (pi / 2.0)) / pi;
+        Point2::new(u, v)
+    }
+
+    // Where light sampling sees the sphere, see `Hitable::get_pdf_value`.
+    fn mid_center(&self) -> Vector3<f32> {
+        (self.center0 + self.center1) * 0.5
+    }
+}
+
+impl fmt::Display for MovingSphere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[MovingSphere(center0: {:?}, center1: {:?}, time0: {}, time1: {}, radius: {})]",
+            self.center0, self.center1, self.time0, self.time1, self.radius
+        )
+    }
+}
+
+impl Hitable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("movingsphere::hit()");
+        stat.moving_sphere_hit();
+
+        let center = self.center(ray.get_time());
+        let oc = ray.get_origin() - center;
+        let a = ray.get_direction().dot(ray.get_direction());
+        let b = oc.dot(ray.get_direction());
+        let c = oc.dot(oc) - self.radius_sq;
+        let discriminant = (b * b) - (a * c);
+        if discriminant <= 0.0 {
+            return None;
+        }
+
+        let disc_sqrt = discriminant.sqrt();
+        for t in [(-b - disc_sqrt) / a, (-b + disc_sqrt) / a] {
+            if t < t_max && t > t_min {
+                let p = ray.get_point_at_parameter(t);
+                let normal = (p - center) / self.radius;
+                return Some(HitRecord::new(
+                    t,
+                    p,
+                    normal,
+                    0.0, // todo: distance_squared
+                    self.material_id,
+                    self.get_sphere_uv(normal),
+                ));
+            }
+        }
+        None
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        let center = self.mid_center();
+        let ray = Ray::new_at_time(origin, v, (self.time0 + self.time1) * 0.5, stat);
+        if self.hit(&ray, 0.001, f32::MAX, stat).is_none() {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - (self.radius_sq / (center - origin).magnitude2())).sqrt();
+        1.0 / (2.0 * f32::consts::PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        let direction = self.mid_center() - origin;
+        let uvw = OrthoNormalBase::from_w(direction);
+        uvw.local(random_to_sphere(self.radius, direction.magnitude2()))
+    }
+}
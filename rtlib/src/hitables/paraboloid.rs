@@ -0,0 +1,118 @@
+use crate::hitables::polynomial::solve_quadratic;
+use crate::hitables::{get_phi, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// The paraboloid x^2 + y^2 = radius^2 * z / z_max around the z axis, cut to
+/// z_min..z_max and swept `phi_max` degrees around the axis.  It is open at both ends.
+/// Place it with a `Transform`.
+pub struct Paraboloid {
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    phi_max: f32,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Paraboloid {
+    /// z_max must be positive, z_min is raised to zero if it is below.
+    pub fn new(
+        radius: f32,
+        z_min: f32,
+        z_max: f32,
+        phi_max: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let z_min = z_min.max(0.0);
+        let bounding_box = AABB::new(vec3(-radius, -radius, z_min), vec3(radius, radius, z_max));
+
+        Arc::new(Box::new(Paraboloid {
+            radius,
+            z_min,
+            z_max,
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians(),
+            material_id,
+            bounding_box,
+        }))
+    }
+}
+
+impl fmt::Display for Paraboloid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Paraboloid(radius: {}, z_min: {}, z_max: {}, phi_max: {})]",
+            self.radius,
+            self.z_min,
+            self.z_max,
+            self.phi_max.to_degrees()
+        )
+    }
+}
+
+impl Hitable for Paraboloid {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("paraboloid::hit()");
+        stat.paraboloid_hit();
+
+        let o = ray.get_origin().cast::<f64>().unwrap();
+        let d = ray.get_direction().cast::<f64>().unwrap();
+        let k = f64::from(self.z_max) / f64::from(self.radius * self.radius);
+
+        // k * (x^2 + y^2) - z = 0
+        let a = k * ((d.x * d.x) + (d.y * d.y));
+        let b = (2.0 * k * ((o.x * d.x) + (o.y * d.y))) - d.z;
+        let c = (k * ((o.x * o.x) + (o.y * o.y))) - o.z;
+
+        for t in solve_quadratic(a, b, c) {
+            let t = t as f32;
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = ray.get_point_at_parameter(t);
+            if p.z < self.z_min || p.z > self.z_max {
+                continue;
+            }
+            let phi = get_phi(p.x, p.y);
+            if phi > self.phi_max {
+                continue;
+            }
+
+            let k = k as f32;
+            return Some(HitRecord::new(
+                t,
+                p,
+                to_unit_vector(vec3(2.0 * k * p.x, 2.0 * k * p.y, -1.0)),
+                0.0, // todo: distance_squared
+                self.material_id,
+                Point2::new(
+                    phi / self.phi_max,
+                    (p.z - self.z_min) / (self.z_max - self.z_min),
+                ),
+            ));
+        }
+
+        None
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::hitables::{get_area_pdf_value, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::next_rand_f32;
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// The flat quad spanned by `edge_u` and `edge_v` from the corner `origin`, in any
+/// orientation.  The normal is `edge_u` cross `edge_v` and the UVs follow the edges.  As
+/// a light it is sampled uniformly over its area.
+pub struct Parallelogram {
+    origin: Vector3<f32>,
+    edge_u: Vector3<f32>,
+    edge_v: Vector3<f32>,
+    normal: Vector3<f32>,
+    // n / |n|^2 for the unnormalized normal n, see hit()
+    w: Vector3<f32>,
+    area: f32,
+    material_id: u64,
+}
+
+impl Parallelogram {
+    pub fn new(
+        origin: Vector3<f32>,
+        edge_u: Vector3<f32>,
+        edge_v: Vector3<f32>,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let n = edge_u.cross(edge_v);
+        Arc::new(Box::new(Parallelogram {
+            origin,
+            edge_u,
+            edge_v,
+            normal: to_unit_vector(n),
+            w: n / n.dot(n),
+            area: n.magnitude(),
+            material_id,
+        }))
+    }
+}
+
+impl fmt::Display for Parallelogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Parallelogram(origin: {:?}, edge_u: {:?}, edge_v: {:?})]",
+            self.origin, self.edge_u, self.edge_v
+        )
+    }
+}
+
+impl Hitable for Parallelogram {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("parallelogram::hit()");
+        stat.parallelogram_hit();
+
+        let denominator = self.normal.dot(ray.get_direction());
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.origin - ray.get_origin()) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // the plane coordinates of the hit, from the parts of the cross products that
+        // lie along the normal
+        let p = ray.get_point_at_parameter(t);
+        let planar = p - self.origin;
+        let u = self.w.dot(planar.cross(self.edge_v));
+        let v = self.w.dot(self.edge_u.cross(planar));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            t,
+            p,
+            self.normal,
+            0.0, // todo: distance_squared
+            self.material_id,
+            Point2::new(u, v),
+        ))
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        let corners = [
+            self.origin,
+            self.origin + self.edge_u,
+            self.origin + self.edge_v,
+            self.origin + self.edge_u + self.edge_v,
+        ];
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for c in corners.iter().skip(1) {
+            min = vec3(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z));
+            max = vec3(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z));
+        }
+
+        // pad so a quad lying in an axis plane still has some thickness
+        let pad = vec3(0.0001, 0.0001, 0.0001);
+        AABB::new(min - pad, max + pad)
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        get_area_pdf_value(self, self.area, origin, v, stat)
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        let random_point =
+            self.origin + (next_rand_f32() * self.edge_u) + (next_rand_f32() * self.edge_v);
+        random_point - origin
+    }
+}
@@ -0,0 +1,110 @@
+// Real roots of low order polynomials for the analytic shapes.  Everything is done in
+// f64: the torus quartic loses most of its precision in f32.
+
+/// Real roots of a*x^2 + b*x + c in ascending order.
+pub(crate) fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+
+    let discriminant = (b * b) - (4.0 * a * c);
+    if discriminant < 0.0 {
+        return vec![];
+    }
+
+    // avoids cancellation between -b and the root of the discriminant
+    let q = -0.5 * (b + (b.signum() * discriminant.sqrt()));
+    if q == 0.0 {
+        // b and c are both zero
+        return vec![0.0];
+    }
+    let (t0, t1) = (q / a, c / q);
+    vec![t0.min(t1), t0.max(t1)]
+}
+
+// The largest real root of x^3 + a*x^2 + b*x + c.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let q = ((a * a) - (3.0 * b)) / 9.0;
+    let r = ((2.0 * a * a * a) - (9.0 * a * b) + (27.0 * c)) / 54.0;
+    let q3 = q * q * q;
+
+    let root = if (r * r) < q3 {
+        // three real roots at angle offsets of 0, 2pi/3 and 4pi/3, the one at 2pi/3 has
+        // its cosine closest to -1 and so is the largest
+        let theta = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+        (-2.0 * q.sqrt() * ((theta + (2.0 * std::f64::consts::PI)) / 3.0).cos()) - (a / 3.0)
+    } else {
+        let big_a = -r.signum() * (r.abs() + ((r * r) - q3).sqrt()).cbrt();
+        let big_b = if big_a == 0.0 { 0.0 } else { q / big_a };
+        (big_a + big_b) - (a / 3.0)
+    };
+
+    // one Newton step cleans up the trigonometry
+    let f = ((root + a) * root + b) * root + c;
+    let df = ((3.0 * root) + (2.0 * a)) * root + b;
+    if df.abs() > 1e-12 {
+        root - (f / df)
+    } else {
+        root
+    }
+}
+
+/// Real roots of c4*x^4 + c3*x^3 + c2*x^2 + c1*x + c0 in ascending order, by Ferrari's
+/// method with a few Newton iterations on each root to recover lost precision.
+pub(crate) fn solve_quartic(c4: f64, c3: f64, c2: f64, c1: f64, c0: f64) -> Vec<f64> {
+    if c4 == 0.0 {
+        // a cubic isn't needed by any shape yet
+        return solve_quadratic(c2, c1, c0);
+    }
+
+    // x^4 + a*x^3 + b*x^2 + c*x + d, then x = y - a/4 gives y^4 + p*y^2 + q*y + r
+    let (a, b, c, d) = (c3 / c4, c2 / c4, c1 / c4, c0 / c4);
+    let a2 = a * a;
+    let p = b - (0.375 * a2);
+    let q = c - (0.5 * a * b) + (0.125 * a2 * a);
+    let r = d - (0.25 * a * c) + (0.0625 * a2 * b) - (3.0 * a2 * a2 / 256.0);
+
+    let mut roots = vec![];
+    if q.abs() < 1e-12 {
+        // biquadratic
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                let y = z.sqrt();
+                roots.push(y);
+                roots.push(-y);
+            }
+        }
+    } else {
+        // pick m so that both sides of (y^2 + p/2 + m)^2 = 2m*y^2 - q*y + m^2 + m*p + p^2/4 - r
+        // are squares, the right hand side then is (s*y - q/(2s))^2 with s = sqrt(2m)
+        let m = largest_cubic_root(p, (0.25 * p * p) - r, -0.125 * q * q);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        let half = (0.5 * p) + m;
+        roots.extend(solve_quadratic(1.0, -s, half + (0.5 * q / s)));
+        roots.extend(solve_quadratic(1.0, s, half - (0.5 * q / s)));
+    }
+
+    let mut roots: Vec<f64> = roots
+        .into_iter()
+        .map(|y| {
+            let mut x = y - (0.25 * a);
+            for _ in 0..3 {
+                let f = (((x + a) * x + b) * x + c) * x + d;
+                let df = (((4.0 * x) + (3.0 * a)) * x + (2.0 * b)) * x + c;
+                if df.abs() < 1e-12 {
+                    break;
+                }
+                x -= f / df;
+            }
+            x
+        })
+        .collect();
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+}
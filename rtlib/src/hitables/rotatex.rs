@@ -68,37 +68,41 @@ impl Hitable for RotateX {
         info!("rotatex::hit()");
         let mut origin = to_single_array(ray.get_origin());
         let mut dir = to_single_array(ray.get_direction());
-        origin[1] = (self.cos_theta * ray.get_origin().y) - (self.sin_theta * ray.get_origin().z);
-        origin[2] = (self.sin_theta * ray.get_origin().y) + (self.cos_theta * ray.get_origin().z);
+        origin[1] = (self.cos_theta * ray.get_origin().y) + (self.sin_theta * ray.get_origin().z);
+        origin[2] = (-self.sin_theta * ray.get_origin().y) + (self.cos_theta * ray.get_origin().z);
         dir[1] =
-            (self.cos_theta * ray.get_direction().y) - (self.sin_theta * ray.get_direction().z);
+            (self.cos_theta * ray.get_direction().y) + (self.sin_theta * ray.get_direction().z);
         dir[2] =
-            (self.sin_theta * ray.get_direction().y) + (self.cos_theta * ray.get_direction().z);
-        let rotated_ray = Ray::new(
+            (-self.sin_theta * ray.get_direction().y) + (self.cos_theta * ray.get_direction().z);
+        let rotated_ray = Ray::new_at_time(
             vec3(origin[0], origin[1], origin[2]),
             vec3(dir[0], dir[1], dir[2]),
+            ray.get_time(),
             stat,
         );
         if let Some(hit_record) = self.hitable.hit(&rotated_ray, t_min, t_max, stat) {
             let mut p = to_single_array(hit_record.get_p());
             let mut normal = to_single_array(hit_record.get_normal());
             p[1] =
-                (self.cos_theta * hit_record.get_p().y) + (self.sin_theta * hit_record.get_p().z);
+                (self.cos_theta * hit_record.get_p().y) - (self.sin_theta * hit_record.get_p().z);
             p[2] =
-                (-self.sin_theta * hit_record.get_p().y) + (self.cos_theta * hit_record.get_p().z);
+                (self.sin_theta * hit_record.get_p().y) + (self.cos_theta * hit_record.get_p().z);
             normal[1] = (self.cos_theta * hit_record.get_normal().y)
-                + (self.sin_theta * hit_record.get_normal().z);
-            normal[2] = (-self.sin_theta * hit_record.get_normal().y)
+                - (self.sin_theta * hit_record.get_normal().z);
+            normal[2] = (self.sin_theta * hit_record.get_normal().y)
                 + (self.cos_theta * hit_record.get_normal().z);
 
-            return Some(HitRecord::new(
-                hit_record.get_t(),
-                vec3(p[0], p[1], p[2]),
-                vec3(normal[0], normal[1], normal[2]),
-                0.0, // todo: distance_squared
-                hit_record.get_material_id(),
-                hit_record.get_uv_coords(),
-            ));
+            return Some(
+                HitRecord::new(
+                    hit_record.get_t(),
+                    vec3(p[0], p[1], p[2]),
+                    vec3(normal[0], normal[1], normal[2]),
+                    0.0, // todo: distance_squared
+                    hit_record.get_material_id(),
+                    hit_record.get_uv_coords(),
+                )
+                .with_vertex_color(hit_record.get_vertex_color()),
+            );
         }
 
         None
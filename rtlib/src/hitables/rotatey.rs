@@ -74,9 +74,10 @@ impl Hitable for RotateY {
             (self.cos_theta * ray.get_direction().x) - (self.sin_theta * ray.get_direction().z);
         dir[2] =
             (self.sin_theta * ray.get_direction().x) + (self.cos_theta * ray.get_direction().z);
-        let rotated_ray = Ray::new(
+        let rotated_ray = Ray::new_at_time(
             vec3(origin[0], origin[1], origin[2]),
             vec3(dir[0], dir[1], dir[2]),
+            ray.get_time(),
             stat,
         );
         if let Some(hit_record) = self.hitable.hit(&rotated_ray, t_min, t_max, stat) {
@@ -91,14 +92,17 @@ impl Hitable for RotateY {
             normal[2] = (-self.sin_theta * hit_record.get_normal().x)
                 + (self.cos_theta * hit_record.get_normal().z);
 
-            return Some(HitRecord::new(
-                hit_record.get_t(),
-                vec3(p[0], p[1], p[2]),
-                vec3(normal[0], normal[1], normal[2]),
-                0.0, // todo: distance_squared
-                hit_record.get_material_id(),
-                hit_record.get_uv_coords(),
-            ));
+            return Some(
+                HitRecord::new(
+                    hit_record.get_t(),
+                    vec3(p[0], p[1], p[2]),
+                    vec3(normal[0], normal[1], normal[2]),
+                    0.0, // todo: distance_squared
+                    hit_record.get_material_id(),
+                    hit_record.get_uv_coords(),
+                )
+                .with_vertex_color(hit_record.get_vertex_color()),
+            );
         }
 
         None
@@ -68,37 +68,41 @@ impl Hitable for RotateZ {
         info!("rotatez::hit()");
         let mut origin = to_single_array(ray.get_origin());
         let mut dir = to_single_array(ray.get_direction());
-        origin[0] = (self.cos_theta * ray.get_origin().x) - (self.sin_theta * ray.get_origin().y);
-        origin[1] = (self.sin_theta * ray.get_origin().x) + (self.cos_theta * ray.get_origin().y);
+        origin[0] = (self.cos_theta * ray.get_origin().x) + (self.sin_theta * ray.get_origin().y);
+        origin[1] = (-self.sin_theta * ray.get_origin().x) + (self.cos_theta * ray.get_origin().y);
         dir[0] =
-            (self.cos_theta * ray.get_direction().x) - (self.sin_theta * ray.get_direction().y);
+            (self.cos_theta * ray.get_direction().x) + (self.sin_theta * ray.get_direction().y);
         dir[1] =
-            (self.sin_theta * ray.get_direction().x) + (self.cos_theta * ray.get_direction().y);
-        let rotated_ray = Ray::new(
+            (-self.sin_theta * ray.get_direction().x) + (self.cos_theta * ray.get_direction().y);
+        let rotated_ray = Ray::new_at_time(
             vec3(origin[0], origin[1], origin[2]),
             vec3(dir[0], dir[1], dir[2]),
+            ray.get_time(),
             stat,
         );
         if let Some(hit_record) = self.hitable.hit(&rotated_ray, t_min, t_max, stat) {
             let mut p = to_single_array(hit_record.get_p());
             let mut normal = to_single_array(hit_record.get_normal());
             p[0] =
-                (self.cos_theta * hit_record.get_p().x) + (self.sin_theta * hit_record.get_p().y);
+                (self.cos_theta * hit_record.get_p().x) - (self.sin_theta * hit_record.get_p().y);
             p[1] =
-                (-self.sin_theta * hit_record.get_p().x) + (self.cos_theta * hit_record.get_p().y);
+                (self.sin_theta * hit_record.get_p().x) + (self.cos_theta * hit_record.get_p().y);
             normal[0] = (self.cos_theta * hit_record.get_normal().x)
-                + (self.sin_theta * hit_record.get_normal().y);
-            normal[1] = (-self.sin_theta * hit_record.get_normal().x)
+                - (self.sin_theta * hit_record.get_normal().y);
+            normal[1] = (self.sin_theta * hit_record.get_normal().x)
                 + (self.cos_theta * hit_record.get_normal().y);
 
-            return Some(HitRecord::new(
-                hit_record.get_t(),
-                vec3(p[0], p[1], p[2]),
-                vec3(normal[0], normal[1], normal[2]),
-                0.0, // todo: distance_squared
-                hit_record.get_material_id(),
-                hit_record.get_uv_coords(),
-            ));
+            return Some(
+                HitRecord::new(
+                    hit_record.get_t(),
+                    vec3(p[0], p[1], p[2]),
+                    vec3(normal[0], normal[1], normal[2]),
+                    0.0, // todo: distance_squared
+                    hit_record.get_material_id(),
+                    hit_record.get_uv_coords(),
+                )
+                .with_vertex_color(hit_record.get_vertex_color()),
+            );
         }
 
         None
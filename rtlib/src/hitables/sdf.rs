@@ -0,0 +1,336 @@
+use crate::hitables::{clip_slabs, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+// Sphere tracing gives up after this many steps, rays grazing a surface can take many.
+const MAX_STEPS: usize = 512;
+
+/// A tree of signed distance functions: negative inside, positive outside.  Shapes
+/// built around an axis use the z axis, like the analytic shapes.
+pub enum SdfNode {
+    Sphere {
+        radius: f32,
+    },
+    /// A box with its edges rounded by `radius`, zero gives sharp edges.
+    RoundBox {
+        half_size: Vector3<f32>,
+        radius: f32,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// A sphere swept along the segment from `a` to `b`.
+    Capsule {
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        radius: f32,
+    },
+    /// The power 8 Mandelbulb fractal (or any other power) by its distance estimate.
+    /// It fits in a sphere of radius 1.2.
+    Mandelbulb {
+        power: f32,
+        iterations: u32,
+    },
+    Union {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+    },
+    Intersection {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+    },
+    Difference {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+    },
+    /// Unions that blend where the shapes are within `k` of each other, which is what
+    /// makes metaballs.
+    SmoothUnion {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+        k: f32,
+    },
+    SmoothIntersection {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+        k: f32,
+    },
+    SmoothDifference {
+        left: Box<SdfNode>,
+        right: Box<SdfNode>,
+        k: f32,
+    },
+    Translate {
+        offset: Vector3<f32>,
+        node: Box<SdfNode>,
+    },
+    Scale {
+        factor: f32,
+        node: Box<SdfNode>,
+    },
+    /// Repeats the node in cells of `period`, centered on the origin.  A zero component
+    /// leaves that axis alone.  The copies shouldn't reach out of their cells.
+    Repeat {
+        period: Vector3<f32>,
+        node: Box<SdfNode>,
+    },
+    /// Turns the node `rate` radians around the z axis per unit of z.
+    Twist {
+        rate: f32,
+        node: Box<SdfNode>,
+    },
+}
+
+// Polynomial smooth minimum, and the weight it gives to `a`.
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + (0.5 * (b - a) / k)).clamp(0.0, 1.0);
+    (b + (h * (a - b))) - (k * h * (1.0 - h))
+}
+
+fn repeat_axis(p: f32, period: f32) -> f32 {
+    if period > 0.0 {
+        p - (period * (p / period).round())
+    } else {
+        p
+    }
+}
+
+impl SdfNode {
+    pub fn distance(&self, p: Vector3<f32>) -> f32 {
+        match self {
+            SdfNode::Sphere { radius } => p.magnitude() - radius,
+            SdfNode::RoundBox { half_size, radius } => {
+                let q = vec3(
+                    p.x.abs() - (half_size.x - radius),
+                    p.y.abs() - (half_size.y - radius),
+                    p.z.abs() - (half_size.z - radius),
+                );
+                let outside = vec3(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).magnitude();
+                let inside = q.x.max(q.y).max(q.z).min(0.0);
+                (outside + inside) - radius
+            }
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = ((p.x * p.x) + (p.y * p.y)).sqrt() - major_radius;
+                ((ring * ring) + (p.z * p.z)).sqrt() - minor_radius
+            }
+            SdfNode::Capsule { a, b, radius } => {
+                let pa = p - a;
+                let ba = b - a;
+                let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+                (pa - (ba * h)).magnitude() - radius
+            }
+            SdfNode::Mandelbulb { power, iterations } => mandelbulb(p, *power, *iterations),
+            SdfNode::Union { left, right } => left.distance(p).min(right.distance(p)),
+            SdfNode::Intersection { left, right } => left.distance(p).max(right.distance(p)),
+            SdfNode::Difference { left, right } => left.distance(p).max(-right.distance(p)),
+            SdfNode::SmoothUnion { left, right, k } => {
+                smooth_min(left.distance(p), right.distance(p), *k)
+            }
+            SdfNode::SmoothIntersection { left, right, k } => {
+                -smooth_min(-left.distance(p), -right.distance(p), *k)
+            }
+            SdfNode::SmoothDifference { left, right, k } => {
+                -smooth_min(-left.distance(p), right.distance(p), *k)
+            }
+            SdfNode::Translate { offset, node } => node.distance(p - offset),
+            // a negative factor mirrors through the origin, the inside stays inside
+            SdfNode::Scale { factor, node } => node.distance(p / *factor) * factor.abs(),
+            SdfNode::Repeat { period, node } => node.distance(vec3(
+                repeat_axis(p.x, period.x),
+                repeat_axis(p.y, period.y),
+                repeat_axis(p.z, period.z),
+            )),
+            SdfNode::Twist { rate, node } => {
+                let (sin, cos) = (-rate * p.z).sin_cos();
+                node.distance(vec3(
+                    (cos * p.x) - (sin * p.y),
+                    (sin * p.x) + (cos * p.y),
+                    p.z,
+                ))
+            }
+        }
+    }
+
+    // How much faster than the distance to the surface the function can change for
+    // points within `radius` of the origin.  Sphere tracing divides its steps by this so
+    // they don't overshoot where a node (like a twist) stretches space.
+    fn lipschitz(&self, radius: f32) -> f32 {
+        match self {
+            SdfNode::Union { left, right }
+            | SdfNode::Intersection { left, right }
+            | SdfNode::Difference { left, right }
+            | SdfNode::SmoothUnion { left, right, .. }
+            | SdfNode::SmoothIntersection { left, right, .. }
+            | SdfNode::SmoothDifference { left, right, .. } => {
+                left.lipschitz(radius).max(right.lipschitz(radius))
+            }
+            SdfNode::Translate { offset, node } => node.lipschitz(radius + offset.magnitude()),
+            SdfNode::Scale { factor, node } => node.lipschitz(radius / factor.abs()),
+            SdfNode::Repeat { period, node } => node.lipschitz(radius.min(period.magnitude())),
+            SdfNode::Twist { rate, node } => {
+                let stretch = rate * radius;
+                node.lipschitz(radius) * (1.0 + (stretch * stretch)).sqrt()
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+fn mandelbulb(p: Vector3<f32>, power: f32, iterations: u32) -> f32 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = z.magnitude();
+    for _ in 0..iterations {
+        if r > 2.0 || r == 0.0 {
+            break;
+        }
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = (r.powf(power - 1.0) * power * dr) + 1.0;
+        let zr = r.powf(power);
+        z = (vec3(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ) * zr)
+            + p;
+        r = z.magnitude();
+    }
+    if r == 0.0 {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+/// A surface given by a distance function, found by sphere tracing: the ray advances by
+/// the distance to the nearest surface until it is close enough to count as a hit.  Only
+/// the part of the ray inside the declared bounds is traced, so they must contain the
+/// whole surface.  Normals come from the gradient of the distance.
+pub struct Sdf {
+    root: SdfNode,
+    material_id: u64,
+    // distance below which a point is on the surface
+    epsilon: f32,
+    lipschitz: f32,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Sdf {
+    pub fn new(
+        root: SdfNode,
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let radius = min.magnitude().max(max.magnitude());
+        Arc::new(Box::new(Sdf {
+            lipschitz: root.lipschitz(radius),
+            root,
+            material_id,
+            epsilon: (max - min).magnitude() * 1e-5,
+            bounding_box: AABB::new(min, max),
+        }))
+    }
+
+    fn get_normal(&self, p: Vector3<f32>) -> Vector3<f32> {
+        // tetrahedral central differences, four evaluations instead of six
+        let h = self.epsilon;
+        let k0 = vec3(1.0, -1.0, -1.0);
+        let k1 = vec3(-1.0, -1.0, 1.0);
+        let k2 = vec3(-1.0, 1.0, -1.0);
+        let k3 = vec3(1.0, 1.0, 1.0);
+        let gradient = (k0 * self.root.distance(p + (k0 * h)))
+            + (k1 * self.root.distance(p + (k1 * h)))
+            + (k2 * self.root.distance(p + (k2 * h)))
+            + (k3 * self.root.distance(p + (k3 * h)));
+        if gradient.magnitude2() == 0.0 {
+            return vec3(0.0, 0.0, 1.0);
+        }
+        to_unit_vector(gradient)
+    }
+}
+
+impl fmt::Display for Sdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[Sdf(aabb: <{}>)]", self.bounding_box)
+    }
+}
+
+impl Hitable for Sdf {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("sdf::hit()");
+        stat.sdf_hit();
+
+        let (t_start, t_end) = clip_slabs(
+            self.bounding_box.min,
+            self.bounding_box.max,
+            ray,
+            t_min,
+            t_max,
+        )?;
+        let length = ray.get_direction().magnitude();
+        if length == 0.0 {
+            return None;
+        }
+
+        // Rays leaving the surface start right on it.  They only count a hit once they
+        // have been clear of it, or they would hit where they started.
+        let mut t = t_start;
+        let mut clear =
+            self.root.distance(ray.get_point_at_parameter(t)).abs() > 2.0 * self.epsilon;
+        for _ in 0..MAX_STEPS {
+            if t > t_end {
+                return None;
+            }
+            let p = ray.get_point_at_parameter(t);
+            let distance = self.root.distance(p).abs();
+            if distance < self.epsilon && clear {
+                let normal = self.get_normal(p);
+                return Some(HitRecord::new(
+                    t,
+                    p,
+                    normal,
+                    0.0, // todo: distance_squared
+                    self.material_id,
+                    Point2::new(
+                        0.5 + (normal.z.atan2(normal.x) / (2.0 * f32::consts::PI)),
+                        0.5 + (normal.y.asin() / f32::consts::PI),
+                    ),
+                ));
+            }
+            if distance > 2.0 * self.epsilon {
+                clear = true;
+            }
+            t += distance.max(self.epsilon) / (self.lipschitz * length);
+        }
+
+        None
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
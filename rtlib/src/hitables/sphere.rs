@@ -52,6 +52,36 @@ impl Sphere {
     pub fn get_material_id(&self) -> u64 {
         self.material_id
     }
+
+    // Where the ray crosses the sphere, nearest first.
+    fn get_roots(&self, ray: &Ray) -> Option<[f32; 2]> {
+        let oc = ray.get_origin() - self.center();
+        let a = ray.get_direction().dot(ray.get_direction());
+        let b = oc.dot(ray.get_direction());
+        let c = oc.dot(oc) - (self.radius_sq);
+        let discriminant = (b * b) - (a * c);
+        if discriminant <= 0.0 {
+            return None;
+        }
+
+        let disc_sqrt = discriminant.sqrt();
+        Some([(-b - disc_sqrt) / a, (-b + disc_sqrt) / a])
+    }
+
+    fn to_hit_record(&self, ray: &Ray, t: f32) -> HitRecord {
+        let p = ray.get_point_at_parameter(t);
+        // The length p - c would be the radius
+        let normal = (p - self.center()) / self.radius();
+
+        HitRecord::new(
+            t,
+            p,
+            normal,
+            0.0, // todo: distance_squared
+            self.get_material_id(),
+            self.get_sphere_uv(p),
+        )
+    }
 }
 
 impl fmt::Display for Sphere {
@@ -69,46 +99,24 @@ impl Hitable for Sphere {
         info!("sphere::hit()");
         stat.sphere_hit();
 
-        let oc = ray.get_origin() - self.center();
-        let a = ray.get_direction().dot(ray.get_direction());
-        let b = oc.dot(ray.get_direction());
-        let c = oc.dot(oc) - (self.radius_sq);
-        let discriminant = (b * b) - (a * c);
+        self.get_roots(ray)?
+            .iter()
+            .find(|t| **t < t_max && **t > t_min)
+            .map(|t| self.to_hit_record(ray, *t))
+    }
 
-        if discriminant > 0.0 {
-            let disc_sqrt = discriminant.sqrt();
-            let t = (-b - disc_sqrt) / a;
-            if t < t_max && t > t_min {
-                let p = ray.get_point_at_parameter(t);
-                // The length p - c would be the radius
-                let normal = (p - self.center()) / self.radius();
-
-                return Some(HitRecord::new(
-                    t,
-                    p,
-                    normal,
-                    0.0, // todo: distance_squared
-                    self.get_material_id(),
-                    self.get_sphere_uv(p),
-                ));
-            }
-            let t: f32 = (-b + disc_sqrt) / a;
-            if t < t_max && t > t_min {
-                let p = ray.get_point_at_parameter(t);
-                // The length p - c would be the radius
-                let normal = (p - self.center()) / self.radius();
-
-                return Some(HitRecord::new(
-                    t,
-                    p,
-                    normal,
-                    0.0, // todo: distance_squared
-                    self.get_material_id(),
-                    self.get_sphere_uv(p),
-                ));
-            }
+    fn hit_all(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Vec<HitRecord> {
+        info!("sphere::hit_all()");
+        stat.sphere_hit();
+
+        match self.get_roots(ray) {
+            Some(roots) => roots
+                .iter()
+                .filter(|t| **t < t_max && **t > t_min)
+                .map(|t| self.to_hit_record(ray, *t))
+                .collect(),
+            None => vec![],
         }
-        None
     }
 
     fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
@@ -0,0 +1,138 @@
+use crate::hitables::polynomial::solve_quartic;
+use crate::hitables::{get_phi, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+/// A torus around the z axis: a tube of `minor_radius` around the circle of
+/// `major_radius` in the xy plane, swept `phi_max` degrees.  u goes around the z axis
+/// and v around the tube.  Place it with a `Transform`.
+pub struct Torus {
+    major_radius: f32,
+    minor_radius: f32,
+    phi_max: f32,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Torus {
+    pub fn new(
+        major_radius: f32,
+        minor_radius: f32,
+        phi_max: f32,
+        material_id: u64,
+    ) -> ThreadHitable {
+        let extent = major_radius + minor_radius;
+        let bounding_box = AABB::new(
+            vec3(-extent, -extent, -minor_radius),
+            vec3(extent, extent, minor_radius),
+        );
+
+        Arc::new(Box::new(Torus {
+            major_radius,
+            minor_radius,
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians(),
+            material_id,
+            bounding_box,
+        }))
+    }
+
+    // Parameters of the ray's intersections with the whole torus, in ascending order.
+    fn intersect(&self, ray: &Ray) -> Vec<f32> {
+        let length = f64::from(ray.get_direction().magnitude());
+        if length == 0.0 {
+            return vec![];
+        }
+        let d = ray.get_direction().cast::<f64>().unwrap() / length;
+        let o = ray.get_origin().cast::<f64>().unwrap();
+
+        // Start from the point nearest the torus, far away origins make the quartic's
+        // coefficients huge and its roots imprecise.
+        let major = f64::from(self.major_radius);
+        let minor = f64::from(self.minor_radius);
+        let shift = ((-o.dot(d)) - (major + minor)).max(0.0);
+        let o = o + (d * shift);
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2) with p = o + t * d and |d| = 1
+        let four_major_sq = 4.0 * major * major;
+        let e = o.dot(o) + (major * major) - (minor * minor);
+        let f = o.dot(d);
+        let c3 = 4.0 * f;
+        let c2 = (2.0 * e) + (4.0 * f * f) - (four_major_sq * ((d.x * d.x) + (d.y * d.y)));
+        let c1 = (4.0 * f * e) - (2.0 * four_major_sq * ((o.x * d.x) + (o.y * d.y)));
+        let c0 = (e * e) - (four_major_sq * ((o.x * o.x) + (o.y * o.y)));
+
+        solve_quartic(1.0, c3, c2, c1, c0)
+            .into_iter()
+            .map(|t| ((t + shift) / length) as f32)
+            .collect()
+    }
+}
+
+impl fmt::Display for Torus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Torus(major_radius: {}, minor_radius: {}, phi_max: {})]",
+            self.major_radius,
+            self.minor_radius,
+            self.phi_max.to_degrees()
+        )
+    }
+}
+
+impl Hitable for Torus {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("torus::hit()");
+        stat.torus_hit();
+
+        for t in self.intersect(ray) {
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = ray.get_point_at_parameter(t);
+            let phi = get_phi(p.x, p.y);
+            if phi > self.phi_max {
+                continue;
+            }
+
+            // the normal points away from the nearest point of the center circle
+            let distance_to_axis = ((p.x * p.x) + (p.y * p.y)).sqrt();
+            let center = if distance_to_axis > 0.0 {
+                vec3(p.x, p.y, 0.0) * (self.major_radius / distance_to_axis)
+            } else {
+                vec3(self.major_radius, 0.0, 0.0)
+            };
+            let theta = get_phi(distance_to_axis - self.major_radius, p.z);
+            return Some(HitRecord::new(
+                t,
+                p,
+                to_unit_vector(p - center),
+                0.0, // todo: distance_squared
+                self.material_id,
+                Point2::new(phi / self.phi_max, theta / (2.0 * f32::consts::PI)),
+            ));
+        }
+
+        None
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(
+        &self,
+        _origin: Vector3<f32>,
+        _v: Vector3<f32>,
+        _stat: &mut RenderStats,
+    ) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
+        vec3(1.0, 0.0, 0.0)
+    }
+}
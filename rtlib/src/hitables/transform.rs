@@ -0,0 +1,162 @@
+use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, Matrix4, Vector3};
+use cgmath::{Matrix, SquareMatrix};
+use std::fmt;
+use std::sync::Arc;
+
+/// Places a hitable with an affine matrix: any mix of translation, rotation about any
+/// axis, non-uniform scale and shear.  Placements compose by multiplying their matrices
+/// (`outer * inner`) before wrapping, so a whole chain costs one wrapper.
+pub struct Transform {
+    hitable: ThreadHitable,
+    object_to_world: Matrix4<f32>,
+    world_to_object: Matrix4<f32>,
+    // inverse transpose, keeps normals perpendicular under non-uniform scale and shear
+    normal_to_world: Matrix4<f32>,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl Transform {
+    /// The matrix must be affine and invertible.
+    pub fn new(hitable: ThreadHitable, object_to_world: Matrix4<f32>) -> ThreadHitable {
+        Arc::new(Box::new(Transform::build(hitable, object_to_world)))
+    }
+
+    pub(crate) fn build(hitable: ThreadHitable, object_to_world: Matrix4<f32>) -> Transform {
+        let world_to_object = object_to_world
+            .invert()
+            .expect("transform matrix must be invertible");
+        let bounding_box =
+            transform_bounding_box(&object_to_world, &hitable.get_bounding_box(0.0, 1.0));
+
+        Transform {
+            hitable,
+            object_to_world,
+            world_to_object,
+            normal_to_world: world_to_object.transpose(),
+            bounding_box,
+        }
+    }
+
+    pub fn get_matrix(&self) -> Matrix4<f32> {
+        self.object_to_world
+    }
+
+    // The direction isn't normalized, so t is the same in both spaces.
+    fn to_object_ray(&self, ray: &Ray, stat: &mut RenderStats) -> Ray {
+        Ray::new_at_time(
+            transform_point(&self.world_to_object, ray.get_origin()),
+            transform_vector(&self.world_to_object, ray.get_direction()),
+            ray.get_time(),
+            stat,
+        )
+    }
+
+    pub(crate) fn hit_transformed(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stat: &mut RenderStats,
+    ) -> Option<HitRecord> {
+        let object_ray = self.to_object_ray(ray, stat);
+        self.hitable.hit(&object_ray, t_min, t_max, stat).map(|hr| {
+            HitRecord::new(
+                hr.get_t(),
+                ray.get_point_at_parameter(hr.get_t()),
+                to_unit_vector(transform_vector(&self.normal_to_world, hr.get_normal())),
+                0.0, // todo: distance_squared
+                hr.get_material_id(),
+                hr.get_uv_coords(),
+            )
+            .with_vertex_color(hr.get_vertex_color())
+        })
+    }
+
+    pub(crate) fn any_hit_transformed(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stat: &mut RenderStats,
+    ) -> bool {
+        let object_ray = self.to_object_ray(ray, stat);
+        self.hitable.any_hit(&object_ray, t_min, t_max, stat)
+    }
+}
+
+pub(crate) fn transform_point(m: &Matrix4<f32>, p: Vector3<f32>) -> Vector3<f32> {
+    (m * p.extend(1.0)).truncate()
+}
+
+pub(crate) fn transform_vector(m: &Matrix4<f32>, v: Vector3<f32>) -> Vector3<f32> {
+    (m * v.extend(0.0)).truncate()
+}
+
+// Arvo's method: each output extent is the translation plus, per input axis, whichever
+// end of the input box contributes less (for min) or more (for max).  Gives the same box
+// as transforming all eight corners, without doing so.
+pub(crate) fn transform_bounding_box(m: &Matrix4<f32>, b: &AABB) -> Arc<Box<AABB>> {
+    let b_min = [b.min.x, b.min.y, b.min.z];
+    let b_max = [b.max.x, b.max.y, b.max.z];
+    let mut min = [m.w.x, m.w.y, m.w.z];
+    let mut max = min;
+    for i in 0..3 {
+        for j in 0..3 {
+            // cgmath matrices are column major, m[j][i] is row i, column j
+            let a = m[j][i] * b_min[j];
+            let b = m[j][i] * b_max[j];
+            min[i] += a.min(b);
+            max[i] += a.max(b);
+        }
+    }
+    AABB::new(vec3(min[0], min[1], min[2]), vec3(max[0], max[1], max[2]))
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[Transform({}, matrix: {:?})]",
+            self.hitable, self.object_to_world
+        )
+    }
+}
+
+impl Hitable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("transform::hit()");
+        stat.transform_hit();
+        self.hit_transformed(ray, t_min, t_max, stat)
+    }
+
+    fn any_hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> bool {
+        info!("transform::any_hit()");
+        stat.transform_hit();
+        self.any_hit_transformed(ray, t_min, t_max, stat)
+    }
+
+    // Sampling happens in object space.  The pdf is only exact for transforms that
+    // preserve angles (rotation, translation and uniform scale).
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        self.hitable.get_pdf_value(
+            transform_point(&self.world_to_object, origin),
+            transform_vector(&self.world_to_object, v),
+            stat,
+        )
+    }
+
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        transform_vector(
+            &self.object_to_world,
+            self.hitable
+                .random(transform_point(&self.world_to_object, origin)),
+        )
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+}
@@ -32,20 +32,24 @@ impl fmt::Display for Translate {
 impl Hitable for Translate {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
         info!("translate::hit()");
-        let moved_ray = Ray::new(
+        let moved_ray = Ray::new_at_time(
             ray.get_origin() - self.displacement,
             ray.get_direction(),
+            ray.get_time(),
             stat,
         );
         if let Some(hr) = self.hitable.hit(&moved_ray, t_min, t_max, stat) {
-            return Some(HitRecord::new(
-                hr.get_t(),
-                hr.get_p() + self.displacement,
-                hr.get_normal(),
-                0.0, // todo: distance_squared
-                hr.get_material_id(),
-                hr.get_uv_coords(),
-            ));
+            return Some(
+                HitRecord::new(
+                    hr.get_t(),
+                    hr.get_p() + self.displacement,
+                    hr.get_normal(),
+                    0.0, // todo: distance_squared
+                    hr.get_material_id(),
+                    hr.get_uv_coords(),
+                )
+                .with_vertex_color(hr.get_vertex_color()),
+            );
         }
 
         None
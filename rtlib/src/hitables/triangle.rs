@@ -1,4 +1,5 @@
-use crate::hitables::{HitRecord, Hitable, ThreadHitable, AABB};
+use crate::hitables::{get_area_pdf_value, HitRecord, Hitable, ThreadHitable, AABB};
+use crate::next_rand_f32;
 use crate::render::Ray;
 use crate::stats::RenderStats;
 use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
@@ -8,6 +9,7 @@ use std::{f32, fmt};
 pub struct Triangle {
     vertices: Vec<Vector3<f32>>,
     surface_normal: Vector3<f32>,
+    area: f32,
     material_id: u64,
 }
 
@@ -17,12 +19,12 @@ impl Triangle {
             panic!("triangle must have exactly 3 vertices");
         }
 
-        let surface_normal =
-            to_unit_vector((vertices[2] - vertices[1]).cross(vertices[1] - vertices[0]));
+        let n = (vertices[2] - vertices[1]).cross(vertices[1] - vertices[0]);
 
         Arc::new(Box::new(Triangle {
             vertices,
-            surface_normal,
+            surface_normal: to_unit_vector(n),
+            area: 0.5 * n.magnitude(),
             material_id,
         }))
     }
@@ -49,7 +51,7 @@ impl Hitable for Triangle {
         let pvec = dir.cross(e2);
         let det = e1.dot(pvec);
 
-        if det > -0.0001 && det < 0.0001 {
+        if det.abs() < 1.0e-12 {
             return None;
         }
 
@@ -109,16 +111,24 @@ impl Hitable for Triangle {
         AABB::new(min, max)
     }
 
-    fn get_pdf_value(
-        &self,
-        _origin: Vector3<f32>,
-        _v: Vector3<f32>,
-        _stat: &mut RenderStats,
-    ) -> f32 {
-        1.0
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        get_area_pdf_value(self, self.area, origin, v, stat)
     }
 
-    fn random(&self, _origin: Vector3<f32>) -> Vector3<f32> {
-        Vector3::unit_x()
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        random_on_triangle(self.vertices[0], self.vertices[1], self.vertices[2]) - origin
     }
 }
+
+/// A point picked uniformly over the area of the triangle.  The square root keeps the
+/// density even, without it points would bunch up at the first vertex.
+pub(crate) fn random_on_triangle(
+    v0: Vector3<f32>,
+    v1: Vector3<f32>,
+    v2: Vector3<f32>,
+) -> Vector3<f32> {
+    let su = next_rand_f32().sqrt();
+    let b0 = 1.0 - su;
+    let b1 = next_rand_f32() * su;
+    (b0 * v0) + (b1 * v1) + ((1.0 - b0 - b1) * v2)
+}
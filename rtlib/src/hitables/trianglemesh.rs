@@ -0,0 +1,507 @@
+use crate::hitables::{
+    get_area_pdf_value, random_on_triangle, traverse_flat_bvh, FlatBvhNode, HitRecord, Hitable,
+    ThreadHitable, AABB,
+};
+use crate::next_rand_f32;
+use crate::render::{Color, Ray};
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Point2, Vector3};
+use std::sync::Arc;
+use std::{f32, fmt};
+
+const MAX_FACES_PER_LEAF: usize = 4;
+
+/// Indices of one triangle into the shared position, normal and uv arrays of a mesh.
+/// Normals and uvs are indexed separately so formats like OBJ can be loaded without
+/// duplicating vertices.
+#[derive(Copy, Clone, Debug)]
+pub struct MeshFace {
+    pub vertices: [u32; 3],
+    pub normals: Option<[u32; 3]>,
+    pub uvs: Option<[u32; 3]>,
+}
+
+impl MeshFace {
+    pub fn new(vertices: [u32; 3], normals: Option<[u32; 3]>, uvs: Option<[u32; 3]>) -> MeshFace {
+        MeshFace {
+            vertices,
+            normals,
+            uvs,
+        }
+    }
+}
+
+struct FaceHit {
+    t: f32,
+    u: f32,
+    v: f32,
+    face: usize,
+}
+
+// Everything is behind an Arc so a clone is cheap and shares the geometry and its BVH.
+#[derive(Clone)]
+pub struct TriangleMesh {
+    positions: Arc<Vec<Vector3<f32>>>,
+    normals: Arc<Vec<Vector3<f32>>>,
+    uvs: Arc<Vec<Point2<f32>>>,
+    colors: Arc<Vec<Color>>,
+    faces: Arc<Vec<MeshFace>>,
+    // running total of the face areas, for picking faces when sampled as a light
+    cumulative_areas: Arc<Vec<f32>>,
+    nodes: Arc<Vec<FlatBvhNode>>,
+    material_id: u64,
+    bounding_box: Arc<Box<AABB>>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Arc<Vec<Vector3<f32>>>,
+        normals: Arc<Vec<Vector3<f32>>>,
+        uvs: Arc<Vec<Point2<f32>>>,
+        faces: Vec<MeshFace>,
+        material_id: u64,
+    ) -> ThreadHitable {
+        Arc::new(Box::new(TriangleMesh::build(
+            positions,
+            normals,
+            uvs,
+            faces,
+            material_id,
+        )))
+    }
+
+    /// Same as `new` but hands back the concrete mesh so it can be inspected before it is
+    /// placed in a scene.
+    pub fn build(
+        positions: Arc<Vec<Vector3<f32>>>,
+        normals: Arc<Vec<Vector3<f32>>>,
+        uvs: Arc<Vec<Point2<f32>>>,
+        mut faces: Vec<MeshFace>,
+        material_id: u64,
+    ) -> TriangleMesh {
+        if faces.is_empty() {
+            panic!("triangle mesh must have at least one face");
+        }
+
+        let nodes = build_mesh_bvh(&positions, &mut faces);
+        let bounding_box = AABB::new(nodes[0].min, nodes[0].max);
+
+        let mut total_area = 0.0;
+        let cumulative_areas = faces
+            .iter()
+            .map(|face| {
+                let v0 = positions[face.vertices[0] as usize];
+                let v1 = positions[face.vertices[1] as usize];
+                let v2 = positions[face.vertices[2] as usize];
+                total_area += 0.5 * (v1 - v0).cross(v2 - v0).magnitude();
+                total_area
+            })
+            .collect();
+
+        TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            colors: Arc::new(vec![]),
+            faces: Arc::new(faces),
+            cumulative_areas: Arc::new(cumulative_areas),
+            nodes: Arc::new(nodes),
+            material_id,
+            bounding_box,
+        }
+    }
+
+    /// Attaches per-vertex colors, indexed like the positions.
+    pub fn with_colors(mut self, colors: Arc<Vec<Color>>) -> TriangleMesh {
+        self.colors = colors;
+        self
+    }
+
+    /// A copy of this mesh that shares all of its data but uses another material.
+    pub fn with_material(&self, material_id: u64) -> TriangleMesh {
+        let mut mesh = self.clone();
+        mesh.material_id = material_id;
+        mesh
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// The total area of the faces.
+    pub fn get_area(&self) -> f32 {
+        self.cumulative_areas[self.cumulative_areas.len() - 1]
+    }
+
+    /// Generates smooth vertex normals for faces that have none.  Normals of neighbouring
+    /// faces are area weighted and only averaged when the faces meet at less than
+    /// `crease_angle` degrees, so hard edges stay sharp.  The returned normals are indexed
+    /// by the `normals` member that is filled in on each face.
+    pub fn smooth_normals(
+        positions: &[Vector3<f32>],
+        faces: &mut [MeshFace],
+        crease_angle: f32,
+    ) -> Vec<Vector3<f32>> {
+        let cos_crease = (crease_angle * f32::consts::PI / 180.0).cos();
+
+        // area weighted (unnormalized) face normals
+        let face_normals: Vec<Vector3<f32>> = faces
+            .iter()
+            .map(|face| {
+                let v0 = positions[face.vertices[0] as usize];
+                let v1 = positions[face.vertices[1] as usize];
+                let v2 = positions[face.vertices[2] as usize];
+                (v2 - v1).cross(v1 - v0)
+            })
+            .collect();
+
+        // vertex -> adjacent faces, stored compactly as offsets into one array
+        let mut adjacency_offsets = vec![0_usize; positions.len() + 1];
+        for face in faces.iter() {
+            for vertex in face.vertices.iter() {
+                adjacency_offsets[*vertex as usize + 1] += 1;
+            }
+        }
+        for i in 1..adjacency_offsets.len() {
+            adjacency_offsets[i] += adjacency_offsets[i - 1];
+        }
+        let mut fill = adjacency_offsets.clone();
+        let mut adjacency = vec![0_u32; adjacency_offsets[positions.len()]];
+        for (face_index, face) in faces.iter().enumerate() {
+            for vertex in face.vertices.iter() {
+                adjacency[fill[*vertex as usize]] = face_index as u32;
+                fill[*vertex as usize] += 1;
+            }
+        }
+
+        // The common case is a vertex where all faces are smooth, so start with one
+        // normal per vertex and only add extra normals for corners on a crease.
+        let mut normals: Vec<Vector3<f32>> = (0..positions.len())
+            .map(|vertex| {
+                let adjacent = &adjacency[adjacency_offsets[vertex]..adjacency_offsets[vertex + 1]];
+                adjacent
+                    .iter()
+                    .fold(vec3(0.0, 0.0, 0.0), |sum, face_index| {
+                        sum + face_normals[*face_index as usize]
+                    })
+            })
+            .map(safe_unit_vector)
+            .collect();
+
+        for (face_index, face) in faces.iter_mut().enumerate() {
+            let this_normal = safe_unit_vector(face_normals[face_index]);
+            let mut corner_normals = [0_u32; 3];
+            for (corner, corner_normal) in corner_normals.iter_mut().enumerate() {
+                let vertex = face.vertices[corner] as usize;
+                let adjacent = &adjacency[adjacency_offsets[vertex]..adjacency_offsets[vertex + 1]];
+                let mut sum = vec3(0.0, 0.0, 0.0);
+                let mut is_creased = false;
+                for other in adjacent.iter() {
+                    let other_normal = face_normals[*other as usize];
+                    if safe_unit_vector(other_normal).dot(this_normal) >= cos_crease {
+                        sum += other_normal;
+                    } else {
+                        is_creased = true;
+                    }
+                }
+
+                *corner_normal = if is_creased {
+                    normals.push(safe_unit_vector(sum));
+                    (normals.len() - 1) as u32
+                } else {
+                    vertex as u32
+                };
+            }
+            face.normals = Some(corner_normals);
+        }
+
+        normals
+    }
+
+    fn get_vertices(&self, face: &MeshFace) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        (
+            self.positions[face.vertices[0] as usize],
+            self.positions[face.vertices[1] as usize],
+            self.positions[face.vertices[2] as usize],
+        )
+    }
+
+    fn hit_face(&self, face_index: usize, ray: &Ray, t_min: f32, t_max: f32) -> Option<FaceHit> {
+        let (v0, v1, v2) = self.get_vertices(&self.faces[face_index]);
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+        let dir = ray.get_direction();
+        let pvec = dir.cross(e2);
+        let det = e1.dot(pvec);
+
+        if det.abs() < 1.0e-12 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let tvec = ray.get_origin() - v0;
+        let u = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let qvec = tvec.cross(e1);
+        let v = dir.dot(qvec) * inv_det;
+        if v < 0.0 || (u + v) > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(qvec) * inv_det;
+        if t > t_min && t < t_max {
+            Some(FaceHit {
+                t,
+                u,
+                v,
+                face: face_index,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn to_hit_record(&self, ray: &Ray, face_hit: &FaceHit) -> HitRecord {
+        let face = &self.faces[face_hit.face];
+        let w = 1.0 - face_hit.u - face_hit.v;
+
+        let normal = match face.normals {
+            Some(n) => safe_unit_vector(
+                (w * self.normals[n[0] as usize])
+                    + (face_hit.u * self.normals[n[1] as usize])
+                    + (face_hit.v * self.normals[n[2] as usize]),
+            ),
+            None => {
+                let (v0, v1, v2) = self.get_vertices(face);
+                to_unit_vector((v2 - v1).cross(v1 - v0))
+            }
+        };
+
+        let uv_coords = match face.uvs {
+            Some(uv) => {
+                let uv0 = self.uvs[uv[0] as usize];
+                let uv1 = self.uvs[uv[1] as usize];
+                let uv2 = self.uvs[uv[2] as usize];
+                Point2::new(
+                    (w * uv0.x) + (face_hit.u * uv1.x) + (face_hit.v * uv2.x),
+                    (w * uv0.y) + (face_hit.u * uv1.y) + (face_hit.v * uv2.y),
+                )
+            }
+            None => Point2::new(face_hit.u, face_hit.v),
+        };
+
+        let vertex_color = if self.colors.is_empty() {
+            None
+        } else {
+            let c = face.vertices;
+            Some(
+                self.colors[c[0] as usize]
+                    .multiply_by_scalar(w)
+                    .add(self.colors[c[1] as usize].multiply_by_scalar(face_hit.u))
+                    .add(self.colors[c[2] as usize].multiply_by_scalar(face_hit.v)),
+            )
+        };
+
+        HitRecord::new(
+            face_hit.t,
+            ray.get_point_at_parameter(face_hit.t),
+            normal,
+            0.0, // todo: distance_squared
+            self.material_id,
+            uv_coords,
+        )
+        .with_vertex_color(vertex_color)
+    }
+}
+
+impl fmt::Display for TriangleMesh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[TriangleMesh(faces: {}, vertices: {}, aabb: {})]",
+            self.faces.len(),
+            self.positions.len(),
+            self.bounding_box
+        )
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, stat: &mut RenderStats) -> Option<HitRecord> {
+        info!("trianglemesh::hit()");
+        stat.triangle_mesh_hit();
+
+        let mut closest: Option<FaceHit> = None;
+        traverse_flat_bvh(
+            &self.nodes,
+            ray,
+            t_min,
+            t_max,
+            stat,
+            |range, mut closest_t, stat| {
+                for face_index in range {
+                    stat.triangle_hit();
+                    if let Some(face_hit) = self.hit_face(face_index, ray, t_min, closest_t) {
+                        closest_t = face_hit.t;
+                        closest = Some(face_hit);
+                    }
+                }
+                Some(closest_t)
+            },
+        );
+
+        closest.map(|face_hit| self.to_hit_record(ray, &face_hit))
+    }
+
+    fn get_bounding_box(&self, _t0: f32, _t1: f32) -> Arc<Box<AABB>> {
+        self.bounding_box.clone()
+    }
+
+    fn get_pdf_value(&self, origin: Vector3<f32>, v: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        get_area_pdf_value(self, self.get_area(), origin, v, stat)
+    }
+
+    // picks a face with probability proportional to its area, then a point on it
+    fn random(&self, origin: Vector3<f32>) -> Vector3<f32> {
+        let target = next_rand_f32() * self.get_area();
+        let face_index = self
+            .cumulative_areas
+            .partition_point(|area| *area <= target)
+            .min(self.faces.len() - 1);
+        let (v0, v1, v2) = self.get_vertices(&self.faces[face_index]);
+        random_on_triangle(v0, v1, v2) - origin
+    }
+}
+
+fn safe_unit_vector(v: Vector3<f32>) -> Vector3<f32> {
+    let magnitude = v.magnitude();
+    if magnitude > 0.0 {
+        v / magnitude
+    } else {
+        v
+    }
+}
+
+fn get_component(v: Vector3<f32>, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+struct FaceBuildInfo {
+    face: MeshFace,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    centroid: Vector3<f32>,
+}
+
+fn build_mesh_bvh(positions: &[Vector3<f32>], faces: &mut Vec<MeshFace>) -> Vec<FlatBvhNode> {
+    let mut infos: Vec<FaceBuildInfo> = faces
+        .iter()
+        .map(|face| {
+            let v0 = positions[face.vertices[0] as usize];
+            let v1 = positions[face.vertices[1] as usize];
+            let v2 = positions[face.vertices[2] as usize];
+            let min = vec3(
+                v0.x.min(v1.x).min(v2.x),
+                v0.y.min(v1.y).min(v2.y),
+                v0.z.min(v1.z).min(v2.z),
+            );
+            let max = vec3(
+                v0.x.max(v1.x).max(v2.x),
+                v0.y.max(v1.y).max(v2.y),
+                v0.z.max(v1.z).max(v2.z),
+            );
+            FaceBuildInfo {
+                face: *face,
+                min,
+                max,
+                centroid: (min + max) * 0.5,
+            }
+        })
+        .collect();
+
+    let mut nodes = Vec::with_capacity(2 * infos.len() / MAX_FACES_PER_LEAF + 1);
+    build_mesh_bvh_node(&mut infos, 0, &mut nodes);
+
+    *faces = infos.into_iter().map(|info| info.face).collect();
+    nodes
+}
+
+// Splits at the median of the longest centroid axis.  This keeps the tree balanced, so
+// the fixed size traversal stack can never overflow for a mesh that fits in memory.
+fn build_mesh_bvh_node(
+    infos: &mut [FaceBuildInfo],
+    first_face: usize,
+    nodes: &mut Vec<FlatBvhNode>,
+) -> usize {
+    let mut min = vec3(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = vec3(-f32::MAX, -f32::MAX, -f32::MAX);
+    let mut centroid_min = min;
+    let mut centroid_max = max;
+    for info in infos.iter() {
+        min = vec3(
+            min.x.min(info.min.x),
+            min.y.min(info.min.y),
+            min.z.min(info.min.z),
+        );
+        max = vec3(
+            max.x.max(info.max.x),
+            max.y.max(info.max.y),
+            max.z.max(info.max.z),
+        );
+        centroid_min = vec3(
+            centroid_min.x.min(info.centroid.x),
+            centroid_min.y.min(info.centroid.y),
+            centroid_min.z.min(info.centroid.z),
+        );
+        centroid_max = vec3(
+            centroid_max.x.max(info.centroid.x),
+            centroid_max.y.max(info.centroid.y),
+            centroid_max.z.max(info.centroid.z),
+        );
+    }
+
+    let node_index = nodes.len();
+    nodes.push(FlatBvhNode {
+        min,
+        max,
+        offset: first_face as u32,
+        count: infos.len() as u32,
+        axis: 0,
+    });
+
+    if infos.len() <= MAX_FACES_PER_LEAF {
+        return node_index;
+    }
+
+    let extent = centroid_max - centroid_min;
+    let axis = if extent.x > extent.y && extent.x > extent.z {
+        0
+    } else if extent.y > extent.z {
+        1
+    } else {
+        2
+    };
+
+    let mid = infos.len() / 2;
+    infos.select_nth_unstable_by(mid, |a, b| {
+        get_component(a.centroid, axis).total_cmp(&get_component(b.centroid, axis))
+    });
+
+    let (left, right) = infos.split_at_mut(mid);
+    build_mesh_bvh_node(left, first_face, nodes);
+    let right_index = build_mesh_bvh_node(right, first_face + mid, nodes);
+
+    let node = &mut nodes[node_index];
+    node.offset = right_index as u32;
+    node.count = 0;
+    node.axis = axis as u8;
+
+    node_index
+}
@@ -8,11 +8,12 @@ pub mod cameras;
 pub mod hitables;
 pub mod materials;
 pub mod pdfs;
+pub mod phasefunctions;
 pub mod render;
 pub mod textures;
 pub mod stats;
 
-pub use cgmath::{vec3, InnerSpace, Point2, Vector3};
+pub use cgmath::{vec3, InnerSpace, Matrix4, Point2, Vector3};
 
 use rand::Rng;
 use std::f32;
@@ -0,0 +1,167 @@
+use crate::hitables::HitRecord;
+use crate::materials::{fresnel_conductor, reflect_about, GgxDistribution};
+use crate::materials::{Material, ScatterResult, ThreadMaterial};
+use crate::pdfs::{MicrofacetReflectionPdf, OrthoNormalBase};
+use crate::render::{Color, Ray};
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, vec3, InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Metals with a known complex index of refraction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+}
+
+impl ConductorPreset {
+    /// `eta` and `k` of the complex index of refraction, per RGB channel.
+    pub fn get_ior(self) -> (Color, Color) {
+        match self {
+            ConductorPreset::Gold => (
+                Color::new(0.143, 0.374, 1.442),
+                Color::new(3.983, 2.385, 1.603),
+            ),
+            ConductorPreset::Copper => (
+                Color::new(0.200, 0.924, 1.102),
+                Color::new(3.912, 2.452, 2.142),
+            ),
+            ConductorPreset::Aluminium => (
+                Color::new(1.657, 0.880, 0.521),
+                Color::new(9.224, 6.270, 4.837),
+            ),
+        }
+    }
+}
+
+/// A metal with microfacets spread by a GGX distribution, from a mirror at roughness 0 to
+/// a dull sheen at 1.  Brushed metals are rougher across `brush_direction` than along
+/// it, the direction is projected onto the surface at each hit.  The color comes from
+/// the Fresnel reflectance of the complex index of refraction `eta` + i`k`, per RGB
+/// channel.  Either side of the surface reflects.
+pub struct ConductorMaterial {
+    eta: Color,
+    k: Color,
+    distribution: GgxDistribution,
+    brush_direction: Option<Vector3<f32>>,
+}
+
+impl ConductorMaterial {
+    pub fn new(eta: Color, k: Color, roughness: f32) -> ThreadMaterial {
+        Arc::new(Box::new(ConductorMaterial {
+            eta,
+            k,
+            distribution: GgxDistribution::from_roughness(roughness, roughness),
+            brush_direction: None,
+        }))
+    }
+
+    pub fn new_brushed(
+        eta: Color,
+        k: Color,
+        roughness_along: f32,
+        roughness_across: f32,
+        brush_direction: Vector3<f32>,
+    ) -> ThreadMaterial {
+        Arc::new(Box::new(ConductorMaterial {
+            eta,
+            k,
+            distribution: GgxDistribution::from_roughness(roughness_along, roughness_across),
+            brush_direction: Some(brush_direction),
+        }))
+    }
+
+    pub fn from_preset(preset: ConductorPreset, roughness: f32) -> ThreadMaterial {
+        let (eta, k) = preset.get_ior();
+        ConductorMaterial::new(eta, k, roughness)
+    }
+
+    // The shading frame with the normal turned toward the viewer, and the direction to
+    // the viewer in it.
+    fn get_local_frame(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+    ) -> (OrthoNormalBase, Vector3<f32>) {
+        let wo = -to_unit_vector(ray_in.get_direction());
+        let normal = hit_record.get_normal();
+        let normal = if normal.dot(wo) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        // the distribution's x axis is along the brushing
+        let uvw = match self.brush_direction {
+            Some(direction) => OrthoNormalBase::from_w_and_direction(normal, direction),
+            None => OrthoNormalBase::from_w(normal),
+        };
+        let local_wo = uvw.to_local(wo);
+        (uvw, local_wo)
+    }
+}
+
+impl Material for ConductorMaterial {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let (uvw, wo) = self.get_local_frame(ray_in, hit_record);
+        if wo.z <= 0.0 {
+            return Arc::new(Box::new(ScatterResult::new_false()));
+        }
+
+        if self.distribution.is_smooth() {
+            let reflected = uvw.local(reflect_about(wo, vec3(0.0, 0.0, 1.0)));
+            let specular_ray =
+                Ray::new_at_time(hit_record.get_p(), reflected, ray_in.get_time(), stat);
+            return Arc::new(Box::new(ScatterResult::new(
+                true,
+                fresnel_conductor(wo.z, self.eta, self.k),
+                Some(specular_ray),
+                None,
+            )));
+        }
+
+        // the Fresnel term depends on the direction picked, see scattering_color()
+        Arc::new(Box::new(ScatterResult::new(
+            true,
+            Color::one(),
+            None,
+            Some(Arc::new(MicrofacetReflectionPdf::new(
+                uvw,
+                -ray_in.get_direction(),
+                self.distribution,
+            ))),
+        )))
+    }
+
+    fn scattering_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        let (uvw, wo) = self.get_local_frame(ray_in, hit_record);
+        let wi = uvw.to_local(to_unit_vector(scattered.get_direction()));
+        self.distribution.get_reflection_value(wo, wi)
+    }
+
+    fn scattering_color(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        scatter_result: &ScatterResult,
+    ) -> Color {
+        let (uvw, wo) = self.get_local_frame(ray_in, hit_record);
+        let wi = uvw.to_local(to_unit_vector(scattered.get_direction()));
+        let value = self.distribution.get_reflection_value(wo, wi);
+        if value <= 0.0 {
+            return Color::zero();
+        }
+        let h = to_unit_vector(wo + wi);
+        fresnel_conductor(wo.dot(h), self.eta, self.k)
+            .multiply(scatter_result.get_attenuation())
+            .multiply_by_scalar(value)
+    }
+}
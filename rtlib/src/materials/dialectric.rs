@@ -1,43 +1,90 @@
 use crate::hitables::HitRecord;
-use crate::materials::{reflect, refract, Material, ScatterResult, ThreadMaterial};
+use crate::materials::{
+    fresnel_dielectric, get_transmission_half_vector, reflect, refract, GgxDistribution, Material,
+    ScatterResult, ThreadMaterial,
+};
 use crate::next_rand_f32;
+use crate::pdfs::{MicrofacetDielectricPdf, OrthoNormalBase};
 use crate::render::{Color, Ray};
 use crate::stats::RenderStats;
-use crate::{vec3, InnerSpace};
+use crate::{to_unit_vector, vec3, InnerSpace, Vector3};
 use std::f32;
 use std::sync::Arc;
 
+/// Glass, water and the like.  With a roughness above 0 the surface is frosted, made of
+/// GGX microfacets that each reflect and refract.  Light travelling inside is absorbed
+/// so that `absorption_color` is what is left of white light after
+/// `absorption_distance`, which tints thick parts more than thin ones.
 pub struct DialectricMaterial {
     refraction_index: f32,
+    distribution: GgxDistribution,
+    // per unit of distance, for each channel
+    absorption: Color,
 }
 
 impl DialectricMaterial {
     pub fn new(refraction_index: f32) -> ThreadMaterial {
-        Arc::new(Box::new(DialectricMaterial { refraction_index }))
+        DialectricMaterial::new_rough(refraction_index, 0.0, Color::one(), 1.0)
     }
 
-    fn calculate_schlick_approximation(cosine: f32, refraction_index: f32) -> f32 {
-        let mut r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
-        r0 = r0 * r0;
-        r0 + ((1.0 - r0) * (1.0 - cosine).powf(5.0))
+    pub fn new_rough(
+        refraction_index: f32,
+        roughness: f32,
+        absorption_color: Color,
+        absorption_distance: f32,
+    ) -> ThreadMaterial {
+        let absorption = |c: f32| -(c.clamp(1e-6, 1.0).ln()) / absorption_distance.max(1e-6);
+        Arc::new(Box::new(DialectricMaterial {
+            refraction_index,
+            distribution: GgxDistribution::from_roughness(roughness, roughness),
+            absorption: Color::new(
+                absorption(absorption_color.r()),
+                absorption(absorption_color.g()),
+                absorption(absorption_color.b()),
+            ),
+        }))
     }
-}
 
-impl Material for DialectricMaterial {
-    fn scatter(
+    // Beer-Lambert: a ray leaving the inside has been travelling through the material
+    // since it entered, where it started.
+    fn get_transmittance(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        if ray_in.get_direction().dot(hit_record.get_normal()) <= 0.0 {
+            return Color::one();
+        }
+        let distance = hit_record.get_t() * ray_in.get_direction().magnitude();
+        Color::new(
+            (-self.absorption.r() * distance).exp(),
+            (-self.absorption.g() * distance).exp(),
+            (-self.absorption.b() * distance).exp(),
+        )
+    }
+
+    // The shading frame with the normal turned toward the viewer, the direction to the
+    // viewer in it and the index of refraction on the far side over the near one.
+    fn get_local_frame(
         &self,
         ray_in: &Ray,
         hit_record: &HitRecord,
-        stat: &mut RenderStats,
-    ) -> Arc<Box<ScatterResult>> {
+    ) -> (OrthoNormalBase, Vector3<f32>, f32) {
+        let wo = -to_unit_vector(ray_in.get_direction());
+        let (normal, eta) = if hit_record.get_normal().dot(wo) < 0.0 {
+            (-hit_record.get_normal(), 1.0 / self.refraction_index)
+        } else {
+            (hit_record.get_normal(), self.refraction_index)
+        };
+        let uvw = OrthoNormalBase::from_w(normal);
+        let local_wo = uvw.to_local(wo);
+        (uvw, local_wo, eta)
+    }
+
+    fn scatter_smooth(&self, ray_in: &Ray, hit_record: &HitRecord, stat: &mut RenderStats) -> Ray {
         let reflected = reflect(ray_in.get_direction(), hit_record.get_normal());
-        let attenuation = Color::one();
         let (outward_normal, ni_over_nt, cosine) =
             if ray_in.get_direction().dot(hit_record.get_normal()) > 0.0 {
                 (
                     -hit_record.get_normal(),
                     self.refraction_index,
-                    self.refraction_index * ray_in.get_direction().dot(hit_record.get_normal())
+                    ray_in.get_direction().dot(hit_record.get_normal())
                         / ray_in.get_direction().magnitude(),
                 )
             } else {
@@ -51,23 +98,68 @@ impl Material for DialectricMaterial {
 
         let refracted = refract(ray_in.get_direction(), outward_normal, ni_over_nt);
 
-        let scattered = if refracted != vec3(0.0, 0.0, 0.0) {
-            let reflect_probability =
-                DialectricMaterial::calculate_schlick_approximation(cosine, self.refraction_index);
-            if next_rand_f32() < reflect_probability {
-                Ray::new(hit_record.get_p(), reflected, stat)
-            } else {
-                Ray::new(hit_record.get_p(), refracted, stat)
-            }
+        if refracted != vec3(0.0, 0.0, 0.0)
+            && next_rand_f32() >= fresnel_dielectric(cosine, 1.0 / ni_over_nt)
+        {
+            Ray::new_at_time(hit_record.get_p(), refracted, ray_in.get_time(), stat)
         } else {
-            Ray::new(hit_record.get_p(), reflected, stat)
-        };
+            Ray::new_at_time(hit_record.get_p(), reflected, ray_in.get_time(), stat)
+        }
+    }
 
+    // The scattered light without the absorption, cosine included, for both directions in
+    // the local frame.
+    fn get_microfacet_value(&self, wo: Vector3<f32>, wi: Vector3<f32>, eta: f32) -> f32 {
+        if wi.z > 0.0 {
+            let h = to_unit_vector(wo + wi);
+            return fresnel_dielectric(wo.dot(h), eta)
+                * self.distribution.get_reflection_value(wo, wi);
+        }
+        match get_transmission_half_vector(wo, wi, eta) {
+            Some(h) => {
+                (1.0 - fresnel_dielectric(wo.dot(h), eta))
+                    * self.distribution.get_transmission_value(wo, wi, eta)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl Material for DialectricMaterial {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let attenuation = self.get_transmittance(ray_in, hit_record);
+        if self.distribution.is_smooth() {
+            let scattered = self.scatter_smooth(ray_in, hit_record, stat);
+            return Arc::new(Box::new(ScatterResult::new(
+                true,
+                attenuation,
+                Some(scattered),
+                None,
+            )));
+        }
+
+        let (uvw, _wo, eta) = self.get_local_frame(ray_in, hit_record);
         Arc::new(Box::new(ScatterResult::new(
             true,
             attenuation,
-            Some(scattered),
             None,
+            Some(Arc::new(MicrofacetDielectricPdf::new(
+                uvw,
+                -ray_in.get_direction(),
+                eta,
+                self.distribution,
+            ))),
         )))
     }
+
+    fn scattering_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        let (uvw, wo, eta) = self.get_local_frame(ray_in, hit_record);
+        let wi = uvw.to_local(to_unit_vector(scattered.get_direction()));
+        self.get_microfacet_value(wo, wi, eta)
+    }
 }
@@ -28,9 +28,7 @@ impl Material for DiffuseLight {
 
     fn emitted(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
         if hit_record.get_normal().dot(ray_in.get_direction()) < 0.0 {
-            return self
-                .texture
-                .get_value(hit_record.get_uv_coords(), hit_record.get_p());
+            return self.texture.get_hit_value(hit_record);
         } else {
             return Color::zero();
         }
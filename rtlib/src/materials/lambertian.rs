@@ -25,9 +25,7 @@ impl Material for LambertianMaterial {
         hit_record: &HitRecord,
         _stat: &mut RenderStats,
     ) -> Arc<Box<ScatterResult>> {
-        let attenuation = self
-            .albedo
-            .get_value(hit_record.get_uv_coords(), hit_record.get_p());
+        let attenuation = self.albedo.get_hit_value(hit_record);
 
         Arc::new(Box::new(ScatterResult::new(
             true,
@@ -37,7 +35,7 @@ impl Material for LambertianMaterial {
         )))
     }
 
-    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+    fn scattering_value(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
         let cosine = hit_record
             .get_normal()
             .dot(to_unit_vector(scattered.get_direction()));
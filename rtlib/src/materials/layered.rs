@@ -0,0 +1,265 @@
+use crate::hitables::HitRecord;
+use crate::materials::{
+    fresnel_dielectric, reflect_about, GgxDistribution, Material, ScatterResult, ThreadMaterial,
+};
+use crate::next_rand_f32;
+use crate::pdfs::{MicrofacetReflectionPdf, OrthoNormalBase, Pdf, WeightedMixturePdf};
+use crate::render::{Color, Ray};
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, InnerSpace, Vector3};
+use std::sync::Arc;
+
+/// Any material under a thin clear coat, like car paint or varnished wood.  The coat
+/// reflects with the Fresnel reflectance of its index of refraction and a GGX roughness,
+/// and what it lets through reaches the base, tinted so that `absorption_color` is what
+/// is left of white light going straight down through the coat and back up.
+///
+/// The coat is thin, so the base sees the same directions as the coat rather than bent
+/// ones, and light bouncing between the base and the underside of the coat is left out.
+/// When both the coat and the base scatter through a pdf, the pdfs are mixed.  When
+/// either one is a perfect mirror they can't be, and one of them is picked at random
+/// for each scattered ray instead.
+pub struct LayeredMaterial {
+    base: ThreadMaterial,
+    refraction_index: f32,
+    distribution: GgxDistribution,
+    absorption_color: Color,
+}
+
+// How a layered material scatters for a given base.
+enum Layering {
+    Mixed,
+    PickCoatOrSpecularBase,
+    PickSmoothCoatOrBase,
+}
+
+impl LayeredMaterial {
+    pub fn new(
+        base: ThreadMaterial,
+        refraction_index: f32,
+        roughness: f32,
+        absorption_color: Color,
+    ) -> ThreadMaterial {
+        Arc::new(Box::new(LayeredMaterial {
+            base,
+            refraction_index,
+            distribution: GgxDistribution::from_roughness(roughness, roughness),
+            absorption_color,
+        }))
+    }
+
+    // The normal turned toward the viewer and the direction to the viewer.
+    fn get_frame(&self, ray_in: &Ray, hit_record: &HitRecord) -> (Vector3<f32>, Vector3<f32>) {
+        let wo = -to_unit_vector(ray_in.get_direction());
+        let normal = hit_record.get_normal();
+        if normal.dot(wo) < 0.0 {
+            (-normal, wo)
+        } else {
+            (normal, wo)
+        }
+    }
+
+    fn get_layering(&self, base: &ScatterResult) -> Layering {
+        let base_is_specular = !base.is_scattered() || base.get_specular_ray().is_some();
+        if base_is_specular {
+            Layering::PickCoatOrSpecularBase
+        } else if self.distribution.is_smooth() {
+            Layering::PickSmoothCoatOrBase
+        } else {
+            Layering::Mixed
+        }
+    }
+
+    // How often the coat is sampled rather than the base, following its reflectance.
+    fn get_coat_probability(&self, cos_o: f32) -> f32 {
+        fresnel_dielectric(cos_o, self.refraction_index).clamp(0.2, 0.95)
+    }
+
+    // The fraction of light that crosses the coat at `cos` and isn't reflected.
+    fn get_crossing(&self, cos: f32) -> f32 {
+        1.0 - fresnel_dielectric(cos.abs(), self.refraction_index)
+    }
+
+    // The tint along a path through the coat that is `length` times as long as going
+    // straight down and back up.
+    fn get_tint(&self, length: f32) -> Color {
+        let tint = |c: f32| c.clamp(1e-6, 1.0).powf(length);
+        Color::new(
+            tint(self.absorption_color.r()),
+            tint(self.absorption_color.g()),
+            tint(self.absorption_color.b()),
+        )
+    }
+
+    // How much longer the way through the coat is than straight down, for light crossing
+    // it at `cos`, bent by refraction.
+    fn get_path_length(&self, cos: f32) -> f32 {
+        let sin2 = (1.0 - (cos * cos)) / (self.refraction_index * self.refraction_index);
+        1.0 / (1.0 - sin2).max(1e-3).sqrt()
+    }
+
+    // What reaches the base from `cos_i` and makes it back out toward `cos_o`.
+    fn get_layer_transmittance(&self, cos_o: f32, cos_i: f32) -> Color {
+        let length = 0.5 * (self.get_path_length(cos_o) + self.get_path_length(cos_i));
+        self.get_tint(length)
+            .multiply_by_scalar(self.get_crossing(cos_o) * self.get_crossing(cos_i))
+    }
+
+    // The light the rough coat reflects from `wi` toward `wo`, cosine included.
+    fn get_coat_value(&self, normal: Vector3<f32>, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        let uvw = OrthoNormalBase::from_w(normal);
+        let wo = uvw.to_local(wo);
+        let wi = uvw.to_local(wi);
+        if wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = to_unit_vector(wo + wi);
+        fresnel_dielectric(wo.dot(h), self.refraction_index)
+            * self.distribution.get_reflection_value(wo, wi)
+    }
+
+    fn get_coat_pdf(&self, normal: Vector3<f32>, wo: Vector3<f32>) -> Arc<dyn Pdf> {
+        Arc::new(MicrofacetReflectionPdf::new(
+            OrthoNormalBase::from_w(normal),
+            wo,
+            self.distribution,
+        ))
+    }
+
+    // The coat's pdf mixed with the base's, when both have one.
+    fn get_mixed_pdf(
+        &self,
+        base: &ScatterResult,
+        p_coat: f32,
+        normal: Vector3<f32>,
+        wo: Vector3<f32>,
+    ) -> Option<Arc<dyn Pdf>> {
+        Some(Arc::new(WeightedMixturePdf::new(vec![
+            (p_coat, self.get_coat_pdf(normal, wo)),
+            (1.0 - p_coat, base.get_pdf()?),
+        ])))
+    }
+}
+
+impl Material for LayeredMaterial {
+    fn emitted(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        let (normal, wo) = self.get_frame(ray_in, hit_record);
+        let cos_o = normal.dot(wo);
+        self.base
+            .emitted(ray_in, hit_record)
+            .multiply(self.get_tint(0.5 * self.get_path_length(cos_o)))
+            .multiply_by_scalar(self.get_crossing(cos_o))
+    }
+
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let (normal, wo) = self.get_frame(ray_in, hit_record);
+        let cos_o = normal.dot(wo);
+        let p_coat = self.get_coat_probability(cos_o);
+        let base = self.base.scatter(ray_in, hit_record, stat);
+
+        // scattering_color() works out what was picked from the base's result
+        let layering = self.get_layering(&base);
+        if let Layering::Mixed = layering {
+            return Arc::new(Box::new(
+                ScatterResult::new(
+                    true,
+                    Color::one(),
+                    None,
+                    self.get_mixed_pdf(&base, p_coat, normal, wo),
+                )
+                .with_base(base),
+            ));
+        }
+
+        if next_rand_f32() < p_coat {
+            if self.distribution.is_smooth() {
+                let reflected = reflect_about(wo, normal);
+                let specular_ray =
+                    Ray::new_at_time(hit_record.get_p(), reflected, ray_in.get_time(), stat);
+                let reflectance = fresnel_dielectric(cos_o, self.refraction_index);
+                return Arc::new(Box::new(ScatterResult::new(
+                    true,
+                    Color::one().multiply_by_scalar(reflectance / p_coat),
+                    Some(specular_ray),
+                    None,
+                )));
+            }
+            return Arc::new(Box::new(
+                ScatterResult::new(
+                    true,
+                    Color::one(),
+                    None,
+                    Some(self.get_coat_pdf(normal, wo)),
+                )
+                .with_base(base),
+            ));
+        }
+
+        match (layering, base.get_specular_ray()) {
+            (Layering::PickCoatOrSpecularBase, Some(specular_ray)) => {
+                let cos_i = normal.dot(to_unit_vector(specular_ray.get_direction()));
+                let attenuation = base
+                    .get_attenuation()
+                    .multiply(self.get_layer_transmittance(cos_o, cos_i))
+                    .multiply_by_scalar(1.0 / (1.0 - p_coat));
+                Arc::new(Box::new(ScatterResult::new(
+                    true,
+                    attenuation,
+                    Some(specular_ray),
+                    None,
+                )))
+            }
+            (Layering::PickSmoothCoatOrBase, _) => Arc::new(Box::new(
+                ScatterResult::new(true, Color::one(), None, base.get_pdf()).with_base(base),
+            )),
+            _ => Arc::new(Box::new(ScatterResult::new_false())),
+        }
+    }
+
+    // The coat and what the base sends back through it, without the tint of the coat.
+    fn scattering_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        let (normal, wo) = self.get_frame(ray_in, hit_record);
+        let wi = to_unit_vector(scattered.get_direction());
+        let crossing = self.get_crossing(normal.dot(wo)) * self.get_crossing(normal.dot(wi));
+        self.get_coat_value(normal, wo, wi)
+            + (crossing * self.base.scattering_value(ray_in, hit_record, scattered))
+    }
+
+    // Scattering through a pdf only happens for the layerings that mix, or after the
+    // rough coat or a base with a pdf was picked, which is then divided by its chance.
+    fn scattering_color(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        scatter_result: &ScatterResult,
+    ) -> Color {
+        let base = match scatter_result.get_base() {
+            Some(base) => base,
+            None => return Color::zero(),
+        };
+        let (normal, wo) = self.get_frame(ray_in, hit_record);
+        let cos_o = normal.dot(wo);
+        let p_coat = self.get_coat_probability(cos_o);
+        let wi = to_unit_vector(scattered.get_direction());
+
+        let base_color = || {
+            self.base
+                .scattering_color(ray_in, hit_record, scattered, &base)
+                .multiply(self.get_layer_transmittance(cos_o, normal.dot(wi)))
+        };
+        match self.get_layering(&base) {
+            Layering::Mixed => base_color()
+                .add(Color::one().multiply_by_scalar(self.get_coat_value(normal, wo, wi))),
+            Layering::PickCoatOrSpecularBase => {
+                Color::one().multiply_by_scalar(self.get_coat_value(normal, wo, wi) / p_coat)
+            }
+            Layering::PickSmoothCoatOrBase => base_color().multiply_by_scalar(1.0 / (1.0 - p_coat)),
+        }
+    }
+}
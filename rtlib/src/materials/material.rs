@@ -15,9 +15,29 @@ pub trait Material: Sync {
         hit_record: &HitRecord,
         stat: &mut RenderStats,
     ) -> Arc<Box<ScatterResult>>;
-    fn scattering_pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f32 {
+    /// The scattered light for light arriving along `scattered` and leaving along
+    /// `ray_in`, cosine included.  This is not the density `scatter` picks directions
+    /// with, though the two agree for materials like Lambertian.  Colors that depend on
+    /// the directions are left out and come back in `scattering_color`, and materials
+    /// that are nothing but such a color give its mean over the channels.
+    fn scattering_value(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f32 {
         0.0
     }
+    /// How much of the light arriving along `scattered` leaves back along `ray_in`, cosine
+    /// included, for the `scatter_result` that `scattered` was picked from.  Materials
+    /// whose color doesn't depend on the directions can leave this to the attenuation
+    /// from `scatter` and `scattering_value`.
+    fn scattering_color(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        scatter_result: &ScatterResult,
+    ) -> Color {
+        scatter_result
+            .get_attenuation()
+            .multiply_by_scalar(self.scattering_value(ray_in, hit_record, scattered))
+    }
     fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
         Color::zero()
     }
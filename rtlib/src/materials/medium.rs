@@ -0,0 +1,55 @@
+use crate::hitables::HitRecord;
+use crate::materials::{Material, ScatterResult, ThreadMaterial};
+use crate::pdfs::PhaseFunctionPdf;
+use crate::phasefunctions::{IsotropicPhaseFunction, ThreadPhaseFunction};
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::textures::ThreadTexture;
+use std::sync::Arc;
+
+/// What a ray finds when it scatters inside a medium.  The phase function decides where
+/// the light goes next and the albedo how much of it survives.  Scattered directions
+/// go through the pdf, so lights are sampled from inside media too.
+pub struct MediumMaterial {
+    albedo: ThreadTexture,
+    phase_function: ThreadPhaseFunction,
+}
+
+impl MediumMaterial {
+    pub fn new(albedo: ThreadTexture, phase_function: ThreadPhaseFunction) -> ThreadMaterial {
+        Arc::new(Box::new(MediumMaterial {
+            albedo,
+            phase_function,
+        }))
+    }
+
+    /// A medium that scatters equally in every direction.
+    pub fn new_isotropic(albedo: ThreadTexture) -> ThreadMaterial {
+        MediumMaterial::new(albedo, IsotropicPhaseFunction::new())
+    }
+}
+
+impl Material for MediumMaterial {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        _stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let attenuation = self.albedo.get_hit_value(hit_record);
+        Arc::new(Box::new(ScatterResult::new(
+            true,
+            attenuation,
+            None,
+            Some(Arc::new(PhaseFunctionPdf::new(
+                self.phase_function.clone(),
+                ray_in.get_direction(),
+            ))),
+        )))
+    }
+
+    fn scattering_value(&self, ray_in: &Ray, _hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        self.phase_function
+            .get_value(ray_in.get_direction(), scattered.get_direction())
+    }
+}
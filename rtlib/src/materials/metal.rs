@@ -27,9 +27,10 @@ impl Material for MetalMaterial {
         stat: &mut RenderStats,
     ) -> Arc<Box<ScatterResult>> {
         let reflected = reflect(ray_in.get_direction().normalize(), hit_record.get_normal());
-        let specular_ray = Ray::new(
+        let specular_ray = Ray::new_at_time(
             hit_record.get_p(),
             reflected + (self.fuzz * get_random_in_unit_sphere()),
+            ray_in.get_time(),
             stat,
         );
         let attenuation = self.albedo;
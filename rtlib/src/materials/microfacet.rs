@@ -0,0 +1,315 @@
+use crate::next_rand_f32;
+use crate::render::Color;
+use crate::{to_unit_vector, vec3, InnerSpace, Vector3};
+use std::f32;
+
+// Below this the distribution is so narrow that it is treated as a mirror.
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, with Smith's height
+/// correlated masking and shadowing.  Vectors are in a local frame where the surface
+/// normal is z, and `alpha_x` and `alpha_y` are the widths of the distribution along x
+/// and y.  Directions all point away from the surface.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct GgxDistribution {
+    alpha_x: f32,
+    alpha_y: f32,
+}
+
+impl GgxDistribution {
+    /// Roughness goes from 0 for a mirror to 1 for very rough, and alpha is its square,
+    /// which makes the change in appearance about even over that range.
+    pub fn from_roughness(roughness_x: f32, roughness_y: f32) -> GgxDistribution {
+        let to_alpha = |roughness: f32| {
+            let roughness = roughness.clamp(0.0, 1.0);
+            (roughness * roughness).max(1e-4)
+        };
+        GgxDistribution {
+            alpha_x: to_alpha(roughness_x),
+            alpha_y: to_alpha(roughness_y),
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    /// The density of microfacets facing along `h`, per unit of projected area.
+    pub fn get_d(&self, h: Vector3<f32>) -> f32 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let x = h.x / self.alpha_x;
+        let y = h.y / self.alpha_y;
+        let t = (x * x) + (y * y) + (h.z * h.z);
+        1.0 / (f32::consts::PI * self.alpha_x * self.alpha_y * t * t)
+    }
+
+    fn get_lambda(&self, w: Vector3<f32>) -> f32 {
+        if w.z == 0.0 {
+            return f32::MAX;
+        }
+        let a2 = ((self.alpha_x * self.alpha_x * w.x * w.x)
+            + (self.alpha_y * self.alpha_y * w.y * w.y))
+            / (w.z * w.z);
+        0.5 * (-1.0 + (1.0 + a2).sqrt())
+    }
+
+    /// The fraction of microfacets seen from `w` that aren't hidden by others.
+    pub fn get_g1(&self, w: Vector3<f32>) -> f32 {
+        1.0 / (1.0 + self.get_lambda(w))
+    }
+
+    /// The fraction of microfacets that are both seen from `wo` and lit from `wi`.
+    pub fn get_g2(&self, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        1.0 / (1.0 + self.get_lambda(wo) + self.get_lambda(wi))
+    }
+
+    /// A microfacet normal picked in proportion to how much of it is visible from `wo`,
+    /// which wastes no samples on facets facing away.
+    /// see: Sampling the GGX Distribution of Visible Normals, Eric Heitz, JCGT 2018
+    pub fn sample_visible_normal(&self, wo: Vector3<f32>) -> Vector3<f32> {
+        // stretch the view so the distribution becomes a hemisphere
+        let vh = to_unit_vector(vec3(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z));
+        let length_sq = (vh.x * vh.x) + (vh.y * vh.y);
+        let t1 = if length_sq > 0.0 {
+            vec3(-vh.y, vh.x, 0.0) / length_sq.sqrt()
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        // a point on the disk, squashed onto the part of the hemisphere that is visible
+        let r = next_rand_f32().sqrt();
+        let phi = 2.0 * f32::consts::PI * next_rand_f32();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = ((1.0 - s) * (1.0 - (p1 * p1)).max(0.0).sqrt()) + (s * r * phi.sin());
+        let nh = (p1 * t1) + (p2 * t2) + (1.0 - (p1 * p1) - (p2 * p2)).max(0.0).sqrt() * vh;
+
+        // and unstretch the normal
+        to_unit_vector(vec3(
+            self.alpha_x * nh.x,
+            self.alpha_y * nh.y,
+            nh.z.max(1e-6),
+        ))
+    }
+
+    /// The pdf of `sample_visible_normal` returning `h`.
+    pub fn get_visible_normal_pdf(&self, wo: Vector3<f32>, h: Vector3<f32>) -> f32 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.get_g1(wo) * wo.dot(h).max(0.0) * self.get_d(h) / wo.z
+    }
+
+    /// The pdf of reaching `wi` by reflecting `wo` about a visible normal.
+    pub fn get_reflection_pdf(&self, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = to_unit_vector(wo + wi);
+        let wo_dot_h = wo.dot(h);
+        if wo_dot_h <= 0.0 {
+            return 0.0;
+        }
+        self.get_visible_normal_pdf(wo, h) / (4.0 * wo_dot_h)
+    }
+
+    /// D * G2 / (4 cos_o), the light reflected from `wi` to `wo` with the cosine included
+    /// but without the Fresnel term.
+    pub fn get_reflection_value(&self, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = to_unit_vector(wo + wi);
+        self.get_d(h) * self.get_g2(wo, wi) / (4.0 * wo.z)
+    }
+
+    /// The light refracted from `wi` below the surface to `wo` above it with the cosine
+    /// included but without the Fresnel term, where `eta` is the index of refraction below
+    /// over the one above.
+    /// see: Microfacet Models for Refraction through Rough Surfaces, Walter et al. 2007
+    pub fn get_transmission_value(&self, wo: Vector3<f32>, wi: Vector3<f32>, eta: f32) -> f32 {
+        if wi.z >= 0.0 {
+            return 0.0;
+        }
+        match get_transmission_half_vector(wo, wi, eta) {
+            Some(h) => {
+                let denominator = wo.dot(h) + (eta * wi.dot(h));
+                self.get_d(h) * self.get_g2(wo, wi) * wi.dot(h).abs() * wo.dot(h)
+                    / (wo.z * denominator * denominator)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// The pdf of reaching `wi` by refracting `wo` through a visible normal, where `eta`
+    /// is the index of refraction below over the one above.
+    pub fn get_transmission_pdf(&self, wo: Vector3<f32>, wi: Vector3<f32>, eta: f32) -> f32 {
+        match get_transmission_half_vector(wo, wi, eta) {
+            Some(h) => {
+                let denominator = wo.dot(h) + (eta * wi.dot(h));
+                self.get_visible_normal_pdf(wo, h) * eta * eta * wi.dot(h).abs()
+                    / (denominator * denominator)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// The microfacet normal that refracts `wo` above the surface into `wi`, if there is one.
+/// Rough surfaces can refract to directions that end up above the surface too.  `eta` is
+/// the index of refraction below over the one above.
+pub(crate) fn get_transmission_half_vector(
+    wo: Vector3<f32>,
+    wi: Vector3<f32>,
+    eta: f32,
+) -> Option<Vector3<f32>> {
+    if wo.z <= 0.0 {
+        return None;
+    }
+    let h = wo + (eta * wi);
+    if h.magnitude2() == 0.0 {
+        return None;
+    }
+    let h = to_unit_vector(h);
+    let h = if h.z < 0.0 { -h } else { h };
+    // both sides have to see the facet, from opposite sides
+    if wo.dot(h) <= 0.0 || wi.dot(h) >= 0.0 {
+        return None;
+    }
+    Some(h)
+}
+
+/// The generalized Trowbridge-Reitz distribution with an exponent of 1, whose long tails
+/// give the halo around clearcoat highlights.  Only the distribution and its sampling
+/// differ from GGX, masking uses a fixed GGX roughness.
+/// see: Physically Based Shading at Disney, Brent Burley, 2012
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Gtr1Distribution {
+    alpha: f32,
+}
+
+impl Gtr1Distribution {
+    pub fn new(alpha: f32) -> Gtr1Distribution {
+        Gtr1Distribution {
+            alpha: alpha.clamp(1e-3, 0.999),
+        }
+    }
+
+    pub fn get_d(&self, h: Vector3<f32>) -> f32 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let t = 1.0 + ((a2 - 1.0) * h.z * h.z);
+        (a2 - 1.0) / (f32::consts::PI * a2.ln() * t)
+    }
+
+    /// A microfacet normal picked in proportion to D(h) cos(h).
+    pub fn sample_normal(&self) -> Vector3<f32> {
+        let a2 = self.alpha * self.alpha;
+        let cos2 = ((1.0 - a2.powf(1.0 - next_rand_f32())) / (1.0 - a2)).clamp(0.0, 1.0);
+        let sin = (1.0 - cos2).sqrt();
+        let phi = 2.0 * f32::consts::PI * next_rand_f32();
+        vec3(sin * phi.cos(), sin * phi.sin(), cos2.sqrt())
+    }
+
+    /// The pdf of reaching `wi` by reflecting `wo` about a normal from `sample_normal`.
+    pub fn get_reflection_pdf(&self, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = to_unit_vector(wo + wi);
+        self.get_d(h) * h.z / (4.0 * wo.dot(h))
+    }
+
+    /// D * G2 / (4 cos_o) with the masking of a GGX roughness of 0.25.
+    pub fn get_reflection_value(&self, wo: Vector3<f32>, wi: Vector3<f32>) -> f32 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = to_unit_vector(wo + wi);
+        let masking = GgxDistribution {
+            alpha_x: 0.25,
+            alpha_y: 0.25,
+        };
+        self.get_d(h) * masking.get_g1(wo) * masking.get_g1(wi) / (4.0 * wo.z)
+    }
+}
+
+/// Schlick's weight (1 - cos)^5 for blending toward grazing angles.
+pub(crate) fn schlick_weight(cos_theta: f32) -> f32 {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Schlick's approximation of the Fresnel reflectance with `f0` at normal incidence.
+pub(crate) fn fresnel_schlick(cos_theta: f32, f0: Color) -> Color {
+    let w = schlick_weight(cos_theta);
+    Color::new(
+        f0.r() + ((1.0 - f0.r()) * w),
+        f0.g() + ((1.0 - f0.g()) * w),
+        f0.b() + ((1.0 - f0.b()) * w),
+    )
+}
+
+/// `v` mirrored about `h`, for vectors pointing away from the surface.
+pub(crate) fn reflect_about(v: Vector3<f32>, h: Vector3<f32>) -> Vector3<f32> {
+    (2.0 * v.dot(h) * h) - v
+}
+
+/// `v` refracted through the surface with normal `h`, both pointing to the same side, or
+/// None for total internal reflection.  `eta` is the index of refraction on the other
+/// side over the one on the side of `v`.
+pub(crate) fn refract_about(v: Vector3<f32>, h: Vector3<f32>, eta: f32) -> Option<Vector3<f32>> {
+    let cos_i = v.dot(h);
+    let sin2_t = (1.0 - (cos_i * cos_i)).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((-v / eta) + (((cos_i / eta) - cos_t) * h))
+}
+
+/// Reflectance of the interface between two dielectrics, seen at `cos_theta` from the side
+/// where `eta` is the index of refraction on the other side over this one.
+pub(crate) fn fresnel_dielectric(cos_theta: f32, eta: f32) -> f32 {
+    let cos_i = cos_theta.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - (cos_i * cos_i)) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - (eta * cos_t)) / (cos_i + (eta * cos_t));
+    let rp = ((eta * cos_i) - cos_t) / ((eta * cos_i) + cos_t);
+    0.5 * ((rs * rs) + (rp * rp))
+}
+
+/// Reflectance of a metal with the complex index of refraction eta + ik, per channel.
+/// see: Physically Based Rendering (3rd edition), 8.2.1
+pub(crate) fn fresnel_conductor(cos_theta: f32, eta: Color, k: Color) -> Color {
+    let channel = |eta: f32, k: f32| {
+        let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = ((t0 * t0) + (4.0 * eta2 * k2)).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_theta.clamp(0.0, 1.0) * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = (cos2 * a2_plus_b2) + (sin2 * sin2);
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rp + rs)
+    };
+    Color::new(
+        channel(eta.r(), k.r()),
+        channel(eta.g(), k.g()),
+        channel(eta.b(), k.b()),
+    )
+}
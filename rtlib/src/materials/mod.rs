@@ -1,22 +1,36 @@
+mod conductor;
 mod dialectric;
 mod diffuselight;
-mod isotropic;
 mod lambertian;
+mod layered;
 mod material;
+mod medium;
 mod metal;
+mod microfacet;
+mod orennayar;
+mod principled;
 mod scatterresult;
 
+pub use self::conductor::{ConductorMaterial, ConductorPreset};
 pub use self::dialectric::DialectricMaterial;
 pub use self::diffuselight::DiffuseLight;
-pub use self::isotropic::IsotropicMaterial;
 pub use self::lambertian::LambertianMaterial;
+pub use self::layered::LayeredMaterial;
 pub use self::material::CompiledMaterial;
 pub use self::material::CompiledMaterials;
 pub use self::material::Material;
 pub use self::material::ThreadMaterial;
+pub use self::medium::MediumMaterial;
 pub use self::metal::MetalMaterial;
+pub use self::orennayar::OrenNayarMaterial;
+pub use self::principled::{PrincipledMaterial, PrincipledParameters};
 pub use self::scatterresult::ScatterResult;
 
+pub(crate) use self::microfacet::{
+    fresnel_conductor, fresnel_dielectric, fresnel_schlick, get_transmission_half_vector,
+    reflect_about, refract_about, schlick_weight, GgxDistribution, Gtr1Distribution,
+};
+
 use crate::{vec3, InnerSpace, Vector3};
 
 // todo: move these to a trait so we can extend the Vector3 type with them directly.
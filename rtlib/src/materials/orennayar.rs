@@ -0,0 +1,82 @@
+use crate::hitables::HitRecord;
+use crate::materials::{Material, ScatterResult, ThreadMaterial};
+use crate::pdfs::CosinePdf;
+use crate::render::Ray;
+use crate::stats::RenderStats;
+use crate::textures::ThreadTexture;
+use crate::{to_unit_vector, InnerSpace};
+use std::f32;
+use std::sync::Arc;
+
+/// A rough diffuse surface made of tiny Lambertian v-grooves, like clay, concrete or the
+/// moon.  It looks flatter than Lambertian and brighter toward the light at grazing
+/// angles.  `sigma` is the spread of the groove slopes in degrees, at 0 it is Lambertian.
+/// see: Generalization of Lambert's Reflectance Model, Oren and Nayar, 1994
+pub struct OrenNayarMaterial {
+    albedo: ThreadTexture,
+    a: f32,
+    b: f32,
+}
+
+impl OrenNayarMaterial {
+    pub fn new(albedo: ThreadTexture, sigma: f32) -> ThreadMaterial {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+        Arc::new(Box::new(OrenNayarMaterial {
+            albedo,
+            a: 1.0 - (sigma2 / (2.0 * (sigma2 + 0.33))),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }))
+    }
+}
+
+impl Material for OrenNayarMaterial {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        hit_record: &HitRecord,
+        _stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let attenuation = self.albedo.get_hit_value(hit_record);
+
+        Arc::new(Box::new(ScatterResult::new(
+            true,
+            attenuation,
+            None,
+            Some(Arc::new(CosinePdf::new(hit_record.get_normal()))),
+        )))
+    }
+
+    fn scattering_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        let normal = hit_record.get_normal();
+        let wi = to_unit_vector(scattered.get_direction());
+        let wo = -to_unit_vector(ray_in.get_direction());
+        let cos_i = normal.dot(wi);
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+        let cos_o = normal.dot(wo).clamp(0.0, 1.0);
+        let sin_i = (1.0 - (cos_i * cos_i)).max(0.0).sqrt();
+        let sin_o = (1.0 - (cos_o * cos_o)).max(0.0).sqrt();
+
+        // the cosine of the angle between the two directions around the normal
+        let cos_phi = if sin_i > 1e-4 && sin_o > 1e-4 {
+            let ti = wi - (cos_i * normal);
+            let to = wo - (cos_o * normal);
+            (ti.dot(to) / (sin_i * sin_o)).max(0.0)
+        } else {
+            0.0
+        };
+
+        // sin(alpha) tan(beta) with alpha the larger of the two angles to the normal
+        let sin_alpha_tan_beta = if cos_i > cos_o {
+            sin_o * sin_i / cos_i
+        } else if cos_o > 0.0 {
+            sin_i * sin_o / cos_o
+        } else {
+            0.0
+        };
+
+        cos_i * (self.a + (self.b * cos_phi * sin_alpha_tan_beta)) / f32::consts::PI
+    }
+}
@@ -0,0 +1,293 @@
+use crate::hitables::HitRecord;
+use crate::materials::{
+    fresnel_dielectric, fresnel_schlick, get_transmission_half_vector, schlick_weight,
+    GgxDistribution, Gtr1Distribution, Material, ScatterResult, ThreadMaterial,
+};
+use crate::pdfs::{
+    CosinePdf, MicrofacetClearcoatPdf, MicrofacetDielectricPdf, MicrofacetReflectionPdf,
+    OrthoNormalBase, Pdf, WeightedMixturePdf,
+};
+use crate::render::{Color, Ray};
+use crate::stats::RenderStats;
+use crate::textures::{ColorTexture, ThreadTexture};
+use crate::{to_unit_vector, InnerSpace, Vector3};
+use std::f32;
+use std::sync::Arc;
+
+/// What a `PrincipledMaterial` is made of.  Every parameter but the index of refraction
+/// comes from a texture, the scalar ones from the mean of its channels, and all of them
+/// go from 0 to 1.
+pub struct PrincipledParameters {
+    pub base_color: ThreadTexture,
+    pub metallic: ThreadTexture,
+    pub roughness: ThreadTexture,
+    /// Reflectance of the non metallic parts, 0.5 is the 4% of most dielectrics.
+    pub specular: ThreadTexture,
+    /// How much the non metallic reflections take on the base color.
+    pub specular_tint: ThreadTexture,
+    /// A soft rim at grazing angles, for cloth.
+    pub sheen: ThreadTexture,
+    /// A second, clear and sharp specular layer on top.
+    pub clearcoat: ThreadTexture,
+    /// From a satin clearcoat at 0 to a glossy one at 1.
+    pub clearcoat_gloss: ThreadTexture,
+    /// How much of the non metallic parts is see through glass rather than diffuse.
+    pub transmission: ThreadTexture,
+    pub refraction_index: f32,
+}
+
+impl Default for PrincipledParameters {
+    fn default() -> PrincipledParameters {
+        let value = |v: f32| ColorTexture::new(v, v, v);
+        PrincipledParameters {
+            base_color: value(0.8),
+            metallic: value(0.0),
+            roughness: value(0.5),
+            specular: value(0.5),
+            specular_tint: value(0.0),
+            sheen: value(0.0),
+            clearcoat: value(0.0),
+            clearcoat_gloss: value(1.0),
+            transmission: value(0.0),
+            refraction_index: 1.5,
+        }
+    }
+}
+
+/// One material for most surfaces, after the Disney principled BRDF.  It blends a
+/// diffuse base with sheen, a GGX specular lobe that goes from dielectric to metal, a
+/// GTR1 clearcoat and rough transmission.  Scattered directions pick a lobe in proportion
+/// to how much it is expected to contribute, and the pdf is the mix of all of them.
+/// see: Physically Based Shading at Disney, Brent Burley, 2012
+pub struct PrincipledMaterial {
+    parameters: PrincipledParameters,
+}
+
+impl PrincipledMaterial {
+    pub fn new(parameters: PrincipledParameters) -> ThreadMaterial {
+        Arc::new(Box::new(PrincipledMaterial { parameters }))
+    }
+
+    fn get_lobes(&self, hit_record: &HitRecord) -> Lobes {
+        let scalar = |texture: &ThreadTexture| {
+            let c = texture.get_hit_value(hit_record);
+            ((c.r() + c.g() + c.b()) / 3.0).clamp(0.0, 1.0)
+        };
+        let parameters = &self.parameters;
+
+        let base_color = parameters.base_color.get_hit_value(hit_record).clamp();
+        let metallic = scalar(&parameters.metallic);
+        let roughness = scalar(&parameters.roughness);
+        let specular = scalar(&parameters.specular);
+        let specular_tint = scalar(&parameters.specular_tint);
+        let transmission = scalar(&parameters.transmission);
+
+        // the hue of the base color without its brightness
+        let luminance = get_luminance(base_color);
+        let tint = if luminance > 0.0 {
+            base_color.multiply_by_scalar(1.0 / luminance)
+        } else {
+            Color::one()
+        };
+        let dielectric_specular =
+            lerp(Color::one(), tint, specular_tint).multiply_by_scalar(0.08 * specular);
+
+        let diffuse_weight = (1.0 - metallic) * (1.0 - transmission);
+        Lobes {
+            base_color,
+            specular_color: lerp(dielectric_specular, base_color, metallic),
+            diffuse_weight,
+            // Disney's default sheen tint, halfway to the hue of the base color
+            sheen: lerp(Color::one(), tint, 0.5)
+                .multiply_by_scalar(scalar(&parameters.sheen) * diffuse_weight),
+            clearcoat: 0.25 * scalar(&parameters.clearcoat),
+            transmission: (1.0 - metallic) * transmission,
+            roughness,
+            specular: GgxDistribution::from_roughness(roughness, roughness),
+            coat: Gtr1Distribution::new(
+                0.1 + ((0.001 - 0.1) * scalar(&parameters.clearcoat_gloss)),
+            ),
+        }
+    }
+
+    // The shading frame with the normal turned toward the viewer, the direction to the
+    // viewer in it and the index of refraction on the far side over the near one.
+    fn get_local_frame(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+    ) -> (Vector3<f32>, Vector3<f32>, f32) {
+        let wo = -to_unit_vector(ray_in.get_direction());
+        if hit_record.get_normal().dot(wo) < 0.0 {
+            (
+                -hit_record.get_normal(),
+                wo,
+                1.0 / self.parameters.refraction_index,
+            )
+        } else {
+            (
+                hit_record.get_normal(),
+                wo,
+                self.parameters.refraction_index,
+            )
+        }
+    }
+
+    fn get_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let lobes = self.get_lobes(hit_record);
+        let (normal, wo, eta) = self.get_local_frame(ray_in, hit_record);
+        let uvw = OrthoNormalBase::from_w(normal);
+        let wi = uvw.to_local(to_unit_vector(scattered.get_direction()));
+        lobes.get_value(uvw.to_local(wo), wi, eta)
+    }
+
+    fn get_pdf(
+        &self,
+        lobes: &Lobes,
+        normal: Vector3<f32>,
+        wo: Vector3<f32>,
+        eta: f32,
+    ) -> WeightedMixturePdf {
+        let cos_o = normal.dot(wo);
+        let frame = || OrthoNormalBase::from_w(normal);
+
+        // roughly how much light each lobe sends back toward wo
+        let specular = (1.0 - lobes.transmission)
+            * get_luminance(fresnel_schlick(cos_o, lobes.specular_color));
+        let diffuse =
+            (lobes.diffuse_weight * get_luminance(lobes.base_color)) + get_luminance(lobes.sheen);
+        let clearcoat = lobes.clearcoat * schlick_weight(cos_o).max(0.04);
+        // this one reflects as well, so even total internal reflection goes through it
+        let transmission = lobes.transmission;
+
+        let pdfs: Vec<(f32, Arc<dyn Pdf>)> = vec![
+            (diffuse, Arc::new(CosinePdf::new(normal))),
+            (
+                specular.max(1e-3),
+                Arc::new(MicrofacetReflectionPdf::new(frame(), wo, lobes.specular)),
+            ),
+            (
+                clearcoat,
+                Arc::new(MicrofacetClearcoatPdf::new(frame(), wo, lobes.coat)),
+            ),
+            (
+                transmission,
+                Arc::new(MicrofacetDielectricPdf::new(
+                    frame(),
+                    wo,
+                    eta,
+                    lobes.specular,
+                )),
+            ),
+        ];
+        WeightedMixturePdf::new(pdfs)
+    }
+}
+
+// The parameters at one point, turned into the weights and colors of the lobes.
+struct Lobes {
+    base_color: Color,
+    specular_color: Color,
+    diffuse_weight: f32,
+    sheen: Color,
+    clearcoat: f32,
+    transmission: f32,
+    roughness: f32,
+    specular: GgxDistribution,
+    coat: Gtr1Distribution,
+}
+
+impl Lobes {
+    // The scattered light with the cosine included, both directions in the local frame.
+    fn get_value(&self, wo: Vector3<f32>, wi: Vector3<f32>, eta: f32) -> Color {
+        if wo.z <= 0.0 {
+            return Color::zero();
+        }
+
+        if wi.z < 0.0 {
+            return match get_transmission_half_vector(wo, wi, eta) {
+                Some(h) if self.transmission > 0.0 => self.base_color.multiply_by_scalar(
+                    self.transmission
+                        * (1.0 - fresnel_dielectric(wo.dot(h), eta))
+                        * self.specular.get_transmission_value(wo, wi, eta),
+                ),
+                _ => Color::zero(),
+            };
+        }
+
+        let h = to_unit_vector(wo + wi);
+        let cos_d = wi.dot(h);
+
+        // Burley's diffuse, darker at grazing angles and with a retro-reflective bump on
+        // rough surfaces, plus sheen
+        let fl = schlick_weight(wi.z);
+        let fv = schlick_weight(wo.z);
+        let rr = 2.0 * self.roughness * cos_d * cos_d;
+        let lambert = (1.0 - (0.5 * fl)) * (1.0 - (0.5 * fv));
+        let retro = rr * (fl + fv + (fl * fv * (rr - 1.0)));
+        let diffuse = self
+            .base_color
+            .multiply_by_scalar(self.diffuse_weight * (lambert + retro) / f32::consts::PI)
+            .add(self.sheen.multiply_by_scalar(schlick_weight(cos_d)));
+
+        // the see through parts reflect like glass instead of with the specular color
+        let reflection = self.specular.get_reflection_value(wo, wi);
+        let specular = fresnel_schlick(cos_d, self.specular_color)
+            .multiply_by_scalar((1.0 - self.transmission) * reflection)
+            .add(Color::one().multiply_by_scalar(
+                self.transmission * fresnel_dielectric(wo.dot(h), eta) * reflection,
+            ));
+        let clearcoat = self.clearcoat
+            * fresnel_schlick(cos_d, Color::new(0.04, 0.04, 0.04)).r()
+            * self.coat.get_reflection_value(wo, wi);
+
+        diffuse
+            .multiply_by_scalar(wi.z)
+            .add(specular)
+            .add(Color::new(clearcoat, clearcoat, clearcoat))
+    }
+}
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    a.multiply_by_scalar(1.0 - t).add(b.multiply_by_scalar(t))
+}
+
+fn get_luminance(c: Color) -> f32 {
+    (0.2126 * c.r()) + (0.7152 * c.g()) + (0.0722 * c.b())
+}
+
+impl Material for PrincipledMaterial {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        _stat: &mut RenderStats,
+    ) -> Arc<Box<ScatterResult>> {
+        let lobes = self.get_lobes(hit_record);
+        let (normal, wo, eta) = self.get_local_frame(ray_in, hit_record);
+
+        // the colors depend on the direction picked, see scattering_color()
+        Arc::new(Box::new(ScatterResult::new(
+            true,
+            Color::one(),
+            None,
+            Some(Arc::new(self.get_pdf(&lobes, normal, wo, eta))),
+        )))
+    }
+
+    fn scattering_value(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f32 {
+        let value = self.get_value(ray_in, hit_record, scattered);
+        (value.r() + value.g() + value.b()) / 3.0
+    }
+
+    fn scattering_color(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        scatter_result: &ScatterResult,
+    ) -> Color {
+        self.get_value(ray_in, hit_record, scattered)
+            .multiply(scatter_result.get_attenuation())
+    }
+}
@@ -8,6 +8,8 @@ pub struct ScatterResult {
     specular_ray: Option<Ray>,
     attenuation: Color,
     pdf: Option<Arc<dyn Pdf>>,
+    // what the material underneath gave, for materials that wrap another one
+    base: Option<Arc<Box<ScatterResult>>>,
 }
 
 impl ScatterResult {
@@ -22,9 +24,15 @@ impl ScatterResult {
             attenuation,
             specular_ray,
             pdf,
+            base: None,
         }
     }
 
+    pub fn with_base(mut self, base: Arc<Box<ScatterResult>>) -> ScatterResult {
+        self.base = Some(base);
+        self
+    }
+
     pub fn new_false() -> ScatterResult {
         ScatterResult::new(false, Color::zero(), None, None)
     }
@@ -44,4 +52,8 @@ impl ScatterResult {
     pub fn get_pdf(&self) -> Option<Arc<dyn Pdf>> {
         self.pdf.clone()
     }
+
+    pub fn get_base(&self) -> Option<Arc<Box<ScatterResult>>> {
+        self.base.clone()
+    }
 }
@@ -23,8 +23,8 @@ fn get_random_cosine_direction() -> Vector3<f32> {
     let sqrtr2 = r2.sqrt();
     let z = (1.0 - r2).sqrt();
     let phi = 2.0 * f32::consts::PI * r1;
-    let x = phi.cos() * 2.0 * sqrtr2;
-    let y = phi.sin() * 2.0 * sqrtr2;
+    let x = phi.cos() * sqrtr2;
+    let y = phi.sin() * sqrtr2;
 
     vec3(x, y, z)
 }
@@ -34,11 +34,11 @@ impl Pdf for CosinePdf {
         let cosine: f32 = to_unit_vector(direction).dot(self.uvw.w());
         let pi = std::f32::consts::PI;
 
-        // todo: book has this as 1.0f, but that causes NaN due to div by zero
+        // directions below the surface are never generated, the ray tracer skips a pdf of 0
         if cosine > 0.0 {
             return cosine / pi;
         }
-        return 0.01;
+        return 0.0;
     }
 
     fn generate(&self) -> Vector3<f32> {
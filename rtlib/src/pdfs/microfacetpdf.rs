@@ -0,0 +1,147 @@
+use crate::materials::{
+    fresnel_dielectric, get_transmission_half_vector, reflect_about, refract_about,
+    GgxDistribution, Gtr1Distribution,
+};
+use crate::next_rand_f32;
+use crate::pdfs::{OrthoNormalBase, Pdf};
+use crate::stats::RenderStats;
+use crate::{to_unit_vector, InnerSpace, Vector3};
+
+/// Glossy reflections: `wo`, pointing away from the surface toward where the light
+/// goes, mirrored about microfacet normals picked from the visible part of a GGX
+/// distribution.  Directions below the surface are never generated on purpose, but
+/// steep views can still produce them, and they get a pdf of 0.
+pub struct MicrofacetReflectionPdf {
+    uvw: OrthoNormalBase,
+    wo: Vector3<f32>,
+    distribution: GgxDistribution,
+}
+
+impl MicrofacetReflectionPdf {
+    pub(crate) fn new(
+        uvw: OrthoNormalBase,
+        wo: Vector3<f32>,
+        distribution: GgxDistribution,
+    ) -> MicrofacetReflectionPdf {
+        let wo = uvw.to_local(to_unit_vector(wo));
+        MicrofacetReflectionPdf {
+            uvw,
+            wo,
+            distribution,
+        }
+    }
+}
+
+impl Pdf for MicrofacetReflectionPdf {
+    fn get_value(&self, direction: Vector3<f32>, _stat: &mut RenderStats) -> f32 {
+        let wi = self.uvw.to_local(to_unit_vector(direction));
+        self.distribution.get_reflection_pdf(self.wo, wi)
+    }
+
+    fn generate(&self) -> Vector3<f32> {
+        let h = self.distribution.sample_visible_normal(self.wo);
+        self.uvw.local(reflect_about(self.wo, h))
+    }
+}
+
+/// Rough glass: `wo` is reflected or refracted about a visible GGX normal, picking
+/// reflection with the Fresnel reflectance of that normal.  The frame's normal is on the
+/// side of `wo`, and `eta` is the index of refraction on the far side over the near one.
+pub struct MicrofacetDielectricPdf {
+    uvw: OrthoNormalBase,
+    wo: Vector3<f32>,
+    eta: f32,
+    distribution: GgxDistribution,
+}
+
+impl MicrofacetDielectricPdf {
+    pub(crate) fn new(
+        uvw: OrthoNormalBase,
+        wo: Vector3<f32>,
+        eta: f32,
+        distribution: GgxDistribution,
+    ) -> MicrofacetDielectricPdf {
+        let wo = uvw.to_local(to_unit_vector(wo));
+        MicrofacetDielectricPdf {
+            uvw,
+            wo,
+            eta,
+            distribution,
+        }
+    }
+}
+
+impl Pdf for MicrofacetDielectricPdf {
+    // Reflections can end up below the surface and refractions above it, so both ways
+    // of reaching a direction count wherever it is.
+    fn get_value(&self, direction: Vector3<f32>, _stat: &mut RenderStats) -> f32 {
+        let wi = self.uvw.to_local(to_unit_vector(direction));
+        let mut value = 0.0;
+
+        let h = wi + self.wo;
+        if h.magnitude2() > 0.0 {
+            let h = to_unit_vector(h);
+            let wo_dot_h = self.wo.dot(h);
+            if h.z > 0.0 && wo_dot_h > 0.0 {
+                value += fresnel_dielectric(wo_dot_h, self.eta)
+                    * self.distribution.get_visible_normal_pdf(self.wo, h)
+                    / (4.0 * wo_dot_h);
+            }
+        }
+
+        if let Some(h) = get_transmission_half_vector(self.wo, wi, self.eta) {
+            value += (1.0 - fresnel_dielectric(self.wo.dot(h), self.eta))
+                * self
+                    .distribution
+                    .get_transmission_pdf(self.wo, wi, self.eta);
+        }
+        value
+    }
+
+    fn generate(&self) -> Vector3<f32> {
+        let h = self.distribution.sample_visible_normal(self.wo);
+        let reflectance = fresnel_dielectric(self.wo.dot(h), self.eta);
+        let wi = if next_rand_f32() < reflectance {
+            reflect_about(self.wo, h)
+        } else {
+            // no refraction means a reflectance of 1, so this always refracts
+            refract_about(self.wo, h, self.eta).unwrap_or_else(|| reflect_about(self.wo, h))
+        };
+        self.uvw.local(wi)
+    }
+}
+
+/// Clearcoat highlights: `wo` mirrored about normals from a GTR1 distribution, in proportion
+/// to D(h) cos(h).
+pub struct MicrofacetClearcoatPdf {
+    uvw: OrthoNormalBase,
+    wo: Vector3<f32>,
+    distribution: Gtr1Distribution,
+}
+
+impl MicrofacetClearcoatPdf {
+    pub(crate) fn new(
+        uvw: OrthoNormalBase,
+        wo: Vector3<f32>,
+        distribution: Gtr1Distribution,
+    ) -> MicrofacetClearcoatPdf {
+        let wo = uvw.to_local(to_unit_vector(wo));
+        MicrofacetClearcoatPdf {
+            uvw,
+            wo,
+            distribution,
+        }
+    }
+}
+
+impl Pdf for MicrofacetClearcoatPdf {
+    fn get_value(&self, direction: Vector3<f32>, _stat: &mut RenderStats) -> f32 {
+        let wi = self.uvw.to_local(to_unit_vector(direction));
+        self.distribution.get_reflection_pdf(self.wo, wi)
+    }
+
+    fn generate(&self) -> Vector3<f32> {
+        let h = self.distribution.sample_normal();
+        self.uvw.local(reflect_about(self.wo, h))
+    }
+}
@@ -39,3 +39,42 @@ impl Pdf for MixturePdf {
         }
     }
 }
+
+/// Picks one of several pdfs with probabilities in proportion to their weights, like the
+/// lobes of a material.  Pdfs with a weight of 0 are never picked.
+pub struct WeightedMixturePdf {
+    pdfs: Vec<(f32, Arc<dyn Pdf>)>,
+}
+
+impl WeightedMixturePdf {
+    pub fn new(pdfs: Vec<(f32, Arc<dyn Pdf>)>) -> WeightedMixturePdf {
+        let pdfs: Vec<(f32, Arc<dyn Pdf>)> = pdfs.into_iter().filter(|(w, _)| *w > 0.0).collect();
+        if pdfs.is_empty() {
+            panic!("weighted mixture pdf needs a pdf with a weight above 0");
+        }
+        let total: f32 = pdfs.iter().map(|(w, _)| w).sum();
+        WeightedMixturePdf {
+            pdfs: pdfs.into_iter().map(|(w, p)| (w / total, p)).collect(),
+        }
+    }
+}
+
+impl Pdf for WeightedMixturePdf {
+    fn get_value(&self, direction: Vector3<f32>, stat: &mut RenderStats) -> f32 {
+        self.pdfs
+            .iter()
+            .map(|(w, p)| w * p.get_value(direction, stat))
+            .sum()
+    }
+
+    fn generate(&self) -> Vector3<f32> {
+        let mut choice = next_rand_f32();
+        for (w, p) in self.pdfs.iter() {
+            if choice < *w {
+                return p.generate();
+            }
+            choice -= w;
+        }
+        self.pdfs[self.pdfs.len() - 1].1.generate()
+    }
+}
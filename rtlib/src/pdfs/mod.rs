@@ -1,12 +1,18 @@
 mod cosinepdf;
 mod hitablepdf;
+mod microfacetpdf;
 mod mixturepdf;
 mod orthonormalbase;
+mod phasefunctionpdf;
 
 pub use self::cosinepdf::CosinePdf;
 pub use self::hitablepdf::HitablePdf;
-pub use self::mixturepdf::MixturePdf;
+pub use self::microfacetpdf::{
+    MicrofacetClearcoatPdf, MicrofacetDielectricPdf, MicrofacetReflectionPdf,
+};
+pub use self::mixturepdf::{MixturePdf, WeightedMixturePdf};
 pub use self::orthonormalbase::OrthoNormalBase;
+pub use self::phasefunctionpdf::PhaseFunctionPdf;
 
 use crate::stats::RenderStats;
 use crate::Vector3;
@@ -1,4 +1,4 @@
-use crate::{to_unit_vector, Vector3};
+use crate::{to_unit_vector, vec3, InnerSpace, Vector3};
 
 pub struct OrthoNormalBase {
     u: Vector3<f32>,
@@ -24,6 +24,18 @@ impl OrthoNormalBase {
         OrthoNormalBase::new(u, v, w)
     }
 
+    /// A base around `n` with u along `direction` as seen from above the surface.  Falls
+    /// back to `from_w` where `direction` is (close to) parallel to `n`.
+    pub fn from_w_and_direction(n: Vector3<f32>, direction: Vector3<f32>) -> OrthoNormalBase {
+        let w = to_unit_vector(n);
+        let tangent = direction - (w * w.dot(direction));
+        if tangent.magnitude2() < 1e-6 * direction.magnitude2() {
+            return OrthoNormalBase::from_w(w);
+        }
+        let u = to_unit_vector(tangent);
+        OrthoNormalBase::new(u, w.cross(u), w)
+    }
+
     pub fn u(&self) -> Vector3<f32> {
         self.u
     }
@@ -39,4 +51,9 @@ impl OrthoNormalBase {
     pub fn local(&self, a: Vector3<f32>) -> Vector3<f32> {
         return (a.x * self.u) + (a.y * self.v) + (a.z * self.w);
     }
+
+    /// The inverse of `local`, a world vector in u, v, w coordinates.
+    pub fn to_local(&self, a: Vector3<f32>) -> Vector3<f32> {
+        vec3(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
 }
@@ -0,0 +1,32 @@
+use crate::pdfs::Pdf;
+use crate::phasefunctions::ThreadPhaseFunction;
+use crate::stats::RenderStats;
+use crate::Vector3;
+
+/// Directions scattered by a medium, given the direction the light came in along.
+pub struct PhaseFunctionPdf {
+    phase_function: ThreadPhaseFunction,
+    direction_in: Vector3<f32>,
+}
+
+impl PhaseFunctionPdf {
+    pub fn new(
+        phase_function: ThreadPhaseFunction,
+        direction_in: Vector3<f32>,
+    ) -> PhaseFunctionPdf {
+        PhaseFunctionPdf {
+            phase_function,
+            direction_in,
+        }
+    }
+}
+
+impl Pdf for PhaseFunctionPdf {
+    fn get_value(&self, direction: Vector3<f32>, _stat: &mut RenderStats) -> f32 {
+        self.phase_function.get_value(self.direction_in, direction)
+    }
+
+    fn generate(&self) -> Vector3<f32> {
+        self.phase_function.generate(self.direction_in)
+    }
+}
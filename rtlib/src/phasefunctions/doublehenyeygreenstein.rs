@@ -0,0 +1,49 @@
+use crate::next_rand_f32;
+use crate::phasefunctions::{
+    get_cos_theta, henyey_greenstein, sample_henyey_greenstein, PhaseFunction, ThreadPhaseFunction,
+};
+use crate::Vector3;
+use std::sync::Arc;
+
+/// A blend of two Henyey-Greenstein lobes, usually a strong forward one and a weaker
+/// backward one, which fits clouds and skin better than a single lobe.  `weight` is the
+/// share of the `g_forward` lobe.
+pub struct DoubleHenyeyGreensteinPhaseFunction {
+    g_forward: f32,
+    g_back: f32,
+    weight: f32,
+}
+
+impl DoubleHenyeyGreensteinPhaseFunction {
+    pub fn new(g_forward: f32, g_back: f32, weight: f32) -> ThreadPhaseFunction {
+        if g_forward <= -1.0 || g_forward >= 1.0 || g_back <= -1.0 || g_back >= 1.0 {
+            panic!("henyey-greenstein asymmetry must be between -1 and 1");
+        }
+        if !(0.0..=1.0).contains(&weight) {
+            panic!("double henyey-greenstein weight must be between 0 and 1");
+        }
+
+        Arc::new(Box::new(DoubleHenyeyGreensteinPhaseFunction {
+            g_forward,
+            g_back,
+            weight,
+        }))
+    }
+}
+
+impl PhaseFunction for DoubleHenyeyGreensteinPhaseFunction {
+    fn get_value(&self, direction_in: Vector3<f32>, direction_out: Vector3<f32>) -> f32 {
+        let cos_theta = get_cos_theta(direction_in, direction_out);
+        (self.weight * henyey_greenstein(cos_theta, self.g_forward))
+            + ((1.0 - self.weight) * henyey_greenstein(cos_theta, self.g_back))
+    }
+
+    // picks a lobe by its weight, which makes the mix of the two lobes' pdfs the pdf
+    fn generate(&self, direction_in: Vector3<f32>) -> Vector3<f32> {
+        if next_rand_f32() < self.weight {
+            sample_henyey_greenstein(direction_in, self.g_forward)
+        } else {
+            sample_henyey_greenstein(direction_in, self.g_back)
+        }
+    }
+}
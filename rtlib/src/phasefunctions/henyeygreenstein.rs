@@ -0,0 +1,32 @@
+use crate::phasefunctions::{
+    get_cos_theta, henyey_greenstein, sample_henyey_greenstein, PhaseFunction, ThreadPhaseFunction,
+};
+use crate::Vector3;
+use std::sync::Arc;
+
+/// The Henyey-Greenstein lobe.  The asymmetry `g` is the average cosine between the
+/// directions in and out: positive values scatter forward like haze and fog, negative
+/// values back toward the light, and 0 is isotropic.
+pub struct HenyeyGreensteinPhaseFunction {
+    g: f32,
+}
+
+impl HenyeyGreensteinPhaseFunction {
+    pub fn new(g: f32) -> ThreadPhaseFunction {
+        if g <= -1.0 || g >= 1.0 {
+            panic!("henyey-greenstein asymmetry must be between -1 and 1");
+        }
+
+        Arc::new(Box::new(HenyeyGreensteinPhaseFunction { g }))
+    }
+}
+
+impl PhaseFunction for HenyeyGreensteinPhaseFunction {
+    fn get_value(&self, direction_in: Vector3<f32>, direction_out: Vector3<f32>) -> f32 {
+        henyey_greenstein(get_cos_theta(direction_in, direction_out), self.g)
+    }
+
+    fn generate(&self, direction_in: Vector3<f32>) -> Vector3<f32> {
+        sample_henyey_greenstein(direction_in, self.g)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::phasefunctions::{PhaseFunction, ThreadPhaseFunction};
+use crate::{get_random_in_unit_sphere, Vector3};
+use std::f32;
+use std::sync::Arc;
+
+/// Scatters equally in every direction.
+pub struct IsotropicPhaseFunction {}
+
+impl IsotropicPhaseFunction {
+    pub fn new() -> ThreadPhaseFunction {
+        Arc::new(Box::new(IsotropicPhaseFunction {}))
+    }
+}
+
+impl PhaseFunction for IsotropicPhaseFunction {
+    fn get_value(&self, _direction_in: Vector3<f32>, _direction_out: Vector3<f32>) -> f32 {
+        1.0 / (4.0 * f32::consts::PI)
+    }
+
+    fn generate(&self, _direction_in: Vector3<f32>) -> Vector3<f32> {
+        get_random_in_unit_sphere()
+    }
+}
@@ -0,0 +1,54 @@
+mod doublehenyeygreenstein;
+mod henyeygreenstein;
+mod isotropic;
+
+pub use self::doublehenyeygreenstein::DoubleHenyeyGreensteinPhaseFunction;
+pub use self::henyeygreenstein::HenyeyGreensteinPhaseFunction;
+pub use self::isotropic::IsotropicPhaseFunction;
+
+use crate::next_rand_f32;
+use crate::pdfs::OrthoNormalBase;
+use crate::{to_unit_vector, vec3, InnerSpace, Vector3};
+use std::f32;
+use std::sync::Arc;
+
+/// How a medium spreads the light it scatters.  Directions are the way light travels, so
+/// `direction_in` points into the scattering point and `direction_out` away from it,
+/// and neither needs to be a unit vector.  Phase functions integrate to one over the
+/// sphere, so the value is also the pdf of `generate`.
+pub trait PhaseFunction: Sync {
+    fn get_value(&self, direction_in: Vector3<f32>, direction_out: Vector3<f32>) -> f32;
+    fn generate(&self, direction_in: Vector3<f32>) -> Vector3<f32>;
+}
+
+pub type ThreadPhaseFunction = Arc<Box<dyn PhaseFunction + Send>>;
+
+fn get_cos_theta(direction_in: Vector3<f32>, direction_out: Vector3<f32>) -> f32 {
+    to_unit_vector(direction_in).dot(to_unit_vector(direction_out))
+}
+
+// Henyey-Greenstein with asymmetry g, forward scattering for g > 0.
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denominator = 1.0 + (g * g) - (2.0 * g * cos_theta);
+    (1.0 - (g * g)) / (4.0 * f32::consts::PI * denominator * denominator.sqrt())
+}
+
+// Inverts the cdf of henyey_greenstein for the angle and picks the turn around
+// direction_in uniformly.
+fn sample_henyey_greenstein(direction_in: Vector3<f32>, g: f32) -> Vector3<f32> {
+    let r1 = next_rand_f32();
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - (2.0 * r1)
+    } else {
+        let s = (1.0 - (g * g)) / (1.0 - g + (2.0 * g * r1));
+        (1.0 + (g * g) - (s * s)) / (2.0 * g)
+    }
+    .clamp(-1.0, 1.0);
+    let sin_theta = (1.0 - (cos_theta * cos_theta)).max(0.0).sqrt();
+    let phi = 2.0 * f32::consts::PI * next_rand_f32();
+    OrthoNormalBase::from_w(direction_in).local(vec3(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
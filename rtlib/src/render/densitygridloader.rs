@@ -0,0 +1,67 @@
+use crate::hitables::DensityGrid;
+use crate::render::SceneLoadError;
+use serde::Deserialize;
+use std::fs;
+
+#[derive(Deserialize)]
+struct JsonDensityGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+}
+
+// Loads the densities of a grid medium.  A .json file holds
+// `{ "resolution": [x, y, z], "values": [...] }`, anything else is read as raw little
+// endian f32 values and needs the resolution from the caller.  Either way the values
+// are ordered x fastest, then y, then z.
+pub fn load_density_grid_file(
+    file_path: &str,
+    resolution: Option<[usize; 3]>,
+) -> Result<DensityGrid, SceneLoadError> {
+    let data = fs::read(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+    let (resolution, values) = if file_path.to_lowercase().ends_with(".json") {
+        let grid: JsonDensityGrid = serde_json::from_slice(&data)
+            .map_err(|e| SceneLoadError::invalid_data(file_path, &e.to_string()))?;
+        (grid.resolution, grid.values)
+    } else {
+        let resolution = resolution.ok_or_else(|| {
+            SceneLoadError::invalid_data(file_path, "raw density grids need a resolution")
+        })?;
+        if data.len() % 4 != 0 {
+            return Err(SceneLoadError::invalid_data(
+                file_path,
+                "raw density grid size is not a multiple of 4 bytes",
+            ));
+        }
+        let values = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        (resolution, values)
+    };
+
+    if resolution.iter().any(|n| *n < 2) {
+        return Err(SceneLoadError::invalid_data(
+            file_path,
+            "density grid needs at least 2 samples along each axis",
+        ));
+    }
+    if values.len() != resolution[0] * resolution[1] * resolution[2] {
+        return Err(SceneLoadError::invalid_data(
+            file_path,
+            &format!(
+                "density grid has {} values, a {:?} grid needs {}",
+                values.len(),
+                resolution,
+                resolution[0] * resolution[1] * resolution[2]
+            ),
+        ));
+    }
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err(SceneLoadError::invalid_data(
+            file_path,
+            "density grid values must be finite and not negative",
+        ));
+    }
+
+    Ok(DensityGrid::new(resolution, values))
+}
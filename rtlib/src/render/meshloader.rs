@@ -0,0 +1,97 @@
+use crate::hitables::{MeshFace, ThreadHitable, Transform, TriangleMesh};
+use crate::materials::{CompiledMaterials, LambertianMaterial};
+use crate::render::Color;
+use crate::textures::VertexColorTexture;
+use crate::{vec3, Matrix4, Vector3};
+use cgmath::Deg;
+use std::f32;
+use std::sync::Arc;
+
+/// Triangle data read from a mesh file (PLY, STL).  Faces are already wound the way
+/// `Triangle` and `TriangleMesh` expect, and `normals` / `colors` are either empty or
+/// indexed like `positions`.
+#[derive(Default)]
+pub struct LoadedMesh {
+    pub positions: Vec<Vector3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub colors: Vec<Color>,
+    pub faces: Vec<[u32; 3]>,
+}
+
+impl LoadedMesh {
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty()
+    }
+
+    pub fn to_triangle_mesh(&self, material_id: u64) -> TriangleMesh {
+        let has_normals = !self.normals.is_empty();
+        let faces = self
+            .faces
+            .iter()
+            .map(|f| MeshFace::new(*f, if has_normals { Some(*f) } else { None }, None))
+            .collect();
+
+        TriangleMesh::build(
+            Arc::new(self.positions.clone()),
+            Arc::new(self.normals.clone()),
+            Arc::new(vec![]),
+            faces,
+            material_id,
+        )
+        .with_colors(Arc::new(self.colors.clone()))
+    }
+
+    /// Builds the mesh with the given material and places it in the scene.
+    pub fn to_hitable(&self, material_id: u64, placement: &MeshPlacement) -> ThreadHitable {
+        placement.place(Arc::new(Box::new(self.to_triangle_mesh(material_id))))
+    }
+
+    /// Builds the mesh with a Lambertian material whose albedo comes from the vertex
+    /// colors.  Meshes without colors fall back to `default_color`.
+    pub fn to_vertex_colored_hitable(
+        &self,
+        materials: &mut CompiledMaterials,
+        placement: &MeshPlacement,
+        default_color: Color,
+    ) -> ThreadHitable {
+        let texture = VertexColorTexture::new(default_color);
+        let material_id = materials.add(LambertianMaterial::new(texture));
+        self.to_hitable(material_id, placement)
+    }
+}
+
+/// Where a loaded mesh ends up in the scene: it is scaled uniformly about its origin,
+/// rotated around Y (degrees) and then translated.
+#[derive(Copy, Clone, Debug)]
+pub struct MeshPlacement {
+    pub translation: Vector3<f32>,
+    pub angle_y: f32,
+    pub scale: f32,
+}
+
+impl MeshPlacement {
+    pub fn new(translation: Vector3<f32>, angle_y: f32, scale: f32) -> MeshPlacement {
+        MeshPlacement {
+            translation,
+            angle_y,
+            scale,
+        }
+    }
+
+    pub fn identity() -> MeshPlacement {
+        MeshPlacement::new(vec3(0.0, 0.0, 0.0), 0.0, 1.0)
+    }
+
+    pub fn place(&self, hitable: ThreadHitable) -> ThreadHitable {
+        if self.scale == 1.0 && self.angle_y == 0.0 && self.translation == vec3(0.0, 0.0, 0.0) {
+            return hitable;
+        }
+        Transform::new(hitable, self.to_matrix())
+    }
+
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from_angle_y(Deg(self.angle_y))
+            * Matrix4::from_scale(self.scale)
+    }
+}
@@ -1,11 +1,15 @@
 mod color;
 mod consolerenderer;
+mod densitygridloader;
+mod meshloader;
 mod nffparser;
+mod objparser;
 mod perpixelrenderer;
 mod pixel;
 mod pixelarraybuffer;
 mod pixelbuffer;
 mod pixeldata;
+mod plyloader;
 mod ray;
 mod raytracer;
 mod renderconfig;
@@ -13,16 +17,24 @@ mod renderer;
 mod renderexec;
 mod samplingraytracer;
 mod scene;
+mod scenedescription;
+mod scenefileparser;
 mod scenegenerator;
+mod sceneloaderror;
+mod stlloader;
 
 pub use self::color::Color;
 pub use self::consolerenderer::ConsoleRenderer;
+pub use self::densitygridloader::load_density_grid_file;
+pub use self::meshloader::{LoadedMesh, MeshPlacement};
 pub use self::nffparser::NffParser;
+pub use self::objparser::ObjParser;
 pub use self::perpixelrenderer::PerPixelRenderer;
 pub use self::pixel::Pixel;
 pub use self::pixelarraybuffer::PixelArrayBuffer;
 pub use self::pixelbuffer::PixelBuffer;
 pub use self::pixeldata::PixelData;
+pub use self::plyloader::load_ply_file;
 pub use self::ray::Ray;
 pub use self::raytracer::RayTracer;
 pub use self::renderconfig::RenderConfig;
@@ -30,5 +42,14 @@ pub use self::renderer::Renderer;
 pub use self::renderexec::RenderExec;
 pub use self::samplingraytracer::SamplingRayTracer;
 pub use self::scene::Scene;
+pub use self::scenedescription::{
+    CameraDescription, CompiledSceneDescription, DensityGridDescription, HeightMapDescription,
+    HitableDescription, KeyframeDescription, MaterialDescription, PhaseFunctionDescription,
+    ScalarReference, SceneDescription, SdfNodeDescription, TextureDescription, TextureReference,
+    TransformDescription,
+};
+pub use self::scenefileparser::SceneFileParser;
 pub use self::scenegenerator::create_scene;
 pub use self::scenegenerator::SceneGenerator;
+pub use self::sceneloaderror::{SceneLine, SceneLoadError};
+pub use self::stlloader::load_stl_file;
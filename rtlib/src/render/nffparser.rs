@@ -1,6 +1,5 @@
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::sync::Arc;
 
 use crate::cameras::{NormalCamera, ThreadCamera};
@@ -24,8 +23,11 @@ impl NffParser {
     pub fn new(
         file_path: &str,
         render_config: &RenderConfig,
-    ) -> Arc<Box<dyn SceneGenerator + Send>> {
-        Arc::new(Box::new(parse_nff_file(file_path, render_config)))
+    ) -> Result<Arc<Box<dyn SceneGenerator + Send>>, SceneLoadError> {
+        Ok(Arc::new(Box::new(parse_nff_file(
+            file_path,
+            render_config,
+        )?)))
     }
 }
 
@@ -36,7 +38,7 @@ impl SceneGenerator for NffParser {
             self.materials.clone(),
             &self.light_hitable,
             self.get_background_color(),
-            true,
+            &BvhBuilder::default(),
         )
     }
 
@@ -57,30 +59,182 @@ impl SceneGenerator for NffParser {
     }
 }
 
-fn as_f32(s: &str) -> f32 {
-    s.parse::<f32>().unwrap()
+// Polygons meeting at a sharper angle than this keep a hard edge when smoothing.
+const NFF_CREASE_ANGLE: f32 = 45.0;
+
+// Collects the polygons of one material so they can be turned into a single
+// TriangleMesh with shared (welded) vertices once the whole file has been read.
+// Patches bring their own vertex normals, polygons get smoothed ones.
+struct NffMesh {
+    positions: Vec<Vector3<f32>>,
+    vertex_lookup: HashMap<[u32; 3], u32>,
+    normals: Vec<Vector3<f32>>,
+    normal_lookup: HashMap<[u32; 3], u32>,
+    faces: Vec<MeshFace>,
+}
+
+fn to_key(v: Vector3<f32>) -> [u32; 3] {
+    [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()]
 }
 
-fn as_u32(s: &str) -> u32 {
-    s.parse::<u32>().unwrap()
+fn add_welded(
+    items: &mut Vec<Vector3<f32>>,
+    lookup: &mut HashMap<[u32; 3], u32>,
+    v: Vector3<f32>,
+) -> u32 {
+    *lookup.entry(to_key(v)).or_insert_with(|| {
+        items.push(v);
+        (items.len() - 1) as u32
+    })
+}
+
+impl NffMesh {
+    fn new() -> NffMesh {
+        NffMesh {
+            positions: Vec::new(),
+            vertex_lookup: HashMap::new(),
+            normals: Vec::new(),
+            normal_lookup: HashMap::new(),
+            faces: Vec::new(),
+        }
+    }
+
+    fn add_polygon(&mut self, poly_vectors: &[Vector3<f32>]) {
+        if poly_vectors.len() < 3 {
+            return;
+        }
+
+        let indices: Vec<u32> = poly_vectors
+            .iter()
+            .map(|v| add_welded(&mut self.positions, &mut self.vertex_lookup, *v))
+            .collect();
+        for i in 2..indices.len() {
+            self.faces.push(MeshFace::new(
+                [indices[0], indices[i - 1], indices[i]],
+                None,
+                None,
+            ));
+        }
+    }
+
+    fn add_patch(&mut self, poly_vectors: &[Vector3<f32>], poly_normals: &[Vector3<f32>]) {
+        if poly_vectors.len() < 3 {
+            return;
+        }
+
+        let indices: Vec<u32> = poly_vectors
+            .iter()
+            .map(|v| add_welded(&mut self.positions, &mut self.vertex_lookup, *v))
+            .collect();
+        let normal_indices: Vec<u32> = poly_normals
+            .iter()
+            .map(|n| add_welded(&mut self.normals, &mut self.normal_lookup, n.normalize()))
+            .collect();
+        for i in 2..indices.len() {
+            self.faces.push(MeshFace::new(
+                [indices[0], indices[i - 1], indices[i]],
+                Some([normal_indices[0], normal_indices[i - 1], normal_indices[i]]),
+                None,
+            ));
+        }
+    }
+
+    fn into_hitable(mut self, material_id: u64) -> ThreadHitable {
+        let normals = if self.normals.is_empty() {
+            TriangleMesh::smooth_normals(&self.positions, &mut self.faces, NFF_CREASE_ANGLE)
+        } else {
+            self.normals
+        };
+        TriangleMesh::new(
+            Arc::new(self.positions),
+            Arc::new(normals),
+            Arc::new(vec![]),
+            self.faces,
+            material_id,
+        )
+    }
+}
+
+// Maps the NFF shading parameters onto the closest material we have.  Transmitting
+// surfaces become glass, surfaces that are mostly specular become metal with a fuzz
+// derived from the Phong exponent, everything else is diffuse.
+fn compile_nff_material(
+    color: Color,
+    kd: f32,
+    ks: f32,
+    shine: f32,
+    transmittance: f32,
+    index_of_refraction: f32,
+) -> ThreadMaterial {
+    if transmittance > 0.0 {
+        DialectricMaterial::new(index_of_refraction.max(1.0))
+    } else if ks > 0.0 && ks >= kd {
+        let fuzz = (2.0 / (shine.max(0.0) + 2.0)).sqrt();
+        MetalMaterial::new(color, fuzz)
+    } else {
+        let albedo = if kd > 0.0 {
+            color.multiply_by_scalar(kd.min(1.0))
+        } else {
+            color
+        };
+        LambertianMaterial::new(ColorTexture::new(albedo.r(), albedo.g(), albedo.b()))
+    }
 }
 
 enum LookingFor {
     Instruction,
-    ViewpointFrom,
-    ViewpointAt,
-    ViewpointUp,
-    ViewpointAngle,
-    ViewpointHither,
-    ViewpointResolution,
+    Viewpoint,
     Polygon,
+    PolygonPatch,
+    ConeBase,
+    ConeApex,
+}
+
+impl LookingFor {
+    // what an unfinished statement was waiting for when the file ended
+    fn describe(&self) -> Option<&'static str> {
+        match self {
+            LookingFor::Instruction => None,
+            LookingFor::Viewpoint => Some("viewpoint block has no 'resolution' line"),
+            LookingFor::Polygon => Some("polygon has fewer vertices than its count"),
+            LookingFor::PolygonPatch => Some("polygon patch has fewer vertices than its count"),
+            LookingFor::ConeBase | LookingFor::ConeApex => Some("cone is missing its end lines"),
+        }
+    }
+}
+
+// A vertex line of a "p" or "pp" statement.  Anything that doesn't start with a
+// number means the vertex count was too large and we've run into the next statement.
+fn check_vertex_line(line: &SceneLine<'_>, looking_for: &LookingFor) -> Result<(), SceneLoadError> {
+    if line.tokens()[0].parse::<f32>().is_err() {
+        return Err(SceneLoadError::truncated(
+            line.path(),
+            line.get_number(),
+            looking_for.describe().unwrap_or_default(),
+        ));
+    }
+    Ok(())
+}
+
+fn get_polygon_count(line: &SceneLine<'_>) -> Result<u32, SceneLoadError> {
+    let count = line.get_u32(1)?;
+    if count < 3 {
+        return Err(line.error(1, "polygon needs at least 3 vertices"));
+    }
+    Ok(count)
 }
 
 // see: http://www.fileformat.info/format/nff/egff.htm
-fn parse_nff_file(file_path: &str, render_config: &RenderConfig) -> NffParser {
+// and: Eric Haines, "Standard Procedural Databases", NFF description
+fn parse_nff_file(
+    file_path: &str,
+    render_config: &RenderConfig,
+) -> Result<NffParser, SceneLoadError> {
     let mut camera_from = vec3(0.0, 0.0, 0.0);
     let mut camera_at = vec3(0.0, 0.0, 0.0);
     let mut camera_up = vec3(0.0, 0.0, 0.0);
+    let mut vertical_fov = 50.0;
+    let mut hither = 0.0;
     let mut image_width = render_config.width;
     let mut image_height = render_config.height;
 
@@ -90,165 +244,197 @@ fn parse_nff_file(file_path: &str, render_config: &RenderConfig) -> NffParser {
 
     let mut current_item_counter = 0;
     let mut poly_vectors: Vec<Vector3<f32>> = Vec::new();
+    let mut poly_normals: Vec<Vector3<f32>> = Vec::new();
+    let mut cone_base = vec3(0.0, 0.0, 0.0);
+    let mut cone_base_radius = 0.0;
+    let mut meshes: BTreeMap<u64, NffMesh> = BTreeMap::new();
+    let mut patch_meshes: BTreeMap<u64, NffMesh> = BTreeMap::new();
     let mut materials: CompiledMaterials = CompiledMaterials::new();
     let mut hitables = vec![];
     let mut current_material_id: u64 = 0;
     let mut lights = vec![];
 
-    let f = File::open(file_path).unwrap();
-    let file = BufReader::new(&f);
-    for (_num, line) in file.lines().enumerate() {
-        let l = line.unwrap();
+    let contents = fs::read_to_string(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+    let mut line_count = 0;
+    for (num, text) in contents.lines().enumerate() {
+        let line = SceneLine::new(file_path, num + 1, text);
+        line_count = num + 1;
+        if line.is_empty() {
+            continue;
+        }
 
         match looking_for {
             LookingFor::Instruction => {
-                let vec: Vec<&str> = l.split_whitespace().collect();
-                if vec.len() > 0 {
-                    let instruction = vec[0];
-
-                    match instruction {
-                        "b" => {
-                            // background color
-                            background = Color::new(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3]));
-                        }
-                        "v" => {
-                            // viewpoint location
-                            looking_for = LookingFor::ViewpointFrom;
-                        }
-                        "l" => {
-                            // positional light
-                            let light_color = if vec.len() == 7 {
-                                Color::new(as_f32(vec[4]), as_f32(vec[5]), as_f32(vec[6]))
-                            } else {
-                                Color::new(1.0, 1.0, 1.0)
-                            };
-                            let light_matid = materials.add(DiffuseLight::new(ColorTexture::new(
-                                light_color.r(),
-                                light_color.g(),
-                                light_color.b(),
-                            )));
-                            lights.push(Sphere::new(
-                                vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3])),
-                                0.01,
-                                light_matid,
-                            ));
-                            hitables.push(Sphere::new(
-                                vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3])),
-                                0.01,
-                                light_matid,
-                            ));
-                        }
-                        "f" => {
-                            // object material properties
-                            // "f" red green blue Kd Ks Shine T index_of_refraction
-                            // Kd Diffuse component
-                            // Ks Specular
-                            // Shine Phong cosine power for highlights
-                            // T Transmittance (fraction of contribution of the transmitting ray).
-                            // Usually, 0 <= Kd <= 1 and 0 <= Ks <= 1, though it is not required that Kd + Ks = 1. Note that transmitting objects (T > 0) are considered to have two sides for algorithms that need these (normally, objects have one side).
-                            // todo: i don't think i'm assigning the correct values into my solidmaterial yet
-                            current_material_id = materials.add(LambertianMaterial::new(
-                                ColorTexture::new(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3])),
-                            ));
-                            // current_material = SolidMaterial::new(
-                            //     as_f64(vec[6]),
-                            //     as_f64(vec[5]),
-                            //     as_f64(vec[8]),
-                            //     as_f64(vec[7]),
-                            //     ColorVector::new(as_f64(vec[1]), as_f64(vec[2]), as_f64(vec[3])),
-                            // );
-                        }
-                        "c" => {
-                            // cone or cylinder
-                        }
-                        "s" => {
-                            // sphere
-                            hitables.push(Sphere::new(
-                                vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3])),
-                                as_f32(vec[4]),
+                let instruction = line.tokens()[0];
+
+                match instruction {
+                    "b" => {
+                        // background color
+                        let c = line.get_vector(1)?;
+                        background = Color::new(c.x, c.y, c.z);
+                    }
+                    "v" => {
+                        // viewpoint: from, at, up, angle, hither and resolution lines follow
+                        looking_for = LookingFor::Viewpoint;
+                    }
+                    "l" => {
+                        // positional light
+                        let position = line.get_vector(1)?;
+                        let light_color = if line.len() >= 7 {
+                            let c = line.get_vector(4)?;
+                            Color::new(c.x, c.y, c.z)
+                        } else {
+                            Color::new(1.0, 1.0, 1.0)
+                        };
+                        let light_matid = materials.add(DiffuseLight::new(ColorTexture::new(
+                            light_color.r(),
+                            light_color.g(),
+                            light_color.b(),
+                        )));
+                        lights.push(Sphere::new(position, 0.01, light_matid));
+                        hitables.push(Sphere::new(position, 0.01, light_matid));
+                    }
+                    "f" => {
+                        // object material properties
+                        // "f" red green blue Kd Ks Shine T index_of_refraction
+                        // Kd Diffuse component
+                        // Ks Specular
+                        // Shine Phong cosine power for highlights
+                        // T Transmittance (fraction of contribution of the transmitting ray).
+                        // Usually, 0 <= Kd <= 1 and 0 <= Ks <= 1, though it is not required that Kd + Ks = 1. Note that transmitting objects (T > 0) are considered to have two sides for algorithms that need these (normally, objects have one side).
+                        let c = line.get_vector(1)?;
+                        current_material_id = materials.add(compile_nff_material(
+                            Color::new(c.x, c.y, c.z),
+                            line.get_f32(4)?,
+                            line.get_f32(5)?,
+                            line.get_f32(6)?,
+                            line.get_f32(7)?,
+                            line.get_f32(8)?,
+                        ));
+                    }
+                    "c" => {
+                        // cone or cylinder
+                        // "c" followed by "base.x base.y base.z base_radius" and
+                        // "apex.x apex.y apex.z apex_radius" lines, some files put all
+                        // eight numbers on the "c" line itself.  Negative radii only mean
+                        // the inside is visible, which we don't distinguish.
+                        if line.len() > 1 {
+                            hitables.push(Cone::new(
+                                line.get_vector(1)?,
+                                line.get_f32(4)?.abs(),
+                                line.get_vector(5)?,
+                                line.get_f32(8)?.abs(),
+                                false,
+                                360.0,
                                 current_material_id,
                             ));
+                        } else {
+                            looking_for = LookingFor::ConeBase;
                         }
-                        "p" => {
-                            // polygon
-                            current_item_counter = as_u32(vec[1]);
-                            poly_vectors = Vec::new();
-                            looking_for = LookingFor::Polygon;
-                        }
-                        "pp" => {
-                            // polygon patch
-                        }
-                        "#" => {
-                            // comment
-                        }
-                        _ => {
-                            // unknown
-                        }
-                    };
-                }
+                    }
+                    "s" => {
+                        // sphere
+                        hitables.push(Sphere::new(
+                            line.get_vector(1)?,
+                            line.get_f32(4)?,
+                            current_material_id,
+                        ));
+                    }
+                    "p" => {
+                        // polygon
+                        current_item_counter = get_polygon_count(&line)?;
+                        poly_vectors = Vec::new();
+                        looking_for = LookingFor::Polygon;
+                    }
+                    "pp" => {
+                        // polygon patch, each vertex line also carries its normal
+                        current_item_counter = get_polygon_count(&line)?;
+                        poly_vectors = Vec::new();
+                        poly_normals = Vec::new();
+                        looking_for = LookingFor::PolygonPatch;
+                    }
+                    "#" => {
+                        // comment
+                    }
+                    _ => {
+                        // unknown
+                    }
+                };
             }
             LookingFor::Polygon => {
-                if current_item_counter > 0 {
-                    current_item_counter = current_item_counter - 1;
-                    let vec: Vec<&str> = l.split_whitespace().collect();
-                    poly_vectors.push(vec3(as_f32(vec[0]), as_f32(vec[1]), as_f32(vec[2])));
-                }
+                check_vertex_line(&line, &looking_for)?;
+                current_item_counter -= 1;
+                poly_vectors.push(line.get_vector(0)?);
 
                 if current_item_counter == 0 {
-                    if poly_vectors.len() >= 3 {
-                        let first_vert = poly_vectors[0];
-                        let mut prev_vert = poly_vectors[1];
-                        let mut this_vert = poly_vectors[2];
-
-                        hitables.push(Triangle::new(
-                            vec![first_vert, prev_vert, this_vert],
-                            current_material_id,
-                        ));
+                    meshes
+                        .entry(current_material_id)
+                        .or_insert_with(NffMesh::new)
+                        .add_polygon(&poly_vectors);
 
-                        for i in 3..poly_vectors.len() {
-                            prev_vert = this_vert;
-                            this_vert = poly_vectors[i];
+                    looking_for = LookingFor::Instruction;
+                }
+            }
+            LookingFor::PolygonPatch => {
+                check_vertex_line(&line, &looking_for)?;
+                current_item_counter -= 1;
+                poly_vectors.push(line.get_vector(0)?);
+                poly_normals.push(line.get_vector(3)?);
 
-                            hitables.push(Triangle::new(
-                                vec![first_vert, prev_vert, this_vert],
-                                current_material_id,
-                            ));
-                        }
-                    }
+                if current_item_counter == 0 {
+                    patch_meshes
+                        .entry(current_material_id)
+                        .or_insert_with(NffMesh::new)
+                        .add_patch(&poly_vectors, &poly_normals);
 
                     looking_for = LookingFor::Instruction;
                 }
             }
-            LookingFor::ViewpointFrom => {
-                let vec: Vec<&str> = l.split_whitespace().collect();
-                camera_from = vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3]));
-                looking_for = LookingFor::ViewpointAt;
-            }
-            LookingFor::ViewpointAt => {
-                let vec: Vec<&str> = l.split_whitespace().collect();
-                camera_at = vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3]));
-                looking_for = LookingFor::ViewpointUp;
-            }
-            LookingFor::ViewpointUp => {
-                let vec: Vec<&str> = l.split_whitespace().collect();
-                camera_up = vec3(as_f32(vec[1]), as_f32(vec[2]), as_f32(vec[3]));
-                looking_for = LookingFor::ViewpointAngle;
-            }
-            LookingFor::ViewpointAngle => {
-                looking_for = LookingFor::ViewpointHither;
+            LookingFor::ConeBase => {
+                check_vertex_line(&line, &looking_for)?;
+                cone_base = line.get_vector(0)?;
+                cone_base_radius = line.get_f32(3)?.abs();
+                looking_for = LookingFor::ConeApex;
             }
-            LookingFor::ViewpointHither => {
-                looking_for = LookingFor::ViewpointResolution;
-            }
-            LookingFor::ViewpointResolution => {
-                let vec: Vec<&str> = l.split_whitespace().collect();
-                image_width = as_u32(vec[1]);
-                image_height = as_u32(vec[2]);
+            LookingFor::ConeApex => {
+                check_vertex_line(&line, &looking_for)?;
+                hitables.push(Cone::new(
+                    cone_base,
+                    cone_base_radius,
+                    line.get_vector(0)?,
+                    line.get_f32(3)?.abs(),
+                    false,
+                    360.0,
+                    current_material_id,
+                ));
                 looking_for = LookingFor::Instruction;
             }
+            LookingFor::Viewpoint => match line.tokens()[0] {
+                "from" => camera_from = line.get_vector(1)?,
+                "at" => camera_at = line.get_vector(1)?,
+                "up" => camera_up = line.get_vector(1)?,
+                "angle" => vertical_fov = line.get_f32(1)?,
+                "hither" => hither = line.get_f32(1)?,
+                "resolution" => {
+                    image_width = line.get_u32(1)?;
+                    image_height = line.get_u32(2)?;
+
+                    // resolution is the last line of the viewpoint block
+                    looking_for = LookingFor::Instruction;
+                }
+                _ => return Err(line.error(0, "unknown viewpoint line")),
+            },
         }
     }
 
+    if let Some(description) = looking_for.describe() {
+        return Err(SceneLoadError::truncated(
+            file_path,
+            line_count,
+            description,
+        ));
+    }
+
     if image_width == 0 {
         image_width = 100;
     }
@@ -256,14 +442,20 @@ fn parse_nff_file(file_path: &str, render_config: &RenderConfig) -> NffParser {
         image_height = 100
     }
 
-    let vertical_fov = 50.0;
     let aspect = image_width as f32 / image_height as f32;
     let aperture = 0.0;
     let focus_distance = (camera_from - camera_at).magnitude();
 
+    for (material_id, mesh) in meshes {
+        hitables.push(mesh.into_hitable(material_id));
+    }
+    for (material_id, mesh) in patch_meshes {
+        hitables.push(mesh.into_hitable(material_id));
+    }
+
     let light_hitable = HitableList::from_vec(lights);
 
-    NffParser {
+    Ok(NffParser {
         background,
         hitables,
         materials: Arc::new(Box::new(materials)),
@@ -274,14 +466,17 @@ fn parse_nff_file(file_path: &str, render_config: &RenderConfig) -> NffParser {
             render_config.ray_trace_depth,
             render_config.num_samples,
         ),
-        camera: Arc::new(Box::new(NormalCamera::new(
-            camera_from,
-            camera_at,
-            camera_up,
-            vertical_fov,
-            aspect,
-            aperture,
-            focus_distance,
-        ))),
-    }
+        camera: Arc::new(Box::new(
+            NormalCamera::new(
+                camera_from,
+                camera_at,
+                camera_up,
+                vertical_fov,
+                aspect,
+                aperture,
+                focus_distance,
+            )
+            .with_near_clip(hither),
+        )),
+    })
 }
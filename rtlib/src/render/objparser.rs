@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cameras::{NormalCamera, ThreadCamera};
+use crate::hitables::*;
+use crate::materials::*;
+use crate::render::*;
+use crate::textures::*;
+use crate::{vec3, InnerSpace, Point2, Vector3};
+
+const OBJ_VERTICAL_FOV: f32 = 40.0;
+
+pub struct ObjParser {
+    background: Color,
+    render_config: RenderConfig,
+    look_at: Vector3<f32>,
+    camera_distance: f32,
+
+    hitables: Vec<ThreadHitable>,
+    materials: Arc<Box<CompiledMaterials>>,
+    light_hitable: ThreadHitable,
+}
+
+impl ObjParser {
+    pub fn new(
+        file_path: &str,
+        render_config: &RenderConfig,
+    ) -> Result<Arc<Box<dyn SceneGenerator + Send>>, SceneLoadError> {
+        Ok(Arc::new(Box::new(parse_obj_file(
+            file_path,
+            render_config,
+        )?)))
+    }
+}
+
+impl SceneGenerator for ObjParser {
+    fn get_scene(&self) -> Scene {
+        create_scene(
+            &self.hitables,
+            self.materials.clone(),
+            &self.light_hitable,
+            self.get_background_color(),
+            &BvhBuilder::default(),
+        )
+    }
+
+    fn get_camera(&self) -> ThreadCamera {
+        self.get_camera_angled(0.0, 0.0)
+    }
+
+    // OBJ files have no camera, so we orbit around the center of the model at a
+    // distance where its bounding sphere fills the view.
+    fn get_camera_angled(&self, angle_x: f32, angle_y: f32) -> ThreadCamera {
+        let radians_x = angle_x * std::f32::consts::PI / 180.0;
+        let radians_y = angle_y * std::f32::consts::PI / 180.0;
+
+        let look_from = self.look_at
+            + vec3(
+                self.camera_distance * radians_x.sin() * radians_y.cos(),
+                self.camera_distance * radians_y.sin(),
+                self.camera_distance * radians_x.cos() * radians_y.cos(),
+            );
+
+        Arc::new(Box::new(NormalCamera::new(
+            look_from,
+            self.look_at,
+            Vector3::unit_y(),
+            OBJ_VERTICAL_FOV,
+            self.render_config.width as f32 / self.render_config.height as f32,
+            0.0,
+            self.camera_distance,
+        )))
+    }
+
+    fn get_render_config(&self) -> RenderConfig {
+        self.render_config.clone()
+    }
+
+    fn get_background_color(&self) -> Color {
+        self.background
+    }
+}
+
+fn get_color(line: &SceneLine<'_>) -> Result<Color, SceneLoadError> {
+    let c = line.get_vector(1)?;
+    Ok(Color::new(c.x, c.y, c.z))
+}
+
+fn get_max_component(color: Color) -> f32 {
+    color.r().max(color.g()).max(color.b())
+}
+
+// OBJ indices are 1 based and negative values count back from the most recent element.
+// `token_index` is the face corner the index came from, for error reporting.
+fn as_index(
+    line: &SceneLine<'_>,
+    token_index: usize,
+    s: &str,
+    count: usize,
+) -> Result<u32, SceneLoadError> {
+    let index = s
+        .parse::<i64>()
+        .map_err(|_| line.error(token_index, "expected a vertex index"))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(line.error(
+            token_index,
+            "index refers to an element that does not exist",
+        ));
+    }
+    Ok(resolved as u32)
+}
+
+// Material description read from a .mtl file.
+// see: http://paulbourke.net/dataformats/mtl/
+struct ObjMaterial {
+    diffuse: Color,
+    specular: Color,
+    emission: Color,
+    specular_exponent: f32,
+    refraction_index: f32,
+    dissolve: f32,
+    illumination_model: u32,
+    diffuse_map: Option<PathBuf>,
+    // the physically based extension, which switches to a principled material
+    // see: http://exocortex.com/blog/extending_wavefront_mtl_to_support_pbr
+    roughness: Option<f32>,
+    metallic: Option<f32>,
+    sheen: Option<f32>,
+    clearcoat: Option<f32>,
+    clearcoat_roughness: Option<f32>,
+}
+
+impl ObjMaterial {
+    fn new() -> ObjMaterial {
+        ObjMaterial {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::zero(),
+            emission: Color::zero(),
+            specular_exponent: 0.0,
+            refraction_index: 1.5,
+            dissolve: 1.0,
+            illumination_model: 2,
+            diffuse_map: None,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+        }
+    }
+
+    fn is_physically_based(&self) -> bool {
+        self.roughness.is_some()
+            || self.metallic.is_some()
+            || self.sheen.is_some()
+            || self.clearcoat.is_some()
+            || self.clearcoat_roughness.is_some()
+    }
+
+    fn is_emissive(&self) -> bool {
+        get_max_component(self.emission) > 0.0
+    }
+
+    fn compile(
+        &self,
+        materials: &mut CompiledMaterials,
+        textures: &mut HashMap<PathBuf, ThreadTexture>,
+    ) -> Result<u64, SceneLoadError> {
+        if self.is_emissive() {
+            return Ok(materials.add(DiffuseLight::new(ColorTexture::new(
+                self.emission.r(),
+                self.emission.g(),
+                self.emission.b(),
+            ))));
+        }
+
+        // illumination models 4, 6, 7 and 9 are the transparent ones
+        let is_transparent =
+            self.dissolve < 1.0 || matches!(self.illumination_model, 4 | 6 | 7 | 9);
+
+        if self.is_physically_based() {
+            let value = |v: f32| {
+                let v = v.clamp(0.0, 1.0);
+                ColorTexture::new(v, v, v)
+            };
+            let defaults = PrincipledParameters::default();
+            // partly dissolved surfaces are partly see through, transparent models fully
+            let transmission = if self.dissolve < 1.0 {
+                value(1.0 - self.dissolve)
+            } else if is_transparent {
+                value(1.0)
+            } else {
+                defaults.transmission
+            };
+            return Ok(materials.add(PrincipledMaterial::new(PrincipledParameters {
+                base_color: self.get_diffuse_texture(textures)?,
+                metallic: self.metallic.map_or(defaults.metallic, value),
+                roughness: self.roughness.map_or(defaults.roughness, value),
+                sheen: self.sheen.map_or(defaults.sheen, value),
+                clearcoat: self.clearcoat.map_or(defaults.clearcoat, value),
+                clearcoat_gloss: self
+                    .clearcoat_roughness
+                    .map_or(defaults.clearcoat_gloss, |r| value(1.0 - r)),
+                transmission,
+                refraction_index: self.refraction_index.max(1.0),
+                ..defaults
+            })));
+        }
+
+        if is_transparent {
+            return Ok(materials.add(DialectricMaterial::new(self.refraction_index.max(1.0))));
+        }
+
+        // a surface whose highlight is brighter than its diffuse color is treated as metal,
+        // using the usual phong exponent to roughness conversion for the fuzz
+        if get_max_component(self.specular) > get_max_component(self.diffuse) {
+            let fuzz = (2.0 / (self.specular_exponent + 2.0)).sqrt().min(1.0);
+            return Ok(materials.add(MetalMaterial::new(self.specular, fuzz)));
+        }
+
+        Ok(materials.add(LambertianMaterial::new(self.get_diffuse_texture(textures)?)))
+    }
+
+    fn get_diffuse_texture(
+        &self,
+        textures: &mut HashMap<PathBuf, ThreadTexture>,
+    ) -> Result<ThreadTexture, SceneLoadError> {
+        let albedo = match &self.diffuse_map {
+            Some(path) => match textures.get(path) {
+                Some(texture) => texture.clone(),
+                None => {
+                    let path_name = path.to_string_lossy();
+                    let pixel_buffer = PixelBuffer::from_image_file(&path_name).map_err(|e| {
+                        SceneLoadError::Io {
+                            path: path_name.to_string(),
+                            description: e.to_string(),
+                        }
+                    })?;
+                    let texture = ImageTexture::new(Arc::new(Box::new(pixel_buffer)));
+                    textures.insert(path.clone(), texture.clone());
+                    texture
+                }
+            },
+            None => ColorTexture::new(self.diffuse.r(), self.diffuse.g(), self.diffuse.b()),
+        };
+        Ok(albedo)
+    }
+}
+
+fn parse_mtl_file(
+    file_path: &Path,
+    obj_materials: &mut HashMap<String, ObjMaterial>,
+) -> Result<(), SceneLoadError> {
+    let path_name = file_path.to_string_lossy();
+    let directory = file_path.parent().unwrap_or_else(|| Path::new("."));
+    let contents = fs::read_to_string(file_path).map_err(|e| SceneLoadError::io(&path_name, &e))?;
+
+    let mut current_name: Option<String> = None;
+    for (num, text) in contents.lines().enumerate() {
+        let line = SceneLine::new(&path_name, num + 1, text);
+        if line.is_empty() {
+            continue;
+        }
+        let vec = line.tokens();
+
+        if vec[0] == "newmtl" {
+            let name = vec[1..].join(" ");
+            obj_materials.insert(name.clone(), ObjMaterial::new());
+            current_name = Some(name);
+            continue;
+        }
+
+        let material = match current_name.as_ref().and_then(|n| obj_materials.get_mut(n)) {
+            Some(m) => m,
+            None => continue,
+        };
+
+        match vec[0] {
+            "Kd" => material.diffuse = get_color(&line)?,
+            "Ks" => material.specular = get_color(&line)?,
+            "Ke" => material.emission = get_color(&line)?,
+            "Ns" => material.specular_exponent = line.get_f32(1)?,
+            "Ni" => material.refraction_index = line.get_f32(1)?,
+            "d" => material.dissolve = line.get_f32(1)?,
+            "Tr" => material.dissolve = 1.0 - line.get_f32(1)?,
+            "illum" => material.illumination_model = line.get_u32(1)?,
+            "Pr" => material.roughness = Some(line.get_f32(1)?),
+            "Pm" => material.metallic = Some(line.get_f32(1)?),
+            "Ps" => material.sheen = Some(line.get_f32(1)?),
+            "Pc" => material.clearcoat = Some(line.get_f32(1)?),
+            "Pcr" => material.clearcoat_roughness = Some(line.get_f32(1)?),
+            "map_Kd" => {
+                // options like -s or -o come first, the file name is always last
+                line.get_token(1, "a texture file name")?;
+                material.diffuse_map = Some(directory.join(vec[vec.len() - 1]));
+            }
+            _ => {
+                // unsupported statement
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// All faces of one group that share a material end up in a single TriangleMesh.
+struct ObjMesh {
+    material_name: Option<String>,
+    faces: Vec<MeshFace>,
+}
+
+// see: http://paulbourke.net/dataformats/obj/
+fn parse_obj_file(
+    file_path: &str,
+    render_config: &RenderConfig,
+) -> Result<ObjParser, SceneLoadError> {
+    let directory = Path::new(file_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+
+    let mut positions: Vec<Vector3<f32>> = Vec::new();
+    let mut normals: Vec<Vector3<f32>> = Vec::new();
+    let mut uvs: Vec<Point2<f32>> = Vec::new();
+
+    let mut obj_materials: HashMap<String, ObjMaterial> = HashMap::new();
+    let mut meshes: Vec<ObjMesh> = Vec::new();
+    let mut mesh_lookup: HashMap<(String, Option<String>), usize> = HashMap::new();
+    let mut current_group = String::from("default");
+    let mut current_material_name: Option<String> = None;
+
+    let contents = fs::read_to_string(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+    for (num, text) in contents.lines().enumerate() {
+        let line = SceneLine::new(file_path, num + 1, text);
+        if line.is_empty() {
+            continue;
+        }
+        let vec = line.tokens();
+
+        match vec[0] {
+            "v" => {
+                positions.push(line.get_vector(1)?);
+            }
+            "vn" => {
+                normals.push(line.get_vector(1)?);
+            }
+            "vt" => {
+                let v = if vec.len() > 2 { line.get_f32(2)? } else { 0.0 };
+                uvs.push(Point2::new(line.get_f32(1)?, v));
+            }
+            "g" | "o" => {
+                current_group = if vec.len() > 1 {
+                    vec[1..].join(" ")
+                } else {
+                    String::from("default")
+                };
+            }
+            "usemtl" => {
+                current_material_name = Some(vec[1..].join(" "));
+            }
+            "mtllib" => {
+                for mtl_file in vec[1..].iter() {
+                    parse_mtl_file(&directory.join(mtl_file), &mut obj_materials)?;
+                }
+            }
+            "f" => {
+                let mut vertices: Vec<u32> = Vec::new();
+                let mut face_uvs: Vec<u32> = Vec::new();
+                let mut face_normals: Vec<u32> = Vec::new();
+                if vec.len() < 4 {
+                    return Err(line.error(vec.len(), "a face needs at least 3 vertices"));
+                }
+                for (i, corner) in vec.iter().enumerate().skip(1) {
+                    // v, v/vt, v//vn or v/vt/vn
+                    let parts: Vec<&str> = corner.split('/').collect();
+                    vertices.push(as_index(&line, i, parts[0], positions.len())?);
+                    if parts.len() > 1 && !parts[1].is_empty() {
+                        face_uvs.push(as_index(&line, i, parts[1], uvs.len())?);
+                    }
+                    if parts.len() > 2 && !parts[2].is_empty() {
+                        face_normals.push(as_index(&line, i, parts[2], normals.len())?);
+                    }
+                }
+
+                let key = (current_group.clone(), current_material_name.clone());
+                let mesh_index = *mesh_lookup.entry(key).or_insert_with(|| {
+                    meshes.push(ObjMesh {
+                        material_name: current_material_name.clone(),
+                        faces: Vec::new(),
+                    });
+                    meshes.len() - 1
+                });
+
+                let has_uvs = face_uvs.len() == vertices.len();
+                let has_normals = face_normals.len() == vertices.len();
+
+                // fan triangulation, the same way polygons are handled for NFF files
+                for i in 2..vertices.len() {
+                    // OBJ faces are counter clockwise while TriangleMesh (like Triangle)
+                    // derives a flat normal from clockwise winding, so flip the order of
+                    // faces that rely on it.
+                    let corners = if has_normals {
+                        [0, i - 1, i]
+                    } else {
+                        [0, i, i - 1]
+                    };
+                    let pick = |indices: &Vec<u32>| {
+                        [
+                            indices[corners[0]],
+                            indices[corners[1]],
+                            indices[corners[2]],
+                        ]
+                    };
+
+                    meshes[mesh_index].faces.push(MeshFace::new(
+                        pick(&vertices),
+                        if has_normals {
+                            Some(pick(&face_normals))
+                        } else {
+                            None
+                        },
+                        if has_uvs { Some(pick(&face_uvs)) } else { None },
+                    ));
+                }
+            }
+            _ => {
+                // comments, smoothing groups and unsupported statements
+            }
+        }
+    }
+
+    let mut min = vec3(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = vec3(-f32::MAX, -f32::MAX, -f32::MAX);
+    for p in positions.iter() {
+        min = vec3(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = vec3(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+
+    let positions = Arc::new(positions);
+    let normals = Arc::new(normals);
+    let uvs = Arc::new(uvs);
+
+    let mut materials: CompiledMaterials = CompiledMaterials::new();
+    let mut textures: HashMap<PathBuf, ThreadTexture> = HashMap::new();
+    let mut material_ids: HashMap<Option<String>, u64> = HashMap::new();
+    let mut hitables = vec![];
+    let mut lights = vec![];
+
+    for mesh in meshes {
+        if mesh.faces.is_empty() {
+            continue;
+        }
+
+        let default_material = ObjMaterial::new();
+        let obj_material = match &mesh.material_name {
+            Some(name) => obj_materials.get(name).unwrap_or(&default_material),
+            None => &default_material,
+        };
+
+        let material_id = match material_ids.get(&mesh.material_name) {
+            Some(id) => *id,
+            None => {
+                let id = obj_material.compile(&mut materials, &mut textures)?;
+                material_ids.insert(mesh.material_name.clone(), id);
+                id
+            }
+        };
+
+        let hitable = TriangleMesh::new(
+            positions.clone(),
+            normals.clone(),
+            uvs.clone(),
+            mesh.faces,
+            material_id,
+        );
+        if obj_material.is_emissive() {
+            lights.push(hitable.clone());
+        }
+        hitables.push(hitable);
+    }
+
+    let look_at = (min + max) * 0.5;
+    let radius = ((max - min) * 0.5).magnitude().max(0.001);
+    let camera_distance = 1.1 * radius / (OBJ_VERTICAL_FOV * std::f32::consts::PI / 360.0).sin();
+
+    Ok(ObjParser {
+        // without emissive materials the background is the only light in the scene
+        background: Color::new(0.7, 0.8, 1.0),
+        render_config: render_config.clone(),
+        look_at,
+        camera_distance,
+        hitables,
+        materials: Arc::new(Box::new(materials)),
+        light_hitable: HitableList::from_vec(lights),
+    })
+}
@@ -14,6 +14,14 @@ impl PixelBuffer {
             is_y_up: true,
         }
     }
+
+    pub fn from_image_file(file_path: &str) -> image::ImageResult<PixelBuffer> {
+        Ok(PixelBuffer {
+            imgbuf: image::open(file_path)?.to_rgba8(),
+            is_y_up: true,
+        })
+    }
+
     fn clamp_to_pixel(&self, color: Color) -> image::Rgba<u8> {
         let double_clamped = color.clamp();
 
@@ -0,0 +1,429 @@
+use crate::render::{Color, LoadedMesh, SceneLine, SceneLoadError};
+use crate::{vec3, Vector3};
+use std::fs;
+
+type Result<T> = std::result::Result<T, SceneLoadError>;
+
+// Loads a Stanford PLY file (ascii, binary_little_endian or binary_big_endian).
+// The vertex element supplies x/y/z and optionally nx/ny/nz and red/green/blue,
+// the face element supplies a vertex_indices list.  Any other elements and
+// properties are read and ignored.  Polygons are fan triangulated.
+// see: http://paulbourke.net/dataformats/ply/
+pub fn load_ply_file(file_path: &str) -> Result<LoadedMesh> {
+    let data = fs::read(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+    let (header, body_start, header_lines) = parse_header(file_path, &data)?;
+    let mut reader = PlyReader::new(file_path, header.format, &data[body_start..], header_lines);
+
+    let mut mesh = LoadedMesh::default();
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut reader, element, &mut mesh)?,
+            "face" => read_faces(&mut reader, element, &mut mesh)?,
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        read_property(&mut reader, property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    let vertex_count = mesh.positions.len() as u32;
+    if mesh.faces.iter().flatten().any(|i| *i >= vertex_count) {
+        return Err(SceneLoadError::invalid_data(
+            file_path,
+            "face references a vertex that does not exist",
+        ));
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone)]
+enum PlyScalar {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl PlyScalar {
+    fn parse(line: &SceneLine<'_>, index: usize) -> Result<PlyScalar> {
+        match line.get_token(index, "a property type")? {
+            "char" | "int8" => Ok(PlyScalar::Char),
+            "uchar" | "uint8" => Ok(PlyScalar::UChar),
+            "short" | "int16" => Ok(PlyScalar::Short),
+            "ushort" | "uint16" => Ok(PlyScalar::UShort),
+            "int" | "int32" => Ok(PlyScalar::Int),
+            "uint" | "uint32" => Ok(PlyScalar::UInt),
+            "float" | "float32" => Ok(PlyScalar::Float),
+            "double" | "float64" => Ok(PlyScalar::Double),
+            _ => Err(line.error(index, "unknown property type")),
+        }
+    }
+
+    fn get_size(self) -> usize {
+        match self {
+            PlyScalar::Char | PlyScalar::UChar => 1,
+            PlyScalar::Short | PlyScalar::UShort => 2,
+            PlyScalar::Int | PlyScalar::UInt | PlyScalar::Float => 4,
+            PlyScalar::Double => 8,
+        }
+    }
+
+    // value that maps to full intensity when the scalar holds a color channel
+    fn get_color_scale(self) -> f64 {
+        match self {
+            PlyScalar::UChar => 255.0,
+            PlyScalar::UShort => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+enum PlyPropertyKind {
+    Scalar(PlyScalar),
+    List(PlyScalar, PlyScalar),
+}
+
+struct PlyProperty {
+    name: String,
+    kind: PlyPropertyKind,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    fn find_property(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+struct PlyHeader {
+    format: PlyFormat,
+    elements: Vec<PlyElement>,
+}
+
+// Returns the header, the offset of the first body byte and the number of header lines.
+fn parse_header(file_path: &str, data: &[u8]) -> Result<(PlyHeader, usize, usize)> {
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    let mut pos = 0;
+    let mut line_number = 0;
+
+    loop {
+        let line_end = match data[pos..].iter().position(|b| *b == b'\n') {
+            Some(offset) => pos + offset,
+            None => {
+                return Err(SceneLoadError::truncated(
+                    file_path,
+                    line_number,
+                    "header has no end_header line",
+                ))
+            }
+        };
+        let text = String::from_utf8_lossy(&data[pos..line_end]);
+        pos = line_end + 1;
+        line_number += 1;
+
+        let line = SceneLine::new(file_path, line_number, &text);
+        if line_number == 1 {
+            if line.tokens().first() != Some(&"ply") {
+                return Err(line.error(0, "file does not start with 'ply'"));
+            }
+            continue;
+        }
+
+        match line.tokens().first() {
+            None | Some(&"comment") | Some(&"obj_info") => {}
+            Some(&"format") => {
+                format = Some(match line.get_token(1, "a format")? {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(line.error(1, "unknown format")),
+                });
+            }
+            Some(&"element") => {
+                elements.push(PlyElement {
+                    name: line.get_token(1, "an element name")?.to_string(),
+                    count: line.parse::<usize>(2, "an element count")?,
+                    properties: vec![],
+                });
+            }
+            Some(&"property") => {
+                let element = match elements.last_mut() {
+                    Some(e) => e,
+                    None => return Err(line.error(0, "property before any element")),
+                };
+                let property = if line.get_token(1, "a property type")? == "list" {
+                    PlyProperty {
+                        name: line.get_token(4, "a property name")?.to_string(),
+                        kind: PlyPropertyKind::List(
+                            PlyScalar::parse(&line, 2)?,
+                            PlyScalar::parse(&line, 3)?,
+                        ),
+                    }
+                } else {
+                    PlyProperty {
+                        name: line.get_token(2, "a property name")?.to_string(),
+                        kind: PlyPropertyKind::Scalar(PlyScalar::parse(&line, 1)?),
+                    }
+                };
+                element.properties.push(property);
+            }
+            Some(&"end_header") => break,
+            Some(_) => return Err(line.error(0, "unknown header keyword")),
+        }
+    }
+
+    match format {
+        Some(format) => Ok((PlyHeader { format, elements }, pos, line_number)),
+        None => Err(SceneLoadError::truncated(
+            file_path,
+            line_number,
+            "header has no format line",
+        )),
+    }
+}
+
+struct PlyReader<'a> {
+    file_path: &'a str,
+    format: PlyFormat,
+    data: &'a [u8],
+    pos: usize,
+    // only tracked for ascii bodies, so errors can point at a line
+    line_number: usize,
+    line_start: usize,
+}
+
+impl<'a> PlyReader<'a> {
+    fn new(
+        file_path: &'a str,
+        format: PlyFormat,
+        data: &'a [u8],
+        header_lines: usize,
+    ) -> PlyReader<'a> {
+        PlyReader {
+            file_path,
+            format,
+            data,
+            pos: 0,
+            line_number: header_lines + 1,
+            line_start: 0,
+        }
+    }
+
+    fn read_value(&mut self, scalar: PlyScalar) -> Result<f64> {
+        if self.format == PlyFormat::Ascii {
+            return self.read_ascii_value();
+        }
+
+        let size = scalar.get_size();
+        if self.pos + size > self.data.len() {
+            return Err(SceneLoadError::invalid_data(
+                self.file_path,
+                "file ends before all elements were read",
+            ));
+        }
+        let mut bytes = [0_u8; 8];
+        bytes[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+        self.pos += size;
+        if self.format == PlyFormat::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+
+        let value = match scalar {
+            PlyScalar::Char => f64::from(bytes[0] as i8),
+            PlyScalar::UChar => f64::from(bytes[0]),
+            PlyScalar::Short => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            PlyScalar::UShort => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            PlyScalar::Int => {
+                f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::UInt => {
+                f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::Float => {
+                f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            }
+            PlyScalar::Double => f64::from_le_bytes(bytes),
+        };
+        Ok(value)
+    }
+
+    fn read_ascii_value(&mut self) -> Result<f64> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            if self.data[self.pos] == b'\n' {
+                self.line_number += 1;
+                self.line_start = self.pos + 1;
+            }
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(SceneLoadError::truncated(
+                self.file_path,
+                self.line_number,
+                "file ends before all elements were read",
+            ));
+        }
+
+        let token = String::from_utf8_lossy(&self.data[start..self.pos]);
+        token.parse::<f64>().map_err(|_| SceneLoadError::Syntax {
+            path: self.file_path.to_string(),
+            line: self.line_number,
+            column: start - self.line_start + 1,
+            token: token.to_string(),
+            description: "expected a number".to_string(),
+        })
+    }
+}
+
+// Reads a property and returns its values (a single value for scalars).
+fn read_property(reader: &mut PlyReader<'_>, property: &PlyProperty) -> Result<Vec<f64>> {
+    match property.kind {
+        PlyPropertyKind::Scalar(scalar) => Ok(vec![reader.read_value(scalar)?]),
+        PlyPropertyKind::List(count_type, item_type) => {
+            let count = reader.read_value(count_type)?;
+            if count < 0.0 {
+                return Err(SceneLoadError::invalid_data(
+                    reader.file_path,
+                    "negative list length",
+                ));
+            }
+            (0..count as usize)
+                .map(|_| reader.read_value(item_type))
+                .collect()
+        }
+    }
+}
+
+fn read_vertices(
+    reader: &mut PlyReader<'_>,
+    element: &PlyElement,
+    mesh: &mut LoadedMesh,
+) -> Result<()> {
+    let find = |names: &[&str]| names.iter().find_map(|n| element.find_property(n));
+    let position = match (find(&["x"]), find(&["y"]), find(&["z"])) {
+        (Some(x), Some(y), Some(z)) => [x, y, z],
+        _ => {
+            return Err(SceneLoadError::invalid_data(
+                reader.file_path,
+                "vertex element needs x, y and z",
+            ))
+        }
+    };
+    let normal = match (find(&["nx"]), find(&["ny"]), find(&["nz"])) {
+        (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+        _ => None,
+    };
+    let color = match (
+        find(&["red", "diffuse_red", "r"]),
+        find(&["green", "diffuse_green", "g"]),
+        find(&["blue", "diffuse_blue", "b"]),
+    ) {
+        (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+        _ => None,
+    };
+    let color_scales: Vec<f64> = element
+        .properties
+        .iter()
+        .map(|p| match p.kind {
+            PlyPropertyKind::Scalar(scalar) => scalar.get_color_scale(),
+            PlyPropertyKind::List(_, _) => 1.0,
+        })
+        .collect();
+
+    let mut values = vec![0.0; element.properties.len()];
+    for _ in 0..element.count {
+        for (i, property) in element.properties.iter().enumerate() {
+            values[i] = read_property(reader, property)?
+                .first()
+                .cloned()
+                .unwrap_or(0.0);
+        }
+
+        let get_vector = |indices: [usize; 3]| -> Vector3<f32> {
+            vec3(
+                values[indices[0]] as f32,
+                values[indices[1]] as f32,
+                values[indices[2]] as f32,
+            )
+        };
+        mesh.positions.push(get_vector(position));
+        if let Some(n) = normal {
+            mesh.normals.push(get_vector(n));
+        }
+        if let Some(c) = color {
+            mesh.colors.push(Color::new(
+                (values[c[0]] / color_scales[c[0]]) as f32,
+                (values[c[1]] / color_scales[c[1]]) as f32,
+                (values[c[2]] / color_scales[c[2]]) as f32,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(
+    reader: &mut PlyReader<'_>,
+    element: &PlyElement,
+    mesh: &mut LoadedMesh,
+) -> Result<()> {
+    let indices_property = match element
+        .find_property("vertex_indices")
+        .or_else(|| element.find_property("vertex_index"))
+    {
+        Some(i) => i,
+        None => {
+            return Err(SceneLoadError::invalid_data(
+                reader.file_path,
+                "face element needs vertex_indices",
+            ))
+        }
+    };
+
+    for _ in 0..element.count {
+        for (i, property) in element.properties.iter().enumerate() {
+            let values = read_property(reader, property)?;
+            if i != indices_property {
+                continue;
+            }
+            if values.iter().any(|v| *v < 0.0) {
+                return Err(SceneLoadError::invalid_data(
+                    reader.file_path,
+                    "face has a negative vertex index",
+                ));
+            }
+
+            // PLY polygons are counter clockwise, flip them to match Triangle
+            for j in 1..values.len().saturating_sub(1) {
+                mesh.faces
+                    .push([values[0] as u32, values[j + 1] as u32, values[j] as u32]);
+            }
+        }
+    }
+
+    Ok(())
+}
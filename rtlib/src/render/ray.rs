@@ -6,12 +6,33 @@ use crate::stats::RenderStats;
 pub struct Ray {
     origin: Vector3<f32>,
     direction: Vector3<f32>,
+    inverse_direction: Vector3<f32>,
+    time: f32,
 }
 
 impl Ray {
     pub fn new(origin: Vector3<f32>, direction: Vector3<f32>, stat: &mut RenderStats) -> Ray {
+        Ray::new_at_time(origin, direction, 0.0, stat)
+    }
+
+    /// A ray that sees moving geometry where it is at `time`.  Rays spawned from a hit
+    /// should keep the time of the ray that made the hit.
+    pub fn new_at_time(
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        time: f32,
+        stat: &mut RenderStats,
+    ) -> Ray {
         stat.ray_create();
-        Ray { origin, direction }
+        // a zero component becomes +/-inf, which the AABB slab test relies on
+        let inverse_direction =
+            Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        Ray {
+            origin,
+            direction,
+            inverse_direction,
+            time,
+        }
     }
 
     pub fn get_point_at_parameter(self, t: f32) -> Vector3<f32> {
@@ -25,4 +46,12 @@ impl Ray {
     pub fn get_origin(self) -> Vector3<f32> {
         self.origin
     }
+
+    pub fn get_inverse_direction(self) -> Vector3<f32> {
+        self.inverse_direction
+    }
+
+    pub fn get_time(self) -> f32 {
+        self.time
+    }
 }
@@ -53,11 +53,23 @@ impl RayTracer for SamplingRayTracer {
                                     Vector3::new(0.0, 0.0, 0.0),
                                 ));
                                 let p = MixturePdf::new(plight, scatter_result.get_pdf());
-                                let scattered = Ray::new(hit_record.get_p(), p.generate(), stat);
+                                let scattered = Ray::new_at_time(
+                                    hit_record.get_p(),
+                                    p.generate(),
+                                    ray.get_time(),
+                                    stat,
+                                );
 
                                 let pdf_value = p.get_value(scattered.get_direction(), stat);
-                                let scattering_pdf =
-                                    material.scattering_pdf(ray, &hit_record, &scattered);
+                                if pdf_value <= 0.0 {
+                                    return emitted;
+                                }
+                                let scattering_color = material.scattering_color(
+                                    ray,
+                                    &hit_record,
+                                    &scattered,
+                                    &scatter_result,
+                                );
 
                                 let depth_ray_color = self.get_ray_color(
                                     stat,
@@ -66,11 +78,9 @@ impl RayTracer for SamplingRayTracer {
                                     render_config,
                                     depth + 1,
                                 );
-                                let recurse_color = (scatter_result
-                                    .get_attenuation()
-                                    .multiply_by_scalar(scattering_pdf)
-                                    .multiply(depth_ray_color))
-                                .multiply_by_scalar(1.0 / pdf_value);
+                                let recurse_color = scattering_color
+                                    .multiply(depth_ray_color)
+                                    .multiply_by_scalar(1.0 / pdf_value);
                                 return emitted.add(recurse_color);
                             }
                         }
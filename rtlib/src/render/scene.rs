@@ -1,4 +1,4 @@
-use crate::hitables::Hitable;
+use crate::hitables::{BvhBuildStats, Hitable};
 use crate::materials::{CompiledMaterials, Material};
 use crate::render::Color;
 use std::sync::Arc;
@@ -8,6 +8,7 @@ pub struct Scene {
     light_hitable: Arc<Box<dyn Hitable + Send>>,
     materials: Arc<Box<CompiledMaterials>>,
     background_color: Color,
+    bvh_build_stats: Option<BvhBuildStats>,
 }
 
 impl Scene {
@@ -22,9 +23,15 @@ impl Scene {
             light_hitable,
             materials,
             background_color,
+            bvh_build_stats: None,
         }
     }
 
+    pub fn with_bvh_build_stats(mut self, bvh_build_stats: Option<BvhBuildStats>) -> Scene {
+        self.bvh_build_stats = bvh_build_stats;
+        self
+    }
+
     pub fn get_world(&self) -> Arc<Box<dyn Hitable + Send>> {
         self.world.clone()
     }
@@ -44,4 +51,9 @@ impl Scene {
     pub fn get_background_color(&self) -> Color {
         self.background_color
     }
+
+    /// How the world's BVH came out, if it has one.
+    pub fn get_bvh_build_stats(&self) -> Option<BvhBuildStats> {
+        self.bvh_build_stats.clone()
+    }
 }
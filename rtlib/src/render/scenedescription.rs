@@ -0,0 +1,1681 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cameras::{NormalCamera, ThreadCamera};
+use crate::hitables::*;
+use crate::materials::*;
+use crate::phasefunctions::*;
+use crate::render::*;
+use crate::textures::*;
+use crate::{vec3, InnerSpace, Matrix4, Vector3};
+use cgmath::{Deg, Matrix, Quaternion, Rotation3, SquareMatrix};
+
+// Scene files describe vectors and colors as plain [x, y, z] arrays.
+fn to_vector(v: [f32; 3]) -> Vector3<f32> {
+    vec3(v[0], v[1], v[2])
+}
+
+fn to_color(c: [f32; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn is_default_scale(scale: &f32) -> bool {
+    *scale == 1.0
+}
+
+fn default_phi_max() -> f32 {
+    360.0
+}
+
+fn is_default_phi_max(phi_max: &f32) -> bool {
+    *phi_max == 360.0
+}
+
+fn default_white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn is_default_white(color: &[f32; 3]) -> bool {
+    *color == [1.0, 1.0, 1.0]
+}
+
+fn default_scale_3() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn is_default_scale_3(scale: &[f32; 3]) -> bool {
+    *scale == [1.0, 1.0, 1.0]
+}
+
+/// A scene as it is stored in a .json or .toml scene file.  Textures and materials
+/// are referred to by name, lights are sampled directly and are also part of the
+/// world so they don't need to be listed twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneDescription {
+    /// Overrides the render settings given on the command line when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_config: Option<RenderConfig>,
+    pub camera: CameraDescription,
+    pub background: [f32; 3],
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDescription>,
+    pub materials: BTreeMap<String, MaterialDescription>,
+    /// Named geometry that is built once and placed with `instance` hitables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub geometry: BTreeMap<String, Vec<HitableDescription>>,
+    #[serde(default)]
+    pub lights: Vec<HitableDescription>,
+    pub hitables: Vec<HitableDescription>,
+}
+
+/// Parameters of a `NormalCamera`, the aspect ratio comes from the render config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub look_from: [f32; 3],
+    pub look_at: [f32; 3],
+    #[serde(default = "CameraDescription::default_up")]
+    pub up: [f32; 3],
+    pub vertical_fov: f32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub aperture: f32,
+    pub focus_distance: f32,
+    /// Rays get a time between these two, moving hitables are blurred over it.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub shutter_open: f32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub shutter_close: f32,
+}
+
+impl CameraDescription {
+    fn default_up() -> [f32; 3] {
+        [0.0, 1.0, 0.0]
+    }
+
+    /// The camera orbited around `look_at`, `angle_x` degrees around the up vector and
+    /// `angle_y` degrees towards it.
+    pub fn to_camera(&self, aspect: f32, angle_x: f32, angle_y: f32) -> ThreadCamera {
+        let look_at = to_vector(self.look_at);
+        let up = to_vector(self.up).normalize();
+        let offset = to_vector(self.look_from) - look_at;
+
+        let radians_x = angle_x * std::f32::consts::PI / 180.0;
+        let radians_y = angle_y * std::f32::consts::PI / 180.0;
+
+        // Rodrigues' rotation of the offset around the up vector
+        let rotated = (offset * radians_x.cos())
+            + (up.cross(offset) * radians_x.sin())
+            + (up * up.dot(offset) * (1.0 - radians_x.cos()));
+
+        // then tilt it in the plane spanned by the offset and the up vector
+        let side = up.cross(rotated);
+        let look_from = if side.magnitude2() > 0.0 {
+            let axis = side.normalize();
+            look_at
+                + (rotated * radians_y.cos())
+                + (axis.cross(rotated) * radians_y.sin())
+                + (axis * axis.dot(rotated) * (1.0 - radians_y.cos()))
+        } else {
+            look_at + rotated
+        };
+
+        Arc::new(Box::new(
+            NormalCamera::new(
+                look_from,
+                look_at,
+                up,
+                self.vertical_fov,
+                aspect,
+                self.aperture,
+                self.focus_distance,
+            )
+            .with_shutter(self.shutter_open, self.shutter_close),
+        ))
+    }
+}
+
+/// A texture given inline or by the name of an entry in `textures`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureReference {
+    Named(String),
+    Inline(Box<TextureDescription>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureDescription {
+    Color {
+        color: [f32; 3],
+    },
+    Checker {
+        odd: TextureReference,
+        even: TextureReference,
+        scale: [f32; 3],
+    },
+    Noise {
+        interpolate: bool,
+        scale: f32,
+    },
+    VectorNoise {
+        mode: VectorNoiseMode,
+        scale: f32,
+    },
+    /// Path is relative to the scene file.
+    Image {
+        file: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureReference,
+    },
+    /// Rough diffuse, `sigma` is the roughness in degrees.
+    OrenNayar {
+        albedo: TextureReference,
+        sigma: f32,
+    },
+    Metal {
+        albedo: [f32; 3],
+        fuzz: f32,
+    },
+    /// Frosted when `roughness` is above 0.  Tinted by absorbing light on the inside,
+    /// leaving `absorption_color` of white light after `absorption_distance`.
+    Dielectric {
+        refraction_index: f32,
+        #[serde(default, skip_serializing_if = "is_default")]
+        roughness: f32,
+        #[serde(default = "default_white", skip_serializing_if = "is_default_white")]
+        absorption_color: [f32; 3],
+        #[serde(default = "default_scale", skip_serializing_if = "is_default_scale")]
+        absorption_distance: f32,
+    },
+    /// A rough metal, either a `preset` or a complex index of refraction `eta` + i`k`.
+    /// Brushed metals give a `brush_direction` and a `roughness_across` it, `roughness`
+    /// is then the roughness along it.
+    Conductor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preset: Option<ConductorPreset>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        eta: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        k: Option<[f32; 3]>,
+        roughness: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness_across: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        brush_direction: Option<[f32; 3]>,
+    },
+    DiffuseLight {
+        emit: TextureReference,
+    },
+    Isotropic {
+        albedo: TextureReference,
+    },
+    /// Scattering inside a medium, `isotropic` with a choice of phase function.
+    Medium {
+        albedo: TextureReference,
+        phase_function: PhaseFunctionDescription,
+    },
+    /// One material for most surfaces, see `PrincipledMaterial`.  Parameters that are
+    /// left out get the defaults of `PrincipledParameters`.
+    Principled {
+        base_color: TextureReference,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metallic: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        specular: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        specular_tint: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sheen: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat_gloss: Option<ScalarReference>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transmission: Option<ScalarReference>,
+        #[serde(
+            default = "default_refraction_index",
+            skip_serializing_if = "is_default_refraction_index"
+        )]
+        refraction_index: f32,
+    },
+    /// The `base` material, given inline, under a clear coat that leaves
+    /// `absorption_color` of white light going straight through it and back.
+    Layered {
+        base: Box<MaterialDescription>,
+        #[serde(
+            default = "default_refraction_index",
+            skip_serializing_if = "is_default_refraction_index"
+        )]
+        refraction_index: f32,
+        #[serde(default, skip_serializing_if = "is_default")]
+        roughness: f32,
+        #[serde(default = "default_white", skip_serializing_if = "is_default_white")]
+        absorption_color: [f32; 3],
+    },
+}
+
+fn default_refraction_index() -> f32 {
+    1.5
+}
+
+fn is_default_refraction_index(refraction_index: &f32) -> bool {
+    *refraction_index == 1.5
+}
+
+/// A material parameter given as a number or as a texture, whose channels are averaged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScalarReference {
+    Value(f32),
+    Texture(TextureReference),
+}
+
+/// How a medium spreads scattered light, see `PhaseFunction`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PhaseFunctionDescription {
+    #[default]
+    Isotropic,
+    HenyeyGreenstein {
+        g: f32,
+    },
+    DoubleHenyeyGreenstein {
+        g_forward: f32,
+        g_back: f32,
+        weight: f32,
+    },
+}
+
+impl PhaseFunctionDescription {
+    fn to_phase_function(&self) -> Result<ThreadPhaseFunction, String> {
+        let check_g = |g: f32| {
+            if g <= -1.0 || g >= 1.0 {
+                Err(format!(
+                    "phase function asymmetry {} is not between -1 and 1",
+                    g
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        Ok(match self {
+            PhaseFunctionDescription::Isotropic => IsotropicPhaseFunction::new(),
+            PhaseFunctionDescription::HenyeyGreenstein { g } => {
+                check_g(*g)?;
+                HenyeyGreensteinPhaseFunction::new(*g)
+            }
+            PhaseFunctionDescription::DoubleHenyeyGreenstein {
+                g_forward,
+                g_back,
+                weight,
+            } => {
+                check_g(*g_forward)?;
+                check_g(*g_back)?;
+                if !(0.0..=1.0).contains(weight) {
+                    return Err(format!(
+                        "double henyey-greenstein weight {} is not between 0 and 1",
+                        weight
+                    ));
+                }
+                DoubleHenyeyGreensteinPhaseFunction::new(*g_forward, *g_back, *weight)
+            }
+        })
+    }
+}
+
+/// Scale, then rotation about x, y and z (in degrees, in that order), then translation.
+/// A matrix, when given, is applied last and can express anything else, like shear.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransformDescription {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub translation: [f32; 3],
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rotation: [f32; 3],
+    #[serde(
+        default = "default_scale_3",
+        skip_serializing_if = "is_default_scale_3"
+    )]
+    pub scale: [f32; 3],
+    /// Row major, so it reads the way it is written in the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<[[f32; 4]; 4]>,
+}
+
+impl Default for TransformDescription {
+    fn default() -> Self {
+        TransformDescription {
+            translation: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+            scale: default_scale_3(),
+            matrix: None,
+        }
+    }
+}
+
+impl TransformDescription {
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        let matrix = match self.matrix {
+            // cgmath takes columns
+            Some(m) => Matrix4::from(m).transpose(),
+            None => Matrix4::identity(),
+        };
+        matrix
+            * Matrix4::from_translation(to_vector(self.translation))
+            * Matrix4::from_angle_z(Deg(self.rotation[2]))
+            * Matrix4::from_angle_y(Deg(self.rotation[1]))
+            * Matrix4::from_angle_x(Deg(self.rotation[0]))
+            * Matrix4::from_nonuniform_scale(self.scale[0], self.scale[1], self.scale[2])
+    }
+
+    fn to_rotation(&self) -> Quaternion<f32> {
+        Quaternion::from_angle_z(Deg(self.rotation[2]))
+            * Quaternion::from_angle_y(Deg(self.rotation[1]))
+            * Quaternion::from_angle_x(Deg(self.rotation[0]))
+    }
+}
+
+/// The placement of a keyframed hitable at `time`.  Only translation, rotation and
+/// scale can be interpolated, so `matrix` isn't allowed here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyframeDescription {
+    pub time: f32,
+    #[serde(flatten)]
+    pub transform: TransformDescription,
+}
+
+fn default_time_1() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HitableDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    /// Moves from `center0` at `time0` to `center1` at `time1`.
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        #[serde(default)]
+        time0: f32,
+        #[serde(default = "default_time_1")]
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: String,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    Cube {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    /// Capped, around the segment from `base` to `top`.
+    Cylinder {
+        base: [f32; 3],
+        top: [f32; 3],
+        radius: f32,
+        material: String,
+        cap_material: String,
+    },
+    Cone {
+        base: [f32; 3],
+        base_radius: f32,
+        apex: [f32; 3],
+        apex_radius: f32,
+        #[serde(default)]
+        capped: bool,
+        #[serde(
+            default = "default_phi_max",
+            skip_serializing_if = "is_default_phi_max"
+        )]
+        phi_max: f32,
+        material: String,
+    },
+    /// The shapes below are centered on the z axis and are placed with `transform`.
+    /// `phi_max` is how far around the axis they go, in degrees.
+    Disk {
+        #[serde(default, skip_serializing_if = "is_default")]
+        height: f32,
+        radius: f32,
+        #[serde(default, skip_serializing_if = "is_default")]
+        inner_radius: f32,
+        #[serde(
+            default = "default_phi_max",
+            skip_serializing_if = "is_default_phi_max"
+        )]
+        phi_max: f32,
+        material: String,
+    },
+    Paraboloid {
+        radius: f32,
+        #[serde(default, skip_serializing_if = "is_default")]
+        z_min: f32,
+        z_max: f32,
+        #[serde(
+            default = "default_phi_max",
+            skip_serializing_if = "is_default_phi_max"
+        )]
+        phi_max: f32,
+        material: String,
+    },
+    /// The segment from `p1` to `p2` swept around the z axis.
+    Hyperboloid {
+        p1: [f32; 3],
+        p2: [f32; 3],
+        #[serde(
+            default = "default_phi_max",
+            skip_serializing_if = "is_default_phi_max"
+        )]
+        phi_max: f32,
+        material: String,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+        #[serde(
+            default = "default_phi_max",
+            skip_serializing_if = "is_default_phi_max"
+        )]
+        phi_max: f32,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: String,
+    },
+    /// A quad in any orientation, the corner `origin` plus any mix of the two edges.
+    Parallelogram {
+        origin: [f32; 3],
+        edge_u: [f32; 3],
+        edge_v: [f32; 3],
+        material: String,
+    },
+    /// A PLY or STL file relative to the scene file.  Without a material the
+    /// vertex colors of the mesh are used.
+    Mesh {
+        file: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+        #[serde(default, skip_serializing_if = "is_default")]
+        translation: [f32; 3],
+        #[serde(default, skip_serializing_if = "is_default")]
+        angle_y: f32,
+        #[serde(default = "default_scale", skip_serializing_if = "is_default_scale")]
+        scale: f32,
+    },
+    Translate {
+        offset: [f32; 3],
+        hitable: Box<HitableDescription>,
+    },
+    RotateX {
+        angle: f32,
+        hitable: Box<HitableDescription>,
+    },
+    RotateY {
+        angle: f32,
+        hitable: Box<HitableDescription>,
+    },
+    RotateZ {
+        angle: f32,
+        hitable: Box<HitableDescription>,
+    },
+    Scale {
+        factor: f32,
+        hitable: Box<HitableDescription>,
+    },
+    Transform {
+        #[serde(flatten)]
+        transform: TransformDescription,
+        hitable: Box<HitableDescription>,
+    },
+    Keyframed {
+        keyframes: Vec<KeyframeDescription>,
+        hitable: Box<HitableDescription>,
+    },
+    FlipNormals {
+        hitable: Box<HitableDescription>,
+    },
+    ConstantMedium {
+        density: f32,
+        albedo: TextureReference,
+        #[serde(default, skip_serializing_if = "is_default")]
+        phase_function: PhaseFunctionDescription,
+        boundary: Box<HitableDescription>,
+    },
+    /// A cloud or smoke filling the box from `min` to `max`.  `absorption` and
+    /// `scattering` are per unit of density.
+    GridMedium {
+        grid: DensityGridDescription,
+        min: [f32; 3],
+        max: [f32; 3],
+        absorption: f32,
+        scattering: f32,
+        #[serde(default = "default_medium_albedo")]
+        albedo: [f32; 3],
+        #[serde(default, skip_serializing_if = "is_default")]
+        phase_function: PhaseFunctionDescription,
+    },
+    List {
+        hitables: Vec<HitableDescription>,
+    },
+    /// Terrain over the box from `min` to `max`, height 0 at `min.y` and 1 at `max.y`.
+    Heightfield {
+        heights: HeightMapDescription,
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    /// A distance function traced inside the box from `min` to `max`, which must
+    /// contain the whole surface.
+    Sdf {
+        node: SdfNodeDescription,
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    /// Both sides must be closed solids.
+    Csg {
+        operation: CsgOperation,
+        left: Box<HitableDescription>,
+        right: Box<HitableDescription>,
+    },
+    /// Places named geometry, optionally with a material replacing its own.
+    Instance {
+        geometry: String,
+        #[serde(flatten)]
+        transform: TransformDescription,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+}
+
+/// Where a `grid_medium` gets its densities from, see `DensityGrid`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DensityGridDescription {
+    /// A .json grid, or raw f32 values which need the resolution, see
+    /// `load_density_grid_file`.  Relative to the scene file.
+    File {
+        file: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resolution: Option<[usize; 3]>,
+    },
+    Noise {
+        resolution: [usize; 3],
+        frequency: f32,
+    },
+}
+
+fn default_medium_albedo() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// Where a `heightfield` gets its heights from, see `HeightMap`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HeightMapDescription {
+    /// The brightness of an image, relative to the scene file.
+    Image { file: String },
+    Perlin {
+        width: usize,
+        depth: usize,
+        frequency: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+    },
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+/// A node of an `sdf` hitable's distance function, see `SdfNode`.  Unions and
+/// intersections take any number of nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SdfNodeDescription {
+    Sphere {
+        radius: f32,
+    },
+    RoundBox {
+        half_size: [f32; 3],
+        #[serde(default, skip_serializing_if = "is_default")]
+        radius: f32,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Capsule {
+        a: [f32; 3],
+        b: [f32; 3],
+        radius: f32,
+    },
+    Mandelbulb {
+        #[serde(default = "default_mandelbulb_power")]
+        power: f32,
+        #[serde(default = "default_mandelbulb_iterations")]
+        iterations: u32,
+    },
+    Union {
+        nodes: Vec<SdfNodeDescription>,
+    },
+    Intersection {
+        nodes: Vec<SdfNodeDescription>,
+    },
+    Difference {
+        left: Box<SdfNodeDescription>,
+        right: Box<SdfNodeDescription>,
+    },
+    SmoothUnion {
+        nodes: Vec<SdfNodeDescription>,
+        k: f32,
+    },
+    SmoothIntersection {
+        nodes: Vec<SdfNodeDescription>,
+        k: f32,
+    },
+    SmoothDifference {
+        left: Box<SdfNodeDescription>,
+        right: Box<SdfNodeDescription>,
+        k: f32,
+    },
+    Translate {
+        offset: [f32; 3],
+        node: Box<SdfNodeDescription>,
+    },
+    Scale {
+        factor: f32,
+        node: Box<SdfNodeDescription>,
+    },
+    Repeat {
+        period: [f32; 3],
+        node: Box<SdfNodeDescription>,
+    },
+    Twist {
+        rate: f32,
+        node: Box<SdfNodeDescription>,
+    },
+}
+
+fn default_mandelbulb_power() -> f32 {
+    8.0
+}
+
+fn default_mandelbulb_iterations() -> u32 {
+    8
+}
+
+impl SdfNodeDescription {
+    fn to_node(&self) -> Result<SdfNode, String> {
+        // folds the nodes of a union or intersection into a chain of pairs
+        fn fold(
+            nodes: &[SdfNodeDescription],
+            combine: impl Fn(Box<SdfNode>, Box<SdfNode>) -> SdfNode,
+        ) -> Result<SdfNode, String> {
+            let mut iter = nodes.iter();
+            let first = match iter.next() {
+                Some(node) => node.to_node()?,
+                None => return Err("sdf union or intersection has no nodes".to_string()),
+            };
+            iter.try_fold(first, |acc, node| {
+                Ok(combine(Box::new(acc), Box::new(node.to_node()?)))
+            })
+        }
+        let boxed = |node: &SdfNodeDescription| -> Result<Box<SdfNode>, String> {
+            Ok(Box::new(node.to_node()?))
+        };
+
+        Ok(match self {
+            SdfNodeDescription::Sphere { radius } => SdfNode::Sphere { radius: *radius },
+            SdfNodeDescription::RoundBox { half_size, radius } => SdfNode::RoundBox {
+                half_size: to_vector(*half_size),
+                radius: *radius,
+            },
+            SdfNodeDescription::Torus {
+                major_radius,
+                minor_radius,
+            } => SdfNode::Torus {
+                major_radius: *major_radius,
+                minor_radius: *minor_radius,
+            },
+            SdfNodeDescription::Capsule { a, b, radius } => SdfNode::Capsule {
+                a: to_vector(*a),
+                b: to_vector(*b),
+                radius: *radius,
+            },
+            SdfNodeDescription::Mandelbulb { power, iterations } => SdfNode::Mandelbulb {
+                power: *power,
+                iterations: *iterations,
+            },
+            SdfNodeDescription::Union { nodes } => {
+                fold(nodes, |left, right| SdfNode::Union { left, right })?
+            }
+            SdfNodeDescription::Intersection { nodes } => {
+                fold(nodes, |left, right| SdfNode::Intersection { left, right })?
+            }
+            SdfNodeDescription::Difference { left, right } => SdfNode::Difference {
+                left: boxed(left)?,
+                right: boxed(right)?,
+            },
+            SdfNodeDescription::SmoothUnion { nodes, k } => fold(nodes, |left, right| {
+                SdfNode::SmoothUnion { left, right, k: *k }
+            })?,
+            SdfNodeDescription::SmoothIntersection { nodes, k } => fold(nodes, |left, right| {
+                SdfNode::SmoothIntersection { left, right, k: *k }
+            })?,
+            SdfNodeDescription::SmoothDifference { left, right, k } => SdfNode::SmoothDifference {
+                left: boxed(left)?,
+                right: boxed(right)?,
+                k: *k,
+            },
+            SdfNodeDescription::Translate { offset, node } => SdfNode::Translate {
+                offset: to_vector(*offset),
+                node: boxed(node)?,
+            },
+            SdfNodeDescription::Scale { factor, node } => {
+                if *factor == 0.0 {
+                    return Err("sdf scale factor is zero".to_string());
+                }
+                SdfNode::Scale {
+                    factor: *factor,
+                    node: boxed(node)?,
+                }
+            }
+            SdfNodeDescription::Repeat { period, node } => SdfNode::Repeat {
+                period: to_vector(*period),
+                node: boxed(node)?,
+            },
+            SdfNodeDescription::Twist { rate, node } => SdfNode::Twist {
+                rate: *rate,
+                node: boxed(node)?,
+            },
+        })
+    }
+}
+
+/// Everything `create_scene` needs, built from a `SceneDescription`.
+pub struct CompiledSceneDescription {
+    pub hitables: Vec<ThreadHitable>,
+    pub materials: CompiledMaterials,
+    pub light_hitable: ThreadHitable,
+}
+
+struct SceneBuilder<'a> {
+    description: &'a SceneDescription,
+    // used for error messages and to find files the scene refers to
+    file_path: &'a str,
+    directory: &'a Path,
+    materials: CompiledMaterials,
+    material_ids: HashMap<String, u64>,
+    textures: HashMap<String, ThreadTexture>,
+    geometry: HashMap<String, ThreadHitable>,
+    // names of the geometry being built, to catch geometry that instances itself
+    geometry_in_progress: Vec<String>,
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn error(&self, description: String) -> SceneLoadError {
+        SceneLoadError::invalid_data(self.file_path, &description)
+    }
+
+    fn get_file_path(&self, file: &str) -> String {
+        self.directory.join(file).to_string_lossy().to_string()
+    }
+
+    fn build_scalar_reference(
+        &mut self,
+        reference: &Option<ScalarReference>,
+        default: ThreadTexture,
+    ) -> Result<ThreadTexture, SceneLoadError> {
+        match reference {
+            None => Ok(default),
+            Some(ScalarReference::Value(value)) => {
+                if !(0.0..=1.0).contains(value) {
+                    return Err(self.error(format!(
+                        "material parameter {} is not between 0 and 1",
+                        value
+                    )));
+                }
+                Ok(ColorTexture::new(*value, *value, *value))
+            }
+            Some(ScalarReference::Texture(texture)) => self.build_texture_reference(texture, 0),
+        }
+    }
+
+    fn build_texture_reference(
+        &mut self,
+        reference: &TextureReference,
+        depth: usize,
+    ) -> Result<ThreadTexture, SceneLoadError> {
+        match reference {
+            TextureReference::Inline(texture) => self.build_texture(texture, depth),
+            TextureReference::Named(name) => {
+                if let Some(texture) = self.textures.get(name) {
+                    return Ok(texture.clone());
+                }
+
+                let description = self.description;
+                let texture = match description.textures.get(name) {
+                    Some(t) => t,
+                    None => return Err(self.error(format!("unknown texture '{}'", name))),
+                };
+                // named textures can refer to each other, so guard against cycles
+                if depth > description.textures.len() {
+                    return Err(self.error(format!("texture '{}' refers to itself", name)));
+                }
+                let built = self.build_texture(texture, depth + 1)?;
+                self.textures.insert(name.clone(), built.clone());
+                Ok(built)
+            }
+        }
+    }
+
+    fn build_texture(
+        &mut self,
+        texture: &TextureDescription,
+        depth: usize,
+    ) -> Result<ThreadTexture, SceneLoadError> {
+        Ok(match texture {
+            TextureDescription::Color { color } => ColorTexture::new(color[0], color[1], color[2]),
+            TextureDescription::Checker { odd, even, scale } => CheckerTexture::new(
+                self.build_texture_reference(odd, depth)?,
+                self.build_texture_reference(even, depth)?,
+                to_vector(*scale),
+            ),
+            TextureDescription::Noise { interpolate, scale } => {
+                NoiseTexture::new(*interpolate, *scale)
+            }
+            TextureDescription::VectorNoise { mode, scale } => {
+                VectorNoiseTexture::new(*mode, *scale)
+            }
+            TextureDescription::Image { file } => {
+                let path = self.get_file_path(file);
+                let pixel_buffer =
+                    PixelBuffer::from_image_file(&path).map_err(|e| SceneLoadError::Io {
+                        path: path.clone(),
+                        description: e.to_string(),
+                    })?;
+                ImageTexture::new(Arc::new(Box::new(pixel_buffer)))
+            }
+        })
+    }
+
+    fn build_materials(&mut self) -> Result<(), SceneLoadError> {
+        let description = self.description;
+        for (name, material) in description.materials.iter() {
+            let compiled = self.build_material(name, material)?;
+            let id = self.materials.add(compiled);
+            self.material_ids.insert(name.clone(), id);
+        }
+        Ok(())
+    }
+
+    fn build_material(
+        &mut self,
+        name: &str,
+        material: &MaterialDescription,
+    ) -> Result<ThreadMaterial, SceneLoadError> {
+        Ok(match material {
+            MaterialDescription::Lambertian { albedo } => {
+                LambertianMaterial::new(self.build_texture_reference(albedo, 0)?)
+            }
+            MaterialDescription::OrenNayar { albedo, sigma } => {
+                if !(0.0..=90.0).contains(sigma) {
+                    return Err(self.error(format!(
+                        "oren-nayar sigma {} is not between 0 and 90 degrees",
+                        sigma
+                    )));
+                }
+                OrenNayarMaterial::new(self.build_texture_reference(albedo, 0)?, *sigma)
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                MetalMaterial::new(to_color(*albedo), *fuzz)
+            }
+            MaterialDescription::Dielectric {
+                refraction_index,
+                roughness,
+                absorption_color,
+                absorption_distance,
+            } => {
+                if !(0.0..=1.0).contains(roughness) {
+                    return Err(self.error(format!(
+                        "dielectric roughness {} is not between 0 and 1",
+                        roughness
+                    )));
+                }
+                if *absorption_distance <= 0.0 {
+                    return Err(self.error(format!(
+                        "dielectric absorption distance {} is not above 0",
+                        absorption_distance
+                    )));
+                }
+                DialectricMaterial::new_rough(
+                    *refraction_index,
+                    *roughness,
+                    to_color(*absorption_color),
+                    *absorption_distance,
+                )
+            }
+            MaterialDescription::Conductor {
+                preset,
+                eta,
+                k,
+                roughness,
+                roughness_across,
+                brush_direction,
+            } => {
+                for r in [Some(*roughness), *roughness_across].iter().flatten() {
+                    if !(0.0..=1.0).contains(r) {
+                        return Err(
+                            self.error(format!("conductor roughness {} is not between 0 and 1", r))
+                        );
+                    }
+                }
+                let (eta, k) = match (preset, eta, k) {
+                    (Some(preset), None, None) => preset.get_ior(),
+                    (None, Some(eta), Some(k)) => (to_color(*eta), to_color(*k)),
+                    _ => {
+                        return Err(self.error(format!(
+                            "conductor '{}' needs either a preset or both eta and k",
+                            name
+                        )))
+                    }
+                };
+                match (roughness_across, brush_direction) {
+                    (None, None) => ConductorMaterial::new(eta, k, *roughness),
+                    (Some(roughness_across), Some(brush_direction))
+                        if to_vector(*brush_direction).magnitude2() > 0.0 =>
+                    {
+                        ConductorMaterial::new_brushed(
+                            eta,
+                            k,
+                            *roughness,
+                            *roughness_across,
+                            to_vector(*brush_direction),
+                        )
+                    }
+                    _ => {
+                        return Err(self.error(format!(
+                            "brushed conductor '{}' needs both roughness_across and a nonzero brush_direction",
+                            name
+                        )))
+                    }
+                }
+            }
+            MaterialDescription::DiffuseLight { emit } => {
+                DiffuseLight::new(self.build_texture_reference(emit, 0)?)
+            }
+            MaterialDescription::Isotropic { albedo } => {
+                MediumMaterial::new_isotropic(self.build_texture_reference(albedo, 0)?)
+            }
+            MaterialDescription::Medium {
+                albedo,
+                phase_function,
+            } => MediumMaterial::new(
+                self.build_texture_reference(albedo, 0)?,
+                phase_function
+                    .to_phase_function()
+                    .map_err(|e| self.error(e))?,
+            ),
+            MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                clearcoat,
+                clearcoat_gloss,
+                transmission,
+                refraction_index,
+            } => {
+                if *refraction_index < 1.0 {
+                    return Err(self.error(format!(
+                        "principled refraction index {} is below 1",
+                        refraction_index
+                    )));
+                }
+                let defaults = PrincipledParameters::default();
+                PrincipledMaterial::new(PrincipledParameters {
+                    base_color: self.build_texture_reference(base_color, 0)?,
+                    metallic: self.build_scalar_reference(metallic, defaults.metallic)?,
+                    roughness: self.build_scalar_reference(roughness, defaults.roughness)?,
+                    specular: self.build_scalar_reference(specular, defaults.specular)?,
+                    specular_tint: self
+                        .build_scalar_reference(specular_tint, defaults.specular_tint)?,
+                    sheen: self.build_scalar_reference(sheen, defaults.sheen)?,
+                    clearcoat: self.build_scalar_reference(clearcoat, defaults.clearcoat)?,
+                    clearcoat_gloss: self
+                        .build_scalar_reference(clearcoat_gloss, defaults.clearcoat_gloss)?,
+                    transmission: self
+                        .build_scalar_reference(transmission, defaults.transmission)?,
+                    refraction_index: *refraction_index,
+                })
+            }
+            MaterialDescription::Layered {
+                base,
+                refraction_index,
+                roughness,
+                absorption_color,
+            } => {
+                if *refraction_index < 1.0 {
+                    return Err(self.error(format!(
+                        "coat refraction index {} is below 1",
+                        refraction_index
+                    )));
+                }
+                if !(0.0..=1.0).contains(roughness) {
+                    return Err(self.error(format!(
+                        "coat roughness {} is not between 0 and 1",
+                        roughness
+                    )));
+                }
+                LayeredMaterial::new(
+                    self.build_material(name, base)?,
+                    *refraction_index,
+                    *roughness,
+                    to_color(*absorption_color),
+                )
+            }
+        })
+    }
+
+    fn get_material_id(&self, name: &str) -> Result<u64, SceneLoadError> {
+        match self.material_ids.get(name) {
+            Some(id) => Ok(*id),
+            None => Err(self.error(format!("unknown material '{}'", name))),
+        }
+    }
+
+    fn build_geometry(&mut self, name: &str) -> Result<ThreadHitable, SceneLoadError> {
+        if let Some(geometry) = self.geometry.get(name) {
+            return Ok(geometry.clone());
+        }
+
+        let description = self.description;
+        let hitables = match description.geometry.get(name) {
+            Some(h) => h,
+            None => return Err(self.error(format!("unknown geometry '{}'", name))),
+        };
+        if self.geometry_in_progress.iter().any(|n| n == name) {
+            return Err(self.error(format!("geometry '{}' instances itself", name)));
+        }
+
+        self.geometry_in_progress.push(name.to_string());
+        let mut built = vec![];
+        for h in hitables.iter() {
+            built.push(self.build_hitable(h)?);
+        }
+        self.geometry_in_progress.pop();
+
+        let geometry = if built.len() == 1 {
+            built.remove(0)
+        } else {
+            BvhBuilder::default().build(&built, 0.0, 1.0).0
+        };
+        self.geometry.insert(name.to_string(), geometry.clone());
+        Ok(geometry)
+    }
+
+    fn build_hitable(
+        &mut self,
+        hitable: &HitableDescription,
+    ) -> Result<ThreadHitable, SceneLoadError> {
+        Ok(match hitable {
+            HitableDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Sphere::new(to_vector(*center), *radius, self.get_material_id(material)?),
+            HitableDescription::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => MovingSphere::new(
+                to_vector(*center0),
+                to_vector(*center1),
+                *time0,
+                *time1,
+                *radius,
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => XyRect::new(*x0, *x1, *y0, *y1, *k, self.get_material_id(material)?),
+            HitableDescription::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => XzRect::new(*x0, *x1, *z0, *z1, *k, self.get_material_id(material)?),
+            HitableDescription::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => YzRect::new(*y0, *y1, *z0, *z1, *k, self.get_material_id(material)?),
+            HitableDescription::Cube { min, max, material } => Cube::new(
+                to_vector(*min),
+                to_vector(*max),
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Cylinder {
+                base,
+                top,
+                radius,
+                material,
+                cap_material,
+            } => Cylinder::new(
+                to_vector(*base),
+                to_vector(*top),
+                *radius,
+                self.get_material_id(material)?,
+                self.get_material_id(cap_material)?,
+            ),
+            HitableDescription::Cone {
+                base,
+                base_radius,
+                apex,
+                apex_radius,
+                capped,
+                phi_max,
+                material,
+            } => Cone::new(
+                to_vector(*base),
+                *base_radius,
+                to_vector(*apex),
+                *apex_radius,
+                *capped,
+                *phi_max,
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Disk {
+                height,
+                radius,
+                inner_radius,
+                phi_max,
+                material,
+            } => Disk::new(
+                *height,
+                *radius,
+                *inner_radius,
+                *phi_max,
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Paraboloid {
+                radius,
+                z_min,
+                z_max,
+                phi_max,
+                material,
+            } => {
+                if *z_max <= 0.0 {
+                    return Err(self.error("paraboloid needs a positive z_max".to_string()));
+                }
+                Paraboloid::new(
+                    *radius,
+                    *z_min,
+                    *z_max,
+                    *phi_max,
+                    self.get_material_id(material)?,
+                )
+            }
+            HitableDescription::Hyperboloid {
+                p1,
+                p2,
+                phi_max,
+                material,
+            } => {
+                if p1[2] == p2[2] {
+                    return Err(self
+                        .error("hyperboloid ends are at the same height, use a disk".to_string()));
+                }
+                Hyperboloid::new(
+                    to_vector(*p1),
+                    to_vector(*p2),
+                    *phi_max,
+                    self.get_material_id(material)?,
+                )
+            }
+            HitableDescription::Torus {
+                major_radius,
+                minor_radius,
+                phi_max,
+                material,
+            } => Torus::new(
+                *major_radius,
+                *minor_radius,
+                *phi_max,
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Triangle { vertices, material } => Triangle::new(
+                vertices.iter().map(|v| to_vector(*v)).collect(),
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Parallelogram {
+                origin,
+                edge_u,
+                edge_v,
+                material,
+            } => Parallelogram::new(
+                to_vector(*origin),
+                to_vector(*edge_u),
+                to_vector(*edge_v),
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Mesh {
+                file,
+                material,
+                translation,
+                angle_y,
+                scale,
+            } => {
+                if *scale == 0.0 {
+                    return Err(self.error(format!("mesh '{}' has a zero scale", file)));
+                }
+                let path = self.get_file_path(file);
+                let mesh = if path.to_lowercase().ends_with(".stl") {
+                    load_stl_file(&path)?
+                } else {
+                    load_ply_file(&path)?
+                };
+                if mesh.faces.is_empty() {
+                    return Err(self.error(format!("mesh '{}' has no faces", file)));
+                }
+
+                let placement = MeshPlacement::new(to_vector(*translation), *angle_y, *scale);
+                match material {
+                    Some(name) => mesh.to_hitable(self.get_material_id(name)?, &placement),
+                    None => mesh.to_vertex_colored_hitable(
+                        &mut self.materials,
+                        &placement,
+                        Color::new(0.8, 0.8, 0.8),
+                    ),
+                }
+            }
+            HitableDescription::Translate { .. }
+            | HitableDescription::RotateX { .. }
+            | HitableDescription::RotateY { .. }
+            | HitableDescription::RotateZ { .. }
+            | HitableDescription::Scale { .. }
+            | HitableDescription::Transform { .. } => {
+                // nested placements collapse into a single Transform
+                let mut matrix = Matrix4::identity();
+                let mut inner = hitable;
+                while let Some((step, next)) = get_placement(inner) {
+                    matrix = matrix * step;
+                    inner = next;
+                }
+                if matrix.invert().is_none() {
+                    return Err(self.error("a transform can't be inverted".to_string()));
+                }
+                Transform::new(self.build_hitable(inner)?, matrix)
+            }
+            HitableDescription::Keyframed { keyframes, hitable } => {
+                if keyframes.is_empty() {
+                    return Err(self.error("keyframed hitable has no keyframes".to_string()));
+                }
+                let mut built = vec![];
+                for keyframe in keyframes.iter() {
+                    let transform = &keyframe.transform;
+                    if transform.matrix.is_some() {
+                        return Err(self.error(format!(
+                            "keyframe at time {} has a matrix, keyframes only take translation, rotation and scale",
+                            keyframe.time
+                        )));
+                    }
+                    if transform.scale.contains(&0.0) {
+                        return Err(self.error(format!(
+                            "keyframe at time {} has a zero scale",
+                            keyframe.time
+                        )));
+                    }
+                    built.push(TransformKeyframe::new(
+                        keyframe.time,
+                        to_vector(transform.translation),
+                        transform.to_rotation(),
+                        to_vector(transform.scale),
+                    ));
+                }
+                KeyframedTransform::new(self.build_hitable(hitable)?, built)
+            }
+            HitableDescription::FlipNormals { hitable } => {
+                FlipNormals::new(self.build_hitable(hitable)?)
+            }
+            HitableDescription::ConstantMedium {
+                density,
+                albedo,
+                phase_function,
+                boundary,
+            } => {
+                let boundary = self.build_hitable(boundary)?;
+                let texture = self.build_texture_reference(albedo, 0)?;
+                let phase_function = phase_function
+                    .to_phase_function()
+                    .map_err(|e| self.error(e))?;
+                ConstantMedium::new(
+                    boundary,
+                    *density,
+                    texture,
+                    phase_function,
+                    &mut self.materials,
+                )
+            }
+            HitableDescription::GridMedium {
+                grid,
+                min,
+                max,
+                absorption,
+                scattering,
+                albedo,
+                phase_function,
+            } => {
+                let grid = match grid {
+                    DensityGridDescription::File { file, resolution } => {
+                        load_density_grid_file(&self.get_file_path(file), *resolution)?
+                    }
+                    DensityGridDescription::Noise {
+                        resolution,
+                        frequency,
+                    } => {
+                        if resolution.iter().any(|n| *n < 2) {
+                            return Err(self.error(format!(
+                                "noise density grid is {:?}, it needs at least 2 samples along each axis",
+                                resolution
+                            )));
+                        }
+                        DensityGrid::from_noise(*resolution, *frequency)
+                    }
+                };
+                if *absorption < 0.0 || *scattering < 0.0 {
+                    return Err(self.error(
+                        "grid medium absorption and scattering can't be negative".to_string(),
+                    ));
+                }
+                GridMedium::new(
+                    grid,
+                    to_vector(*min),
+                    to_vector(*max),
+                    *absorption,
+                    *scattering,
+                    Color::new(albedo[0], albedo[1], albedo[2]),
+                    phase_function
+                        .to_phase_function()
+                        .map_err(|e| self.error(e))?,
+                    &mut self.materials,
+                )
+            }
+            HitableDescription::List { hitables } => {
+                let mut list = vec![];
+                for h in hitables.iter() {
+                    list.push(self.build_hitable(h)?);
+                }
+                HitableList::from_vec(list)
+            }
+            HitableDescription::Heightfield {
+                heights,
+                min,
+                max,
+                material,
+            } => {
+                let map = match heights {
+                    HeightMapDescription::Image { file } => {
+                        let path = self.get_file_path(file);
+                        HeightMap::from_image_file(&path).map_err(|e| SceneLoadError::Io {
+                            path: path.clone(),
+                            description: e.to_string(),
+                        })?
+                    }
+                    HeightMapDescription::Perlin {
+                        width,
+                        depth,
+                        frequency,
+                        octaves,
+                    } => {
+                        if *width < 2 || *depth < 2 {
+                            return Err(self.error(format!(
+                                "perlin height map is {} by {}, it needs at least 2 by 2",
+                                width, depth
+                            )));
+                        }
+                        HeightMap::from_perlin(*width, *depth, *frequency, *octaves)
+                    }
+                };
+                Heightfield::new(
+                    map,
+                    to_vector(*min),
+                    to_vector(*max),
+                    self.get_material_id(material)?,
+                )
+            }
+            HitableDescription::Sdf {
+                node,
+                min,
+                max,
+                material,
+            } => Sdf::new(
+                node.to_node().map_err(|e| self.error(e))?,
+                to_vector(*min),
+                to_vector(*max),
+                self.get_material_id(material)?,
+            ),
+            HitableDescription::Csg {
+                operation,
+                left,
+                right,
+            } => Csg::new(
+                *operation,
+                self.build_hitable(left)?,
+                self.build_hitable(right)?,
+            ),
+            HitableDescription::Instance {
+                geometry,
+                transform,
+                material,
+            } => {
+                let matrix = transform.to_matrix();
+                if matrix.invert().is_none() {
+                    return Err(self.error(format!(
+                        "instance of '{}' has a transform that can't be inverted",
+                        geometry
+                    )));
+                }
+                let material_id = match material {
+                    Some(name) => Some(self.get_material_id(name)?),
+                    None => None,
+                };
+                Instance::new(self.build_geometry(geometry)?, matrix, material_id)
+            }
+        })
+    }
+}
+
+// The matrix of a placement wrapper and what it wraps.
+fn get_placement(hitable: &HitableDescription) -> Option<(Matrix4<f32>, &HitableDescription)> {
+    match hitable {
+        HitableDescription::Translate { offset, hitable } => {
+            Some((Matrix4::from_translation(to_vector(*offset)), hitable))
+        }
+        HitableDescription::RotateX { angle, hitable } => {
+            Some((Matrix4::from_angle_x(Deg(*angle)), hitable))
+        }
+        HitableDescription::RotateY { angle, hitable } => {
+            Some((Matrix4::from_angle_y(Deg(*angle)), hitable))
+        }
+        HitableDescription::RotateZ { angle, hitable } => {
+            Some((Matrix4::from_angle_z(Deg(*angle)), hitable))
+        }
+        HitableDescription::Scale { factor, hitable } => {
+            Some((Matrix4::from_scale(*factor), hitable))
+        }
+        HitableDescription::Transform { transform, hitable } => {
+            Some((transform.to_matrix(), hitable))
+        }
+        _ => None,
+    }
+}
+
+impl SceneDescription {
+    /// Reads a scene file, the format is picked from the extension (.toml or .json).
+    pub fn from_file(file_path: &str) -> Result<SceneDescription, SceneLoadError> {
+        let contents =
+            fs::read_to_string(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+        if is_toml_file(file_path) {
+            SceneDescription::from_toml(file_path, &contents)
+        } else {
+            SceneDescription::from_json(file_path, &contents)
+        }
+    }
+
+    pub fn from_json(file_path: &str, contents: &str) -> Result<SceneDescription, SceneLoadError> {
+        serde_json::from_str(contents).map_err(|e| SceneLoadError::Syntax {
+            path: file_path.to_string(),
+            line: e.line(),
+            column: e.column(),
+            token: String::new(),
+            description: e.to_string(),
+        })
+    }
+
+    pub fn from_toml(file_path: &str, contents: &str) -> Result<SceneDescription, SceneLoadError> {
+        toml::from_str(contents).map_err(|e| {
+            let offset = e.span().map_or(0, |span| span.start);
+            let before = &contents[..offset];
+            let line = before.matches('\n').count() + 1;
+            let column = offset - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            SceneLoadError::Syntax {
+                path: file_path.to_string(),
+                line,
+                column,
+                token: String::new(),
+                description: e.message().to_string(),
+            }
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the scene, the format is picked from the extension (.toml or .json).
+    pub fn write_to_file(&self, file_path: &str) -> Result<(), SceneLoadError> {
+        let contents = if is_toml_file(file_path) {
+            self.to_toml()
+        } else {
+            self.to_json()
+        };
+        fs::write(file_path, contents).map_err(|e| SceneLoadError::io(file_path, &e))
+    }
+
+    /// Builds the materials and hitables.  `file_path` is where the description came
+    /// from, files it refers to are looked up next to it.
+    pub fn compile(&self, file_path: &str) -> Result<CompiledSceneDescription, SceneLoadError> {
+        let directory = Path::new(file_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."));
+        let mut builder = SceneBuilder {
+            description: self,
+            file_path,
+            directory,
+            materials: CompiledMaterials::new(),
+            material_ids: HashMap::new(),
+            textures: HashMap::new(),
+            geometry: HashMap::new(),
+            geometry_in_progress: vec![],
+        };
+        builder.build_materials()?;
+
+        let mut hitables = vec![];
+        let mut lights = vec![];
+        for light in self.lights.iter() {
+            let hitable = builder.build_hitable(light)?;
+            lights.push(hitable.clone());
+            hitables.push(hitable);
+        }
+        for hitable in self.hitables.iter() {
+            hitables.push(builder.build_hitable(hitable)?);
+        }
+
+        let light_hitable = if lights.len() == 1 {
+            lights.remove(0)
+        } else {
+            HitableList::from_vec(lights)
+        };
+
+        Ok(CompiledSceneDescription {
+            hitables,
+            materials: builder.materials,
+            light_hitable,
+        })
+    }
+
+    pub fn get_background_color(&self) -> Color {
+        to_color(self.background)
+    }
+}
+
+fn is_toml_file(file_path: &str) -> bool {
+    file_path.to_lowercase().ends_with(".toml")
+}
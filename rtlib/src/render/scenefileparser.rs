@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::cameras::ThreadCamera;
+use crate::hitables::{BvhBuilder, ThreadHitable};
+use crate::materials::CompiledMaterials;
+use crate::render::*;
+
+// Loads a .json or .toml scene file (see SceneDescription).
+pub struct SceneFileParser {
+    description: SceneDescription,
+    render_config: RenderConfig,
+
+    hitables: Vec<ThreadHitable>,
+    materials: Arc<Box<CompiledMaterials>>,
+    light_hitable: ThreadHitable,
+}
+
+impl SceneFileParser {
+    pub fn new(
+        file_path: &str,
+        render_config: &RenderConfig,
+    ) -> Result<Arc<Box<dyn SceneGenerator + Send>>, SceneLoadError> {
+        let description = SceneDescription::from_file(file_path)?;
+        let compiled = description.compile(file_path)?;
+        let render_config = description
+            .render_config
+            .clone()
+            .unwrap_or_else(|| render_config.clone());
+
+        Ok(Arc::new(Box::new(SceneFileParser {
+            description,
+            render_config,
+            hitables: compiled.hitables,
+            materials: Arc::new(Box::new(compiled.materials)),
+            light_hitable: compiled.light_hitable,
+        })))
+    }
+}
+
+impl SceneGenerator for SceneFileParser {
+    fn get_scene(&self) -> Scene {
+        create_scene(
+            &self.hitables,
+            self.materials.clone(),
+            &self.light_hitable,
+            self.get_background_color(),
+            &BvhBuilder::default(),
+        )
+    }
+
+    fn get_camera(&self) -> ThreadCamera {
+        self.get_camera_angled(0.0, 0.0)
+    }
+
+    fn get_camera_angled(&self, angle_x: f32, angle_y: f32) -> ThreadCamera {
+        self.description.camera.to_camera(
+            self.render_config.width as f32 / self.render_config.height as f32,
+            angle_x,
+            angle_y,
+        )
+    }
+
+    fn get_render_config(&self) -> RenderConfig {
+        self.render_config.clone()
+    }
+
+    fn get_background_color(&self) -> Color {
+        self.description.get_background_color()
+    }
+
+    fn get_description(&self) -> Option<SceneDescription> {
+        Some(self.description.clone())
+    }
+}
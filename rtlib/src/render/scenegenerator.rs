@@ -1,7 +1,7 @@
 use crate::cameras::ThreadCamera;
-use crate::hitables::{BvhNode, HitableList, ThreadHitable};
+use crate::hitables::{BvhBuilder, ThreadHitable};
 use crate::materials::CompiledMaterials;
-use crate::render::{Color, RenderConfig, Scene};
+use crate::render::{Color, RenderConfig, Scene, SceneDescription, SceneLoadError};
 use std::sync::Arc;
 
 pub trait SceneGenerator: Sync {
@@ -10,23 +10,34 @@ pub trait SceneGenerator: Sync {
     fn get_camera_angled(&self, angle_x: f32, angle_y: f32) -> ThreadCamera;
     fn get_render_config(&self) -> RenderConfig;
     fn get_background_color(&self) -> Color;
+
+    /// The scene in its file form, for generators that can be written out as a scene file.
+    fn get_description(&self) -> Option<SceneDescription> {
+        None
+    }
+
+    /// Writes the description, with the render config, as a TOML or JSON scene file
+    /// depending on the extension of `file_path`.
+    fn write_scene_file(&self, file_path: &str) -> Result<(), SceneLoadError> {
+        let mut description = self.get_description().ok_or_else(|| {
+            SceneLoadError::invalid_data(file_path, "this scene can't be written as a scene file")
+        })?;
+        description.render_config = Some(self.get_render_config());
+        description.write_to_file(file_path)
+    }
 }
 
 pub fn create_scene(
-    hitables: &Vec<ThreadHitable>,
+    hitables: &[ThreadHitable],
     materials: Arc<Box<CompiledMaterials>>,
     light_hitable: &ThreadHitable,
     background_color: Color,
-    use_bvh: bool,
+    bvh_builder: &BvhBuilder,
 ) -> Scene {
-    let mut hitables = hitables.clone();
-    let world = if use_bvh {
-        let bvh_world = BvhNode::new(&mut hitables, 0.0, 0.0);
-        info!("BVH WORLD: {}", bvh_world);
-        bvh_world
-    } else {
-        HitableList::from_vec(hitables)
-    };
+    let (world, bvh_build_stats) = bvh_builder.build(hitables, 0.0, 0.0);
+    if let Some(stats) = &bvh_build_stats {
+        info!("BVH ({:?}): {}", bvh_builder.get_strategy(), stats);
+    }
 
     Scene::new(
         world,
@@ -34,4 +45,5 @@ pub fn create_scene(
         materials.clone(),
         background_color,
     )
+    .with_bvh_build_stats(bvh_build_stats)
 }
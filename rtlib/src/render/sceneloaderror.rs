@@ -0,0 +1,184 @@
+use crate::{vec3, Vector3};
+use std::str::FromStr;
+use std::{error, fmt, io};
+
+/// Why a scene or mesh file could not be loaded.  Line and column numbers are 1 based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneLoadError {
+    /// The file could not be opened or read.
+    Io { path: String, description: String },
+    /// A token on a line could not be understood.
+    Syntax {
+        path: String,
+        line: usize,
+        column: usize,
+        token: String,
+        description: String,
+    },
+    /// The file ended (or the next statement started) before a statement was complete.
+    Truncated {
+        path: String,
+        line: usize,
+        description: String,
+    },
+    /// Binary content that doesn't follow the format.
+    InvalidData { path: String, description: String },
+}
+
+impl SceneLoadError {
+    pub fn io(path: &str, err: &io::Error) -> SceneLoadError {
+        SceneLoadError::Io {
+            path: path.to_string(),
+            description: err.to_string(),
+        }
+    }
+
+    pub fn truncated(path: &str, line: usize, description: &str) -> SceneLoadError {
+        SceneLoadError::Truncated {
+            path: path.to_string(),
+            line,
+            description: description.to_string(),
+        }
+    }
+
+    pub fn invalid_data(path: &str, description: &str) -> SceneLoadError {
+        SceneLoadError::InvalidData {
+            path: path.to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn get_path(&self) -> &str {
+        match self {
+            SceneLoadError::Io { path, .. }
+            | SceneLoadError::Syntax { path, .. }
+            | SceneLoadError::Truncated { path, .. }
+            | SceneLoadError::InvalidData { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for SceneLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneLoadError::Io { path, description } => write!(f, "{}: {}", path, description),
+            SceneLoadError::Syntax {
+                path,
+                line,
+                column,
+                token,
+                description,
+            } => {
+                if token.is_empty() {
+                    write!(f, "{}:{}:{}: {}", path, line, column, description)
+                } else {
+                    write!(
+                        f,
+                        "{}:{}:{}: {} (found '{}')",
+                        path, line, column, description, token
+                    )
+                }
+            }
+            SceneLoadError::Truncated {
+                path,
+                line,
+                description,
+            } => write!(f, "{}:{}: {}", path, line, description),
+            SceneLoadError::InvalidData { path, description } => {
+                write!(f, "{}: {}", path, description)
+            }
+        }
+    }
+}
+
+impl error::Error for SceneLoadError {}
+
+/// One line of a text scene file split into whitespace separated tokens, which knows
+/// where it came from so parse failures can point at the offending token.
+pub struct SceneLine<'a> {
+    path: &'a str,
+    number: usize,
+    text: &'a str,
+    tokens: Vec<&'a str>,
+}
+
+impl<'a> SceneLine<'a> {
+    pub fn new(path: &'a str, number: usize, text: &'a str) -> SceneLine<'a> {
+        SceneLine {
+            path,
+            number,
+            text,
+            tokens: text.split_whitespace().collect(),
+        }
+    }
+
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    pub fn get_number(&self) -> usize {
+        self.number
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn tokens(&self) -> &[&'a str] {
+        &self.tokens
+    }
+
+    pub fn get_token(&self, index: usize, what: &str) -> Result<&'a str, SceneLoadError> {
+        match self.tokens.get(index) {
+            Some(token) => Ok(token),
+            None => Err(self.error(index, &format!("expected {}", what))),
+        }
+    }
+
+    pub fn parse<T: FromStr>(&self, index: usize, what: &str) -> Result<T, SceneLoadError> {
+        let token = self.get_token(index, what)?;
+        token
+            .parse::<T>()
+            .map_err(|_| self.error(index, &format!("expected {}", what)))
+    }
+
+    pub fn get_f32(&self, index: usize) -> Result<f32, SceneLoadError> {
+        self.parse(index, "a number")
+    }
+
+    pub fn get_u32(&self, index: usize) -> Result<u32, SceneLoadError> {
+        self.parse(index, "a non-negative integer")
+    }
+
+    pub fn get_vector(&self, index: usize) -> Result<Vector3<f32>, SceneLoadError> {
+        Ok(vec3(
+            self.get_f32(index)?,
+            self.get_f32(index + 1)?,
+            self.get_f32(index + 2)?,
+        ))
+    }
+
+    /// A syntax error pointing at the token at `index`, or just past the end of the
+    /// line when the token is missing.
+    pub fn error(&self, index: usize, description: &str) -> SceneLoadError {
+        let (column, token) = match self.tokens.get(index) {
+            Some(token) => (
+                (token.as_ptr() as usize) - (self.text.as_ptr() as usize) + 1,
+                token.to_string(),
+            ),
+            None => (self.text.trim_end().len() + 1, String::new()),
+        };
+
+        SceneLoadError::Syntax {
+            path: self.path.to_string(),
+            line: self.number,
+            column,
+            token,
+            description: description.to_string(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::render::{LoadedMesh, SceneLine, SceneLoadError};
+use crate::{vec3, Vector3};
+use std::collections::HashMap;
+use std::fs;
+
+const STL_HEADER_SIZE: usize = 80;
+const STL_TRIANGLE_SIZE: usize = 50;
+
+// Loads a binary STL file, falling back to the ascii form when the size doesn't
+// match the binary layout and the file starts with "solid".  STL stores every
+// triangle separately so shared corners are welded back together, and the
+// per-facet normals are dropped since the triangles derive them from winding.
+pub fn load_stl_file(file_path: &str) -> Result<LoadedMesh, SceneLoadError> {
+    let data = fs::read(file_path).map_err(|e| SceneLoadError::io(file_path, &e))?;
+    let triangles = if is_binary_stl(&data) {
+        read_binary_triangles(&data)
+    } else if data.starts_with(b"solid") {
+        read_ascii_triangles(file_path, &data)?
+    } else {
+        return Err(SceneLoadError::invalid_data(
+            file_path,
+            "file is neither binary nor ascii STL",
+        ));
+    };
+
+    let mut mesh = LoadedMesh::default();
+    let mut vertex_lookup: HashMap<[u32; 3], u32> = HashMap::new();
+    for triangle in triangles {
+        let mut indices = [0_u32; 3];
+        for (index, v) in indices.iter_mut().zip(triangle.iter()) {
+            let key = [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+            let positions = &mut mesh.positions;
+            *index = *vertex_lookup.entry(key).or_insert_with(|| {
+                positions.push(*v);
+                (positions.len() - 1) as u32
+            });
+        }
+
+        // STL facets are counter clockwise, flip them to match Triangle
+        mesh.faces.push([indices[0], indices[2], indices[1]]);
+    }
+
+    Ok(mesh)
+}
+
+fn is_binary_stl(data: &[u8]) -> bool {
+    if data.len() < STL_HEADER_SIZE + 4 {
+        return false;
+    }
+    let count = read_u32(data, STL_HEADER_SIZE) as usize;
+    data.len() == STL_HEADER_SIZE + 4 + (count * STL_TRIANGLE_SIZE)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn read_vector(data: &[u8], pos: usize) -> Vector3<f32> {
+    vec3(
+        f32::from_bits(read_u32(data, pos)),
+        f32::from_bits(read_u32(data, pos + 4)),
+        f32::from_bits(read_u32(data, pos + 8)),
+    )
+}
+
+fn read_binary_triangles(data: &[u8]) -> Vec<[Vector3<f32>; 3]> {
+    let count = read_u32(data, STL_HEADER_SIZE) as usize;
+    (0..count)
+        .map(|i| {
+            // skip the 12 byte facet normal, the trailing attribute word is ignored
+            let pos = STL_HEADER_SIZE + 4 + (i * STL_TRIANGLE_SIZE) + 12;
+            [
+                read_vector(data, pos),
+                read_vector(data, pos + 12),
+                read_vector(data, pos + 24),
+            ]
+        })
+        .collect()
+}
+
+fn read_ascii_triangles(
+    file_path: &str,
+    data: &[u8],
+) -> Result<Vec<[Vector3<f32>; 3]>, SceneLoadError> {
+    let text = String::from_utf8_lossy(data);
+    let mut triangles = vec![];
+    let mut corners: Vec<Vector3<f32>> = vec![];
+    let mut line_count = 0;
+
+    for (num, text) in text.lines().enumerate() {
+        let line = SceneLine::new(file_path, num + 1, text);
+        line_count = num + 1;
+        match line.tokens().first() {
+            Some(&"vertex") => {
+                if corners.len() == 3 {
+                    return Err(line.error(0, "facet has more than 3 vertices"));
+                }
+                corners.push(line.get_vector(1)?);
+            }
+            Some(&"endfacet") => {
+                if corners.len() != 3 {
+                    return Err(line.error(0, "facet has fewer than 3 vertices"));
+                }
+                triangles.push([corners[0], corners[1], corners[2]]);
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+
+    if !corners.is_empty() {
+        return Err(SceneLoadError::truncated(
+            file_path,
+            line_count,
+            "file ends inside a facet",
+        ));
+    }
+
+    Ok(triangles)
+}
@@ -8,12 +8,27 @@ pub struct RenderStats {
     camera_ray_creates: u64,
     aabb_hits: u64,
     bvh_node_hits: u64,
+    cone_hits: u64,
+    csg_hits: u64,
     cube_hits: u64,
     cylinder_hits: u64,
+    disk_hits: u64,
+    grid_medium_hits: u64,
+    heightfield_hits: u64,
     hitable_list_hits: u64,
+    hyperboloid_hits: u64,
+    instance_hits: u64,
+    keyframed_transform_hits: u64,
     medium_hits: u64,
+    moving_sphere_hits: u64,
+    paraboloid_hits: u64,
+    parallelogram_hits: u64,
+    sdf_hits: u64,
     sphere_hits: u64,
+    torus_hits: u64,
+    transform_hits: u64,
     triangle_hits: u64,
+    triangle_mesh_hits: u64,
     xy_rect_hits: u64,
     xz_rect_hits: u64,
     yz_rect_hits: u64,
@@ -41,6 +56,16 @@ impl fmt::Display for RenderStats {
             "bvh_node_hits:        {}\n",
             self.bvh_node_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "cone_hits:            {}\n",
+            self.cone_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "csg_hits:             {}\n",
+            self.csg_hits.to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "cube_hits:            {}\n",
@@ -51,26 +76,92 @@ impl fmt::Display for RenderStats {
             "cylinder_hits:            {}\n",
             self.cylinder_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "disk_hits:            {}\n",
+            self.disk_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "grid_medium_hits:     {}\n",
+            self.grid_medium_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "heightfield_hits:     {}\n",
+            self.heightfield_hits.to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "hitable_list_hits:    {}\n",
             self.hitable_list_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "hyperboloid_hits:     {}\n",
+            self.hyperboloid_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "instance_hits:        {}\n",
+            self.instance_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "keyframed_transform_hits: {}\n",
+            self.keyframed_transform_hits
+                .to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "medium_hits:          {}\n",
             self.medium_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "moving_sphere_hits:   {}\n",
+            self.moving_sphere_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "paraboloid_hits:      {}\n",
+            self.paraboloid_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "parallelogram_hits:   {}\n",
+            self.parallelogram_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "sdf_hits:             {}\n",
+            self.sdf_hits.to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "sphere_hits:          {}\n",
             self.sphere_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "torus_hits:           {}\n",
+            self.torus_hits.to_formatted_string(&Locale::en)
+        )?;
+        write!(
+            f,
+            "transform_hits:       {}\n",
+            self.transform_hits.to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "triangle_hits:        {}\n",
             self.triangle_hits.to_formatted_string(&Locale::en)
         )?;
+        write!(
+            f,
+            "triangle_mesh_hits:   {}\n",
+            self.triangle_mesh_hits.to_formatted_string(&Locale::en)
+        )?;
         write!(
             f,
             "xy_rect_hits:         {}\n",
@@ -96,12 +187,27 @@ impl RenderStats {
             camera_ray_creates: 0,
             aabb_hits: 0,
             bvh_node_hits: 0,
+            cone_hits: 0,
+            csg_hits: 0,
             cube_hits: 0,
             cylinder_hits: 0,
+            disk_hits: 0,
+            grid_medium_hits: 0,
+            heightfield_hits: 0,
             hitable_list_hits: 0,
+            hyperboloid_hits: 0,
+            instance_hits: 0,
+            keyframed_transform_hits: 0,
             medium_hits: 0,
+            moving_sphere_hits: 0,
+            paraboloid_hits: 0,
+            parallelogram_hits: 0,
+            sdf_hits: 0,
             sphere_hits: 0,
+            torus_hits: 0,
+            transform_hits: 0,
             triangle_hits: 0,
+            triangle_mesh_hits: 0,
             xy_rect_hits: 0,
             xz_rect_hits: 0,
             yz_rect_hits: 0,
@@ -114,12 +220,28 @@ impl RenderStats {
             camera_ray_creates: self.camera_ray_creates + other.camera_ray_creates,
             aabb_hits: self.aabb_hits + other.aabb_hits,
             bvh_node_hits: self.bvh_node_hits + other.bvh_node_hits,
+            cone_hits: self.cone_hits + other.cone_hits,
+            csg_hits: self.csg_hits + other.csg_hits,
             cube_hits: self.cube_hits + other.cube_hits,
             cylinder_hits: self.cylinder_hits + other.cylinder_hits,
+            disk_hits: self.disk_hits + other.disk_hits,
+            grid_medium_hits: self.grid_medium_hits + other.grid_medium_hits,
+            heightfield_hits: self.heightfield_hits + other.heightfield_hits,
             hitable_list_hits: self.hitable_list_hits + other.hitable_list_hits,
+            hyperboloid_hits: self.hyperboloid_hits + other.hyperboloid_hits,
+            instance_hits: self.instance_hits + other.instance_hits,
+            keyframed_transform_hits: self.keyframed_transform_hits
+                + other.keyframed_transform_hits,
             medium_hits: self.medium_hits + other.medium_hits,
+            moving_sphere_hits: self.moving_sphere_hits + other.moving_sphere_hits,
+            paraboloid_hits: self.paraboloid_hits + other.paraboloid_hits,
+            parallelogram_hits: self.parallelogram_hits + other.parallelogram_hits,
+            sdf_hits: self.sdf_hits + other.sdf_hits,
             sphere_hits: self.sphere_hits + other.sphere_hits,
+            torus_hits: self.torus_hits + other.torus_hits,
+            transform_hits: self.transform_hits + other.transform_hits,
             triangle_hits: self.triangle_hits + other.triangle_hits,
+            triangle_mesh_hits: self.triangle_mesh_hits + other.triangle_mesh_hits,
             xy_rect_hits: self.xy_rect_hits + other.xy_rect_hits,
             xz_rect_hits: self.xz_rect_hits + other.xz_rect_hits,
             yz_rect_hits: self.yz_rect_hits + other.yz_rect_hits,
@@ -138,24 +260,69 @@ impl RenderStats {
     pub fn bvh_node_hit(&mut self) {
         self.bvh_node_hits += 1;
     }
+    pub fn cone_hit(&mut self) {
+        self.cone_hits += 1;
+    }
+    pub fn csg_hit(&mut self) {
+        self.csg_hits += 1;
+    }
     pub fn cube_hit(&mut self) {
         self.cube_hits += 1;
     }
     pub fn cylinder_hit(&mut self) {
         self.cylinder_hits += 1;
     }
+    pub fn disk_hit(&mut self) {
+        self.disk_hits += 1;
+    }
+    pub fn grid_medium_hit(&mut self) {
+        self.grid_medium_hits += 1;
+    }
+    pub fn heightfield_hit(&mut self) {
+        self.heightfield_hits += 1;
+    }
     pub fn hitable_list_hit(&mut self) {
         self.hitable_list_hits += 1;
     }
+    pub fn hyperboloid_hit(&mut self) {
+        self.hyperboloid_hits += 1;
+    }
+    pub fn instance_hit(&mut self) {
+        self.instance_hits += 1;
+    }
+    pub fn keyframed_transform_hit(&mut self) {
+        self.keyframed_transform_hits += 1;
+    }
     pub fn medium_hit(&mut self) {
         self.medium_hits += 1;
     }
+    pub fn moving_sphere_hit(&mut self) {
+        self.moving_sphere_hits += 1;
+    }
+    pub fn paraboloid_hit(&mut self) {
+        self.paraboloid_hits += 1;
+    }
+    pub fn parallelogram_hit(&mut self) {
+        self.parallelogram_hits += 1;
+    }
+    pub fn sdf_hit(&mut self) {
+        self.sdf_hits += 1;
+    }
     pub fn sphere_hit(&mut self) {
         self.sphere_hits += 1;
     }
+    pub fn torus_hit(&mut self) {
+        self.torus_hits += 1;
+    }
+    pub fn transform_hit(&mut self) {
+        self.transform_hits += 1;
+    }
     pub fn triangle_hit(&mut self) {
         self.triangle_hits += 1;
     }
+    pub fn triangle_mesh_hit(&mut self) {
+        self.triangle_mesh_hits += 1;
+    }
     pub fn xy_rect_hit(&mut self) {
         self.xy_rect_hits += 1;
     }
@@ -24,9 +24,13 @@ impl Texture for ImageTexture {
         let width = self.pixel_buffer.get_width() as i32;
         let height = self.pixel_buffer.get_height() as i32;
 
-        let i = clamp_i32_to_u32((uv_coords.x * (width as f32)) as i32, 0, width - 1);
+        // repeat the image outside of the 0..1 range, meshes often rely on tiling
+        let u = uv_coords.x - uv_coords.x.floor();
+        let v = uv_coords.y - uv_coords.y.floor();
+
+        let i = clamp_i32_to_u32((u * (width as f32)) as i32, 0, width - 1);
         let j = clamp_i32_to_u32(
-            ((1.0 - uv_coords.y * (height as f32)) - 0.001) as i32,
+            (((1.0 - v) * (height as f32)) - 0.001) as i32,
             0,
             height - 1,
         );
@@ -5,6 +5,7 @@ mod noisetexture;
 mod perlin;
 mod vectornoisetexture;
 mod vectorperlin;
+mod vertexcolortexture;
 
 pub use self::checkertexture::CheckerTexture;
 pub use self::colortexture::ColorTexture;
@@ -14,13 +15,20 @@ pub use self::perlin::perlin_noise;
 pub use self::vectornoisetexture::VectorNoiseMode;
 pub use self::vectornoisetexture::VectorNoiseTexture;
 pub use self::vectorperlin::{vector_perlin_noise, vector_perlin_turbulence};
+pub use self::vertexcolortexture::VertexColorTexture;
 
+use crate::hitables::HitRecord;
 use crate::render::Color;
 use crate::{Point2, Vector3};
 use std::sync::Arc;
 
 pub trait Texture: Sync {
     fn get_value(&self, uv_coords: Point2<f32>, p: Vector3<f32>) -> Color;
+    /// The value at a hit, for textures that need more of it than the uv coordinates
+    /// and the point.
+    fn get_hit_value(&self, hit_record: &HitRecord) -> Color {
+        self.get_value(hit_record.get_uv_coords(), hit_record.get_p())
+    }
 }
 
 pub type ThreadTexture = Arc<Box<dyn Texture + Send>>;
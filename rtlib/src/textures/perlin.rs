@@ -9,21 +9,17 @@ struct PerlinStaticData {
 }
 
 fn perlin_generate() -> Vec<f32> {
-    vec![next_rand_f32(); 256]
+    (0..256).map(|_| next_rand_f32()).collect()
 }
 
 fn perlin_generate_perm() -> Vec<usize> {
-    let mut p = vec![0_usize; 256];
-    for i in 0..p.len() - 1 {
-        p[i] = i;
-    }
-
+    let mut p: Vec<usize> = (0..256).collect();
     permute(&mut p);
     p
 }
 
 fn permute(p: &mut [usize]) {
-    for i in (1..p.len() - 1).rev() {
+    for i in (1..p.len()).rev() {
         let target = (next_rand_f32() * ((i + 1) as f32)) as usize;
         p.swap(i, target)
     }
@@ -41,7 +37,7 @@ lazy_static! {
 }
 
 pub fn perlin_noise(p: Vector3<f32>, interpolate: bool) -> f32 {
-    let mut u = p.x - p.y.floor();
+    let mut u = p.x - p.x.floor();
     let mut v = p.y - p.y.floor();
     let mut w = p.z - p.z.floor();
 
@@ -2,8 +2,10 @@ use crate::render::Color;
 use crate::textures::Texture;
 use crate::textures::{vector_perlin_noise, vector_perlin_turbulence, ThreadTexture};
 use crate::{Point2, Vector3};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum VectorNoiseMode {
     DarkNoise,
     DarkTurbulence,
@@ -0,0 +1,27 @@
+use crate::hitables::HitRecord;
+use crate::render::Color;
+use crate::textures::{Texture, ThreadTexture};
+use crate::{Point2, Vector3};
+use std::sync::Arc;
+
+// The vertex colors a mesh interpolated at the hit, or `default_color` for anything
+// without them.
+pub struct VertexColorTexture {
+    default_color: Color,
+}
+
+impl VertexColorTexture {
+    pub fn new(default_color: Color) -> ThreadTexture {
+        Arc::new(Box::new(VertexColorTexture { default_color }))
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn get_value(&self, _uv_coords: Point2<f32>, _p: Vector3<f32>) -> Color {
+        self.default_color
+    }
+
+    fn get_hit_value(&self, hit_record: &HitRecord) -> Color {
+        hit_record.get_vertex_color().unwrap_or(self.default_color)
+    }
+}
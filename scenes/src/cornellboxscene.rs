@@ -1,87 +1,200 @@
 use rtlib::cameras::{NormalCamera, ThreadCamera};
+use rtlib::hitables::BvhBuilder;
 use rtlib::render::create_scene;
-
-#[allow(unused_imports)]
-use rtlib::hitables::{
-    BvhNode, Cube, Cylinder, FlipNormals, HitableList, RotateX, RotateY, RotateZ, Sphere,
-    ThreadHitable, Translate, XyRect, XzRect, YzRect,
-};
-use rtlib::materials::{
-    CompiledMaterials, DialectricMaterial, DiffuseLight, LambertianMaterial, MetalMaterial,
-};
 use rtlib::render::Color;
-use rtlib::render::{RenderConfig, Scene, SceneGenerator};
-use rtlib::textures::{ColorTexture, VectorNoiseMode, VectorNoiseTexture};
+use rtlib::render::{
+    CameraDescription, HitableDescription, MaterialDescription, RenderConfig, Scene,
+    SceneDescription, SceneGenerator, TextureDescription, TextureReference,
+};
+use rtlib::textures::VectorNoiseMode;
 use rtlib::{vec3, Vector3};
+use std::collections::BTreeMap;
 use std::f32;
 use std::sync::Arc;
 
 pub struct CornellBoxScene {
     render_config: RenderConfig,
+    description: SceneDescription,
 }
 
 impl CornellBoxScene {
     pub fn new(render_config: &RenderConfig) -> Arc<Box<dyn SceneGenerator + Send>> {
         Arc::new(Box::new(CornellBoxScene {
             render_config: render_config.clone(),
+            description: create_description(),
         }))
     }
 }
 
+fn color(r: f32, g: f32, b: f32) -> TextureReference {
+    TextureReference::Inline(Box::new(TextureDescription::Color { color: [r, g, b] }))
+}
+
+fn lambertian(r: f32, g: f32, b: f32) -> MaterialDescription {
+    MaterialDescription::Lambertian {
+        albedo: color(r, g, b),
+    }
+}
+
+fn create_description() -> SceneDescription {
+    let mut materials = BTreeMap::new();
+    materials.insert(
+        "light".to_string(),
+        MaterialDescription::DiffuseLight {
+            emit: color(25.0, 25.0, 25.0),
+        },
+    );
+    materials.insert(
+        "glass".to_string(),
+        MaterialDescription::Dielectric {
+            refraction_index: 1.5,
+            roughness: 0.1,
+            absorption_color: [0.55, 0.8, 0.7],
+            absorption_distance: 180.0,
+        },
+    );
+    materials.insert("red".to_string(), lambertian(0.65, 0.05, 0.05));
+    materials.insert("white".to_string(), lambertian(0.73, 0.73, 0.73));
+    materials.insert(
+        "noise".to_string(),
+        MaterialDescription::Lambertian {
+            albedo: TextureReference::Inline(Box::new(TextureDescription::VectorNoise {
+                mode: VectorNoiseMode::DarkNoise,
+                scale: 0.1,
+            })),
+        },
+    );
+    materials.insert("yellow".to_string(), lambertian(1.0, 1.0, 0.0));
+    materials.insert("blue".to_string(), lambertian(0.05, 0.05, 0.73));
+    materials.insert("green".to_string(), lambertian(0.12, 0.45, 0.15));
+    materials.insert(
+        "aluminum".to_string(),
+        MaterialDescription::Metal {
+            albedo: [0.8, 0.85, 0.88],
+            fuzz: 0.0,
+        },
+    );
+
+    let flip = |hitable: HitableDescription| HitableDescription::FlipNormals {
+        hitable: Box::new(hitable),
+    };
+
+    let light_rect = flip(HitableDescription::XzRect {
+        x0: 213.0,
+        x1: 343.0,
+        z0: 227.0,
+        z1: 332.0,
+        k: 554.0,
+        material: "light".to_string(),
+    });
+
+    let glass_sphere = HitableDescription::Sphere {
+        center: [190.0, 90.0, 190.0],
+        radius: 90.0,
+        material: "glass".to_string(),
+    };
+
+    let cylinder = HitableDescription::Cylinder {
+        base: [0.0, 0.0, -50.0],
+        top: [0.0, 0.0, 50.0],
+        radius: 50.0,
+        material: "blue".to_string(),
+        cap_material: "yellow".to_string(),
+    };
+
+    let hitables = vec![
+        flip(HitableDescription::YzRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: "green".to_string(),
+        }),
+        HitableDescription::YzRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: "red".to_string(),
+        },
+        // top
+        flip(HitableDescription::XzRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: "white".to_string(),
+        }),
+        HitableDescription::XzRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: "white".to_string(),
+        },
+        flip(HitableDescription::XyRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: "noise".to_string(),
+        }),
+        HitableDescription::Translate {
+            offset: [265.0, 0.0, 295.0],
+            hitable: Box::new(HitableDescription::RotateY {
+                angle: 15.0,
+                hitable: Box::new(HitableDescription::Cube {
+                    min: [0.0, 0.0, 0.0],
+                    max: [165.0, 330.0, 165.0],
+                    material: "white".to_string(),
+                }),
+            }),
+        },
+        HitableDescription::Translate {
+            offset: [350.0, 100.0, 300.0],
+            hitable: Box::new(HitableDescription::RotateY {
+                angle: 35.0,
+                hitable: Box::new(cylinder),
+            }),
+        },
+        glass_sphere,
+    ];
+
+    SceneDescription {
+        render_config: None,
+        camera: CameraDescription {
+            look_from: [278.0, 278.0, -800.0],
+            look_at: [278.0, 278.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            vertical_fov: 40.0,
+            aperture: 0.0,
+            focus_distance: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        background: [0.1, 0.1, 0.1],
+        textures: BTreeMap::new(),
+        materials,
+        geometry: BTreeMap::new(),
+        lights: vec![light_rect],
+        hitables,
+    }
+}
+
 impl SceneGenerator for CornellBoxScene {
     fn get_scene(&self) -> Scene {
-        let mut materials: CompiledMaterials = CompiledMaterials::new();
-
-        let light_material = materials.add(DiffuseLight::new(ColorTexture::new(25.0, 25.0, 25.0)));
-
-        let glass = materials.add(DialectricMaterial::new(1.5));
-        let red = materials.add(LambertianMaterial::new(ColorTexture::new(0.65, 0.05, 0.05)));
-        let white = materials.add(LambertianMaterial::new(ColorTexture::new(0.73, 0.73, 0.73)));
-        let noise = materials.add(LambertianMaterial::new(VectorNoiseTexture::new(
-            VectorNoiseMode::DarkNoise,
-            0.1,
-        )));
-        let yellow = materials.add(LambertianMaterial::new(ColorTexture::new(1.0, 1.0, 0.0)));
-        let blue = materials.add(LambertianMaterial::new(ColorTexture::new(0.05, 0.05, 0.73)));
-        let green = materials.add(LambertianMaterial::new(ColorTexture::new(0.12, 0.45, 0.15)));
-        let _aluminum = materials.add(MetalMaterial::new(Color::new(0.8, 0.85, 0.88), 0.0));
-        let light_rect = FlipNormals::new(XzRect::new(
-            213.0,
-            343.0,
-            227.0,
-            332.0,
-            554.0,
-            light_material,
-        ));
-
-        let glass_sphere = Sphere::new(vec3(190.0, 90.0, 190.0), 90.0, glass);
-
-        let cylinder = Cylinder::new(vec3(50.0, 50.0, 50.0), 50.0, 50.0, blue, yellow);
-
-        let hitables = vec![
-            FlipNormals::new(YzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)),
-            YzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red),
-            light_rect.clone(),
-            FlipNormals::new(XzRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white)), // top
-            XzRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white),
-            FlipNormals::new(XyRect::new(0.0, 555.0, 0.0, 555.0, 555.0, noise)),
-            Translate::new(
-                RotateY::new(
-                    Cube::new(vec3(0.0, 0.0, 0.0), vec3(165.0, 330.0, 165.0), white),
-                    15.0,
-                ),
-                vec3(265.0, 0.0, 295.0),
-            ),
-            Translate::new(RotateY::new(cylinder, 35.0), vec3(350.0, 100.0, 300.0)),
-            glass_sphere,
-        ];
-
+        let compiled = self.description.compile("cornell_box").unwrap();
         create_scene(
-            &hitables,
-            Arc::new(Box::new(materials)),
-            &light_rect,
+            &compiled.hitables,
+            Arc::new(Box::new(compiled.materials)),
+            &compiled.light_hitable,
             self.get_background_color(),
-            true,
+            &BvhBuilder::default(),
         )
     }
 
@@ -123,6 +236,10 @@ impl SceneGenerator for CornellBoxScene {
     }
 
     fn get_background_color(&self) -> Color {
-        Color::new(0.1, 0.1, 0.1)
+        self.description.get_background_color()
+    }
+
+    fn get_description(&self) -> Option<SceneDescription> {
+        Some(self.description.clone())
     }
 }
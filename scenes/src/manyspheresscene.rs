@@ -1,137 +1,209 @@
-use rtlib::cameras::{NormalCamera, ThreadCamera};
-use rtlib::render::create_scene;
-
-#[allow(unused_imports)]
-use rtlib::hitables::{
-    BvhNode, Cube, FlipNormals, HitableList, RotateY, Sphere, ThreadHitable, Translate, XyRect,
-    XzRect, YzRect,
-};
-use rtlib::materials::{
-    CompiledMaterials, DialectricMaterial, DiffuseLight, LambertianMaterial, MetalMaterial,
-};
+use rtlib::cameras::ThreadCamera;
+use rtlib::hitables::BvhBuilder;
 use rtlib::next_rand_f32;
+use rtlib::render::create_scene;
 use rtlib::render::Color;
-use rtlib::render::{RenderConfig, Scene, SceneGenerator};
-use rtlib::textures::{
-    CheckerTexture, ColorTexture, NoiseTexture, VectorNoiseMode, VectorNoiseTexture,
+use rtlib::render::{
+    CameraDescription, HitableDescription, MaterialDescription, RenderConfig, Scene,
+    SceneDescription, SceneGenerator, TextureDescription, TextureReference,
 };
+use rtlib::textures::VectorNoiseMode;
 use rtlib::{vec3, InnerSpace, Vector3};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 pub struct ManySpheresScene {
     render_config: RenderConfig,
+    description: SceneDescription,
 }
 
 impl ManySpheresScene {
     pub fn new(render_config: &RenderConfig) -> Arc<Box<dyn SceneGenerator + Send>> {
         Arc::new(Box::new(ManySpheresScene {
             render_config: render_config.clone(),
+            description: create_description(),
         }))
     }
 }
 
-impl SceneGenerator for ManySpheresScene {
-    fn get_scene(&self) -> Scene {
-        let mut materials: CompiledMaterials = CompiledMaterials::new();
-
-        let light_material = materials.add(DiffuseLight::new(ColorTexture::new(15.0, 15.0, 15.0)));
-
-        let checker_texture = CheckerTexture::new(
-            ColorTexture::new(0.2, 0.3, 0.1),
-            ColorTexture::new(0.9, 0.9, 0.9),
-            vec3(10.0, 10.0, 10.0),
-        );
-        let lambertian_checker_mat = materials.add(LambertianMaterial::new(checker_texture));
-        let dialectric_mat = materials.add(DialectricMaterial::new(1.5));
-
-        let lamb_color_mat =
-            materials.add(LambertianMaterial::new(ColorTexture::new(0.4, 0.2, 0.1)));
-        let metal_color_mat = materials.add(MetalMaterial::new(Color::new(0.7, 0.6, 0.5), 0.0));
-
-        let mut hitables = vec![
-            Sphere::new(vec3(0.0, -1000.0, 0.0), 1000.0, lambertian_checker_mat),
-            Sphere::new(vec3(0.0, 1.0, 0.0), 1.0, dialectric_mat),
-            Sphere::new(vec3(-4.0, 1.0, 0.0), 1.0, lamb_color_mat),
-            Sphere::new(vec3(4.0, 1.0, 0.0), 1.0, metal_color_mat),
-        ];
-
-        for a in -11..11 {
-            for b in -11..11 {
-                let choose_mat = next_rand_f32();
-                let center = vec3(
-                    (a as f32) * next_rand_f32(),
-                    0.2,
-                    (b as f32) + (0.9 * next_rand_f32()),
-                );
-
-                if (center - vec3(4.0, 0.2, 0.0)).magnitude() > 0.9 {
-                    if choose_mat < 0.2 {
-                        // diffuse
-                        let rand_mat = materials.add(LambertianMaterial::new(ColorTexture::new(
-                            next_rand_f32() * next_rand_f32(),
-                            next_rand_f32() * next_rand_f32(),
-                            next_rand_f32() * next_rand_f32(),
-                        )));
-                        hitables.push(Sphere::new(center, 0.2, rand_mat));
-                    } else if choose_mat < 0.25 {
-                        // noise
-                        let noise_mat = materials.add(LambertianMaterial::new(NoiseTexture::new(
-                            true,
-                            next_rand_f32(),
-                        )));
-                        hitables.push(Sphere::new(center, 0.2, noise_mat));
-                    } else if choose_mat < 0.7 {
-                        // vector noise
-                        let vecnoise_mat =
-                            materials.add(LambertianMaterial::new(VectorNoiseTexture::new(
-                                VectorNoiseMode::DarkTurbulence,
-                                next_rand_f32() * 15.0,
-                            )));
-                        hitables.push(Sphere::new(center, 0.2, vecnoise_mat));
-                    } else if choose_mat < 0.9 {
-                        let metal_mat = materials.add(MetalMaterial::new(
-                            Color::new(
-                                0.5 * (1.0 + next_rand_f32()),
-                                0.5 * (1.0 + next_rand_f32()),
-                                0.5 * (1.0 + next_rand_f32()),
-                            ),
-                            0.5 * next_rand_f32(),
-                        ));
-                        hitables.push(Sphere::new(center, 0.2, metal_mat));
-                    } else {
-                        hitables.push(Sphere::new(center, 0.2, dialectric_mat));
+fn inline(texture: TextureDescription) -> TextureReference {
+    TextureReference::Inline(Box::new(texture))
+}
+
+fn sphere(center: Vector3<f32>, radius: f32, material: &str) -> HitableDescription {
+    HitableDescription::Sphere {
+        center: [center.x, center.y, center.z],
+        radius,
+        material: material.to_string(),
+    }
+}
+
+// The small spheres are placed randomly, so each description is a new arrangement.
+fn create_description() -> SceneDescription {
+    let mut materials = BTreeMap::new();
+    materials.insert(
+        "light".to_string(),
+        MaterialDescription::DiffuseLight {
+            emit: inline(TextureDescription::Color {
+                color: [15.0, 15.0, 15.0],
+            }),
+        },
+    );
+    materials.insert(
+        "checker".to_string(),
+        MaterialDescription::Lambertian {
+            albedo: inline(TextureDescription::Checker {
+                odd: inline(TextureDescription::Color {
+                    color: [0.2, 0.3, 0.1],
+                }),
+                even: inline(TextureDescription::Color {
+                    color: [0.9, 0.9, 0.9],
+                }),
+                scale: [10.0, 10.0, 10.0],
+            }),
+        },
+    );
+    materials.insert(
+        "glass".to_string(),
+        MaterialDescription::Dielectric {
+            refraction_index: 1.5,
+            roughness: 0.0,
+            absorption_color: [1.0, 1.0, 1.0],
+            absorption_distance: 1.0,
+        },
+    );
+    materials.insert(
+        "brown".to_string(),
+        MaterialDescription::Lambertian {
+            albedo: inline(TextureDescription::Color {
+                color: [0.4, 0.2, 0.1],
+            }),
+        },
+    );
+    materials.insert(
+        "metal".to_string(),
+        MaterialDescription::Metal {
+            albedo: [0.7, 0.6, 0.5],
+            fuzz: 0.0,
+        },
+    );
+
+    let mut hitables = vec![
+        sphere(vec3(0.0, -1000.0, 0.0), 1000.0, "checker"),
+        sphere(vec3(0.0, 1.0, 0.0), 1.0, "glass"),
+        sphere(vec3(-4.0, 1.0, 0.0), 1.0, "brown"),
+        sphere(vec3(4.0, 1.0, 0.0), 1.0, "metal"),
+    ];
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = next_rand_f32();
+            let center = vec3(
+                (a as f32) * next_rand_f32(),
+                0.2,
+                (b as f32) + (0.9 * next_rand_f32()),
+            );
+
+            if (center - vec3(4.0, 0.2, 0.0)).magnitude() > 0.9 {
+                let name = format!("sphere_{}_{}", a, b);
+                let material = if choose_mat < 0.2 {
+                    // diffuse
+                    MaterialDescription::Lambertian {
+                        albedo: inline(TextureDescription::Color {
+                            color: [
+                                next_rand_f32() * next_rand_f32(),
+                                next_rand_f32() * next_rand_f32(),
+                                next_rand_f32() * next_rand_f32(),
+                            ],
+                        }),
                     }
-                }
+                } else if choose_mat < 0.25 {
+                    // noise
+                    MaterialDescription::Lambertian {
+                        albedo: inline(TextureDescription::Noise {
+                            interpolate: true,
+                            scale: next_rand_f32(),
+                        }),
+                    }
+                } else if choose_mat < 0.7 {
+                    // vector noise
+                    MaterialDescription::Lambertian {
+                        albedo: inline(TextureDescription::VectorNoise {
+                            mode: VectorNoiseMode::DarkTurbulence,
+                            scale: next_rand_f32() * 15.0,
+                        }),
+                    }
+                } else if choose_mat < 0.9 {
+                    MaterialDescription::Metal {
+                        albedo: [
+                            0.5 * (1.0 + next_rand_f32()),
+                            0.5 * (1.0 + next_rand_f32()),
+                            0.5 * (1.0 + next_rand_f32()),
+                        ],
+                        fuzz: 0.5 * next_rand_f32(),
+                    }
+                } else {
+                    hitables.push(sphere(center, 0.2, "glass"));
+                    continue;
+                };
+
+                materials.insert(name.clone(), material);
+                hitables.push(sphere(center, 0.2, &name));
             }
         }
+    }
 
-        let light_rect = XzRect::new(-2.0, 2.0, -2.0, 2.0, 5.0, light_material);
-        hitables.push(light_rect.clone());
+    let light_rect = HitableDescription::XzRect {
+        x0: -2.0,
+        x1: 2.0,
+        z0: -2.0,
+        z1: 2.0,
+        k: 5.0,
+        material: "light".to_string(),
+    };
+
+    let look_from = vec3(24.0, 2.0, 6.0);
+    let look_at = Vector3::unit_y();
+
+    SceneDescription {
+        render_config: None,
+        camera: CameraDescription {
+            look_from: [look_from.x, look_from.y, look_from.z],
+            look_at: [look_at.x, look_at.y, look_at.z],
+            up: [0.0, 1.0, 0.0],
+            vertical_fov: 15.0,
+            aperture: 0.0,
+            focus_distance: (look_from - look_at).magnitude(),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        background: [0.2, 0.2, 0.2],
+        textures: BTreeMap::new(),
+        materials,
+        geometry: BTreeMap::new(),
+        lights: vec![light_rect],
+        hitables,
+    }
+}
 
+impl SceneGenerator for ManySpheresScene {
+    fn get_scene(&self) -> Scene {
+        let compiled = self.description.compile("many_spheres").unwrap();
         create_scene(
-            &hitables,
-            Arc::new(Box::new(materials)),
-            &light_rect,
+            &compiled.hitables,
+            Arc::new(Box::new(compiled.materials)),
+            &compiled.light_hitable,
             self.get_background_color(),
-            true,
+            &BvhBuilder::default(),
         )
     }
 
     fn get_camera(&self) -> ThreadCamera {
-        let look_from = vec3(24.0, 2.0, 6.0);
-        let look_at = Vector3::unit_y();
-        let dist_to_focus = (look_from - look_at).magnitude();
-        let aperture = 0.0;
-
-        Arc::new(Box::new(NormalCamera::new(
-            look_from,
-            look_at,
-            Vector3::unit_y(),
-            15.0,
+        self.description.camera.to_camera(
             self.render_config.width as f32 / self.render_config.height as f32,
-            aperture,
-            dist_to_focus,
-        )))
+            0.0,
+            0.0,
+        )
     }
 
     fn get_camera_angled(&self, _angle_x: f32, _angle_y: f32) -> ThreadCamera {
@@ -143,6 +215,10 @@ impl SceneGenerator for ManySpheresScene {
     }
 
     fn get_background_color(&self) -> Color {
-        Color::new(0.2, 0.2, 0.2)
+        self.description.get_background_color()
+    }
+
+    fn get_description(&self) -> Option<SceneDescription> {
+        Some(self.description.clone())
     }
 }